@@ -0,0 +1,102 @@
+// Imports
+use std::error::Error;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::thread;
+
+use crate::engine::*;
+use crate::errors::*;
+use crate::game::*;
+use crate::pgn::*;
+
+/// Columns a line of analysis is cut to, so redrawing in place never has wrapped lines
+const LINE_WIDTH: usize = 100;
+
+// Functions
+
+/// The position `arg` names: a FEN, or a PGN file or text whose game is played to its
+/// last move. A file holding a single FEN works too.
+pub fn load_position(arg: &str) -> Result<Game, Box<dyn Error>> {
+    let text = match Path::new(arg).is_file() {
+        true => fs::read_to_string(arg).map_err(|_| PgnError::Unreadable)?,
+        false => arg.to_owned(),
+    };
+    if let Ok(game) = Game::from_fen(text.trim()) {
+        return Ok(game);
+    }
+    let pgn: Pgn = text.parse()?;
+    let (mut game, moves) = pgn.replay()?;
+    for mv in moves {
+        game.play(mv)?;
+    }
+    Ok(game)
+}
+
+/// Searches `game` until Enter is pressed or `depth` is reached, showing the engine's
+/// `multi_pv` best lines after each depth. On a terminal the lines are redrawn in place.
+pub fn analyze(engine: &mut Engine, game: &Game, depth: u8) {
+    let in_place = io::stdout().is_terminal();
+    println!("{game}");
+    println!("{}", game.to_fen());
+    println!("Analyzing {} line(s), press Enter to stop", engine.multi_pv);
+
+    // Only a typed line stops, not the end of input, so piped runs go on to `depth`
+    let stop = engine.stop.clone();
+    thread::spawn(move || {
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line).is_ok_and(|read| read > 0) {
+            stop.store(true, Ordering::Relaxed);
+        }
+    });
+
+    let mut drawn = 0;
+    engine.search_with(game, SearchLimits::depth(depth), |info| {
+        let seconds = info.elapsed.as_secs_f64();
+        let nps = (info.nodes as f64 / seconds.max(0.001)) as u64;
+        let mut text = vec![format!("Depth {}  Nodes {}  NPS {nps}  Time {seconds:.1}s", info.depth, info.nodes)];
+        for (index, line) in info.lines.iter().enumerate() {
+            let row = format!("{:>3}. {:>7}  {}", index + 1, score_text(line.score), movetext(game, &line.pv));
+            text.push(cut(&row, LINE_WIDTH));
+        }
+
+        let mut stdout = io::stdout().lock();
+        if in_place && drawn > 0 {
+            // Back up over the last report and clear everything below the cursor
+            let _ = write!(stdout, "\x1b[{drawn}A\x1b[J");
+        }
+        for row in &text {
+            let _ = writeln!(stdout, "{row}");
+        }
+        let _ = stdout.flush();
+        drawn = text.len();
+    });
+}
+
+/// `+0.35` in pawns, or `#3` / `#-3` for mates, from the side to move's view
+pub fn score_text(score: i32) -> String {
+    match mate_in(score) {
+        Some(moves) => format!("#{moves}"),
+        None => format!("{:+.2}", score as f64 / 100.0),
+    }
+}
+
+/// `row` shortened to at most `width` characters, ending on a whole word
+pub fn cut(row: &str, width: usize) -> String {
+    if row.chars().count() <= width {
+        return row.to_owned();
+    }
+    let mut cut = String::new();
+    for word in row.split(' ') {
+        if cut.chars().count() + word.chars().count() + 4 > width {
+            break;
+        }
+        if !cut.is_empty() {
+            cut.push(' ');
+        }
+        cut.push_str(word);
+    }
+    cut.push_str(" ...");
+    cut
+}
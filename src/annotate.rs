@@ -0,0 +1,286 @@
+// Imports
+use std::fmt::Display;
+use std::fs;
+use std::io::{self, IsTerminal};
+
+use crate::analysis::*;
+use crate::engine::*;
+use crate::errors::*;
+use crate::moves::*;
+use crate::pgn::*;
+use crate::piece::*;
+use crate::variant::*;
+
+/// Depth each position is searched to when none is given
+pub const ANNOTATE_DEPTH: u8 = 8;
+/// Centipawns lost from the best move's score at which a move becomes an inaccuracy, a
+/// mistake or a blunder
+const INACCURACY_LOSS: i32 = 50;
+const MISTAKE_LOSS: i32 = 100;
+const BLUNDER_LOSS: i32 = 300;
+/// Scores are capped at this before comparing, so a slower win of a won game isn't a loss
+const SCORE_CAP: i32 = 1000;
+
+// Custom MoveClass Type Data
+
+/// How a move compares to the engine's best move
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MoveClass {
+    Best,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+// Custom PlayerSummary Type Data
+
+/// How well one side played over a game
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct PlayerSummary {
+    pub moves: u32,
+    pub inaccuracies: u32,
+    pub mistakes: u32,
+    pub blunders: u32,
+    /// Centipawns lost over every move, after capping scores
+    pub total_loss: i64,
+    accuracy_sum: f64,
+}
+
+// Custom MoveClass Type Traits
+
+impl Display for MoveClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveClass::Best => write!(f, "Best move"),
+            MoveClass::Good => write!(f, "Good move"),
+            MoveClass::Inaccuracy => write!(f, "Inaccuracy"),
+            MoveClass::Mistake => write!(f, "Mistake"),
+            MoveClass::Blunder => write!(f, "Blunder"),
+        }
+    }
+}
+
+// Custom PlayerSummary Type Traits
+
+impl Display for PlayerSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "accuracy {:.1}%, average centipawn loss {}, {} inaccuracies, {} mistakes, {} blunders",
+            self.accuracy(),
+            self.average_loss(),
+            self.inaccuracies,
+            self.mistakes,
+            self.blunders,
+        )
+    }
+}
+
+// Impl MoveClass
+
+impl MoveClass {
+    fn from_loss(loss: i32) -> MoveClass {
+        match loss {
+            BLUNDER_LOSS.. => MoveClass::Blunder,
+            MISTAKE_LOSS.. => MoveClass::Mistake,
+            INACCURACY_LOSS.. => MoveClass::Inaccuracy,
+            _ => MoveClass::Good,
+        }
+    }
+
+    /// The PGN glyph: `$6` for `?!`, `$2` for `?` and `$4` for `??`
+    pub fn nag(&self) -> Option<u8> {
+        match self {
+            MoveClass::Inaccuracy => Some(6),
+            MoveClass::Mistake => Some(2),
+            MoveClass::Blunder => Some(4),
+            _ => None,
+        }
+    }
+}
+
+// Impl PlayerSummary
+
+impl PlayerSummary {
+    /// Mean of each move's accuracy, 100% when every move kept the chance of winning
+    pub fn accuracy(&self) -> f64 {
+        match self.moves {
+            0 => 100.0,
+            moves => self.accuracy_sum / moves as f64,
+        }
+    }
+
+    pub fn average_loss(&self) -> i64 {
+        self.total_loss / self.moves.max(1) as i64
+    }
+
+    fn add(&mut self, class: MoveClass, loss: i32, accuracy: f64) {
+        self.moves += 1;
+        self.total_loss += loss as i64;
+        self.accuracy_sum += accuracy;
+        match class {
+            MoveClass::Inaccuracy => self.inaccuracies += 1,
+            MoveClass::Mistake => self.mistakes += 1,
+            MoveClass::Blunder => self.blunders += 1,
+            _ => {},
+        }
+    }
+}
+
+// Functions
+
+/// Searches every position of `pgn` to `depth` and returns the game annotated: each move
+/// gets its score as a `[%eval]` comment, and inaccuracies, mistakes and blunders get a
+/// glyph, a note of the better move and the engine's line as a variation. Also returns
+/// a summary per side, indexed White then Black. `report` is called as each position
+/// is done, with the count so far and the total.
+pub fn annotate<V: Variant>(
+    engine: &mut Engine,
+    pgn: &Pgn,
+    depth: u8,
+    mut report: impl FnMut(usize, usize),
+) -> Result<(Pgn, [PlayerSummary; 2]), PgnError> {
+    let (start, moves) = pgn.replay::<V>()?;
+    let mut positions = vec![start];
+    for &mv in &moves {
+        let mut next = *positions.last().unwrap();
+        next.play_unchecked(mv).map_err(|_| PgnError::IllegalMove(mv.to_string()))?;
+        positions.push(next);
+    }
+
+    // The best line and score of every position, for its side to move
+    engine.multi_pv = 1;
+    let mut searched: Vec<(Vec<Move>, i32)> = Vec::new();
+    for (index, position) in positions.iter().enumerate() {
+        let status = position.status();
+        let result = match (status.is_over(), status.winner()) {
+            (true, Some(winner)) if winner == position.turn => (Vec::new(), MATE_SCORE),
+            (true, Some(_)) => (Vec::new(), -MATE_SCORE),
+            (true, None) => (Vec::new(), 0),
+            (false, _) => {
+                let mut pv = Vec::new();
+                let (_, score) = engine.search_with(position, SearchLimits::depth(depth), |info| pv = info.pv.clone());
+                (pv, score)
+            },
+        };
+        searched.push(result);
+        report(index + 1, positions.len());
+    }
+
+    let mut annotated = Pgn { moves: Vec::new(), ..pgn.clone() };
+    annotated.set_tag("Annotator", "rust_chess");
+    let mut summaries = [PlayerSummary::default(); 2];
+    for (index, &mv) in moves.iter().enumerate() {
+        let position = &positions[index];
+        let (best_line, best_score) = &searched[index];
+        let best = best_line.first().copied();
+        // A ply less from the position after keeps the same horizon as the best move's score
+        let after = &positions[index + 1];
+        let played_score = match best == Some(mv) {
+            true => *best_score,
+            false if after.status().is_over() => -searched[index + 1].1,
+            false => -engine.search_with(after, SearchLimits::depth(depth.saturating_sub(1).max(1)), |_| {}).1,
+        };
+
+        let capped = |score: i32| score.clamp(-SCORE_CAP, SCORE_CAP);
+        let loss = (capped(*best_score) - capped(played_score)).max(0);
+        let class = match best == Some(mv) {
+            true => MoveClass::Best,
+            false => MoveClass::from_loss(loss),
+        };
+        let accuracy = move_accuracy(win_percent(capped(*best_score)), win_percent(capped(played_score)));
+        summaries[color_index(position.turn)].add(class, loss, accuracy);
+
+        // The score goes first, then any comment the game had, then the note on a mistake
+        let white_score = |score: i32| if position.turn == PieceColor::White { score } else { -score };
+        let mut comment = vec![format!("[%eval {}]", eval_text(white_score(played_score)))];
+        comment.extend(pgn.moves[index].comment.clone());
+        let mut variation = None;
+        if let (Some(_), Some(best)) = (class.nag(), best) {
+            comment.push(format!(
+                "({} → {}) {class}. {} was best.",
+                score_text(white_score(*best_score)),
+                score_text(white_score(played_score)),
+                san(position, best),
+            ));
+            variation = Some(movetext(position, best_line));
+        }
+        annotated.push(position, mv, Some(comment.join(" ")));
+        let pgn_move = annotated.moves.last_mut().unwrap();
+        pgn_move.nag = class.nag();
+        pgn_move.variations.extend(variation);
+    }
+    Ok((annotated, summaries))
+}
+
+/// Annotates every game of the PGN file at `path` in the variant its Variant tag names,
+/// writing them to `out` or else printing them, and prints each side's summary
+pub fn annotate_file(engine: &mut Engine, path: &str, depth: u8, out: Option<&str>) -> Result<(), PgnError> {
+    let text = fs::read_to_string(path).map_err(|_| PgnError::Unreadable)?;
+    let report = progress();
+    let mut annotated = String::new();
+    for pgn in read_games(&text)? {
+        let variant = pgn.tag("Variant").unwrap_or(Standard::NAME).to_owned();
+        let (pgn, summaries) = match variant.to_ascii_lowercase().as_str() {
+            "standard" | "chess960" => annotate::<Standard>(engine, &pgn, depth, report),
+            "king of the hill" => annotate::<KingOfTheHill>(engine, &pgn, depth, report),
+            "three-check" => annotate::<ThreeCheck>(engine, &pgn, depth, report),
+            "horde" => annotate::<Horde>(engine, &pgn, depth, report),
+            "antichess" => annotate::<Antichess>(engine, &pgn, depth, report),
+            "atomic" => annotate::<Atomic>(engine, &pgn, depth, report),
+            "racing kings" => annotate::<RacingKings>(engine, &pgn, depth, report),
+            "crazyhouse" => annotate::<Crazyhouse>(engine, &pgn, depth, report),
+            _ => Err(PgnError::UnsupportedVariant(variant)),
+        }?;
+        print_summaries(&pgn, &summaries);
+        annotated.push_str(&format!("{pgn}\n"));
+    }
+    match out {
+        Some(out) => fs::write(out, annotated).map_err(|_| PgnError::Unwritable),
+        None => {
+            print!("{annotated}");
+            Ok(())
+        },
+    }
+}
+
+/// Shows how many positions are searched on one line of a terminal, and nothing otherwise
+pub fn progress() -> impl Fn(usize, usize) + Copy {
+    let terminal = io::stderr().is_terminal();
+    move |done, total| {
+        if terminal {
+            eprint!("\rAnalyzed {done} of {total} positions");
+            if done == total {
+                eprintln!();
+            }
+        }
+    }
+}
+
+/// Prints a line per side with its name from the game's tags
+pub fn print_summaries(pgn: &Pgn, summaries: &[PlayerSummary; 2]) {
+    for (color, summary) in [PieceColor::White, PieceColor::Black].iter().zip(summaries) {
+        let name = pgn.tag(&color.to_string()).unwrap_or("?");
+        println!("{color} ({name}): {summary}");
+    }
+}
+
+/// Chance of winning, in percent, that a score in centipawns stands for, as fitted to
+/// the results of rated games
+pub fn win_percent(score: i32) -> f64 {
+    50.0 + 50.0 * (2.0 / (1.0 + (-0.00368208 * score as f64).exp()) - 1.0)
+}
+
+/// Accuracy of a move, from the win percentages before and after it
+fn move_accuracy(before: f64, after: f64) -> f64 {
+    (103.1668 * (-0.04354 * (before - after).max(0.0)).exp() - 3.1669).clamp(0.0, 100.0)
+}
+
+/// A score in pawns for `[%eval]` comments, or `#3` / `#-3` for mates
+fn eval_text(score: i32) -> String {
+    match mate_in(score) {
+        Some(moves) => format!("#{moves}"),
+        None => format!("{:.2}", score as f64 / 100.0),
+    }
+}
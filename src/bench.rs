@@ -0,0 +1,66 @@
+// Imports
+use std::time::{Duration, Instant};
+
+use crate::engine::*;
+use crate::fen::*;
+use crate::game::*;
+
+/// Depth `bench` searches to when none is given
+pub const BENCH_DEPTH: u8 = 5;
+
+/// Opening, middlegame and endgame positions, several of them from the perft suites
+const BENCH_FENS: [&str; 10] = [
+    STARTING_FEN,
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "2r3k1/pp3ppp/2n1b3/q2pP3/3P4/P1P2N2/5PPP/R2QR1K1 b - - 0 20",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "8/8/4k3/3p4/3P4/4K3/8/8 w - - 0 1",
+    "6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1",
+    "8/5pk1/6p1/8/3B4/5K2/8/8 b - - 0 50",
+];
+
+// Custom BenchResult Type Data
+
+/// Totals over every bench position. `nodes` is repeatable from run to run with one
+/// thread, so it tells whether a change altered the search at all.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BenchResult {
+    pub nodes: u64,
+    pub elapsed: Duration,
+}
+
+// Functions
+
+/// Searches each bench position to `depth` from an empty transposition table,
+/// printing the depth reached, nodes and speed of each and then the totals
+pub fn bench(engine: &mut Engine, depth: u8) -> BenchResult {
+    let mut result = BenchResult { nodes: 0, elapsed: Duration::ZERO };
+    for (index, fen) in BENCH_FENS.iter().enumerate() {
+        let game: Game = Game::from_fen(fen).expect("bench positions are valid FEN");
+        engine.tt.clear();
+
+        let start = Instant::now();
+        let mut reached = 0;
+        let (best, score) = engine.search_with(&game, SearchLimits::depth(depth), |info| reached = info.depth);
+        let elapsed = start.elapsed();
+        result.nodes += engine.nodes;
+        result.elapsed += elapsed;
+
+        let best = best.map_or("none".to_string(), |mv| game.uci_move(mv).to_string());
+        println!(
+            "Position {:>2}: depth {reached} best {best} score {score} nodes {} time {}ms",
+            index + 1,
+            engine.nodes,
+            elapsed.as_millis(),
+        );
+    }
+
+    let millis = result.elapsed.as_millis().max(1);
+    println!("Total time (ms) : {millis}");
+    println!("Nodes searched  : {}", result.nodes);
+    println!("Nodes/second    : {}", result.nodes as u128 * 1000 / millis);
+    result
+}
@@ -2,9 +2,17 @@
 use std::fmt::{Debug, Display};
 
 use crate::piece::*;
+use crate::render::*;
+use crate::square_set::*;
+
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [(-2, -1), (-2, 1), (-1, -2), (-1, 2), (1, -2), (1, 2), (2, -1), (2, 1)];
+const KING_OFFSETS: [(i8, i8); 8] = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
 
 // Custom Board Type Data
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Board {
     pub board: [[Option<Piece>; 8]; 8],
 }
@@ -14,33 +22,14 @@ pub struct Board {
 impl Default for Board {
     fn default() -> Self {
         Board { 
-            board: build_starting_board(), 
+            board: build_starting_board(CLASSICAL_BACK_RANK), 
         }
     }
 }
 
 impl Display for Board {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut row_num: i8 = 8;
-        let mut board_str: String = "   ┌───┬───┬───┬───┬───┬───┬───┬───┐\n".to_owned();
-        for row in &self.board {
-            let mut to_append: String = format!(" {row_num} │");
-            row_num -= 1;
-            for piece in row {
-                match piece {
-                    None => to_append.push_str("   │"),
-                    Some(piece) => to_append.push_str(&format!(" {piece} │")[..]),
-                }
-            }
-            board_str.push_str(&to_append[..]);
-            board_str.push('\n');
-            if row_num != 0 {
-                board_str.push_str("   ├───┼───┼───┼───┼───┼───┼───┼───┤\n")
-            }
-        }
-        board_str.push_str("   └───┴───┴───┴───┴───┴───┴───┴───┘\n");
-        board_str.push_str("     A   B   C   D   E   F   G   H  \n");
-        write!(f, "{board_str}")
+        write!(f, "{}", self.render(&RenderOptions::default(), &[]))
     }
 }
 
@@ -51,75 +40,258 @@ impl Board {
 
         self.board[end.0 as usize][end.1 as usize].as_mut().unwrap().coordinates = end;
         match &mut self.at(end).as_mut().unwrap().piece_type {
-            PieceType::King(val) => *val = false,
-            PieceType::Rook(val) => *val = false,
-            PieceType::Pawn(data) => *data = PawnData { can_en_pessant: false, has_moved: true },
+            PieceType::King(val) => *val = true,
+            PieceType::Rook(val) => *val = true,
+            PieceType::Pawn(data) => *data = PawnData { has_moved: true },
             _ => (),
         }
     }
 
-    pub fn swap_pieces(&mut self, start: Coordinates, end: Coordinates) {
-        let temp = self.board[end.0 as usize][end.1 as usize];
-        self.board[end.0 as usize][end.1 as usize] = self.board[start.0 as usize][start.1 as usize];
-        self.board[start.0 as usize][start.1 as usize] = temp;
+    /// Castles the king on `king` with the rook on `rook` using Chess960 placement:
+    /// the king lands on the g or c file and the rook on the f or d file,
+    /// whichever files they started on. The classical layout is just one case of this.
+    pub fn castle(&mut self, king: Coordinates, rook: Coordinates) {
+        let (king_file, rook_file) = castling_files(king, rook);
+        let mut king_piece = self.at(king).take().unwrap();
+        let mut rook_piece = self.at(rook).take().unwrap();
+
+        king_piece.coordinates = Coordinates(king.0, king_file);
+        king_piece.piece_type = PieceType::King(true);
+        rook_piece.coordinates = Coordinates(rook.0, rook_file);
+        rook_piece.piece_type = PieceType::Rook(true);
+
+        *self.at(king_piece.coordinates) = Some(king_piece);
+        *self.at(rook_piece.coordinates) = Some(rook_piece);
+    }
 
-        self.board[end.0 as usize][end.1 as usize].as_mut().unwrap().coordinates = start;
-        self.board[start.0 as usize][start.1 as usize].as_mut().unwrap().coordinates = end;
+    pub fn at(&mut self, coords: Coordinates) -> &mut Option<Piece> {
+        &mut self.board[coords.0 as usize][coords.1 as usize]
+    }
 
-        if let PieceType::Rook(val) = &mut self.at(start).as_mut().unwrap().piece_type {
-            *val = true;
+    /// Builds the Chess960 starting position with the given Scharnagl index (0..960).
+    /// Index 518 is the classical setup.
+    pub fn chess960(index: u16) -> Board {
+        Board {
+            board: build_starting_board(chess960_back_rank(index % 960)),
         }
+    }
 
-        if let PieceType::King(val) = &mut self.at(end).as_mut().unwrap().piece_type {
-            *val = true;
+    /// Marks every king and rook as moved, so neither side can castle
+    pub fn clear_castling_rights(&mut self) {
+        for piece in self.board.iter_mut().flatten().flatten() {
+            match piece.piece_type {
+                PieceType::King(_) => piece.piece_type = PieceType::King(true),
+                PieceType::Rook(_) => piece.piece_type = PieceType::Rook(true),
+                _ => (),
+            }
         }
     }
 
-    pub fn at(&mut self, coords: Coordinates) -> &mut Option<Piece> {
-        &mut self.board[coords.0 as usize][coords.1 as usize]
+    pub fn get(&self, coords: Coordinates) -> Option<Piece> {
+        self.board[coords.0 as usize][coords.1 as usize]
+    }
+
+    /// Returns every square the piece on `square` attacks.
+    /// Sliding pieces stop at (and include) the first occupied square in each direction,
+    /// pawns only attack diagonally forward, and a piece never attacks its own square.
+    /// An empty square attacks nothing.
+    pub fn attacks_from(&self, square: Coordinates) -> SquareSet {
+        let Some(piece) = self.get(square) else {
+            return SquareSet::default();
+        };
+
+        match piece.piece_type {
+            PieceType::Pawn(_) => {
+                let forward = match piece.color {
+                    PieceColor::White => -1,
+                    PieceColor::Black => 1,
+                };
+                self.step_attacks(square, &[(forward, -1), (forward, 1)])
+            },
+            PieceType::Knight => self.step_attacks(square, &KNIGHT_OFFSETS),
+            PieceType::King(_) => self.step_attacks(square, &KING_OFFSETS),
+            PieceType::Rook(_) => self.ray_attacks(square, &ROOK_DIRECTIONS),
+            PieceType::Bishop => self.ray_attacks(square, &BISHOP_DIRECTIONS),
+            PieceType::Queen => {
+                self.ray_attacks(square, &ROOK_DIRECTIONS) | self.ray_attacks(square, &BISHOP_DIRECTIONS)
+            },
+        }
+    }
+
+    fn step_attacks(&self, square: Coordinates, offsets: &[(i8, i8)]) -> SquareSet {
+        offsets
+            .iter()
+            .filter_map(|&(row, col)| offset_coords(square, row, col))
+            .collect()
+    }
+
+    fn ray_attacks(&self, square: Coordinates, directions: &[(i8, i8)]) -> SquareSet {
+        let mut attacks = SquareSet::default();
+        for &(row, col) in directions {
+            let mut current = square;
+            while let Some(next) = offset_coords(current, row, col) {
+                attacks.insert(next);
+                if self.get(next).is_some() {
+                    break;
+                }
+                current = next;
+            }
+        }
+        attacks
     }
 }
 
 // Functions
 
-fn build_starting_board() -> [[Option<Piece>; 8]; 8] {
-    let mut board = [
-        [None, None, None, None, None, None, None, None],
-        [None, None, None, None, None, None, None, None],
-        [None, None, None, None, None, None, None, None],
-        [None, None, None, None, None, None, None, None],
-        [None, None, None, None, None, None, None, None],
-        [None, None, None, None, None, None, None, None],
-        [None, None, None, None, None, None, None, None],
-        [None, None, None, None, None, None, None, None],
-    ];
-    board[0][0] = build_piece(PieceType::Rook(false), PieceColor::Black, Coordinates(0,0));
-    board[0][1] = build_piece(PieceType::Knight,      PieceColor::Black, Coordinates(0,1));
-    board[0][2] = build_piece(PieceType::Bishop,      PieceColor::Black, Coordinates(0,2));
-    board[0][3] = build_piece(PieceType::Queen,       PieceColor::Black, Coordinates(0,3));
-    board[0][4] = build_piece(PieceType::King(false), PieceColor::Black, Coordinates(0,4));
-    board[0][5] = build_piece(PieceType::Bishop,      PieceColor::Black, Coordinates(0,5));
-    board[0][6] = build_piece(PieceType::Knight,      PieceColor::Black, Coordinates(0,6));
-    board[0][7] = build_piece(PieceType::Rook(false), PieceColor::Black, Coordinates(0,7));
-
-    for i in 0..8 {
-        board[1][i] = build_piece(PieceType::Pawn(PawnData::default()), PieceColor::Black, Coordinates(1,i as u8));
-        board[6][i] = build_piece(PieceType::Pawn(PawnData::default()), PieceColor::White, Coordinates(6,i as u8))
-    }
-
-    for i in 2..6 {
-        for j in 0..8 {
-            board[i][j] = None;
-        }
+const CLASSICAL_BACK_RANK: [PieceType; 8] = [
+    PieceType::Rook(false),
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Queen,
+    PieceType::King(false),
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Rook(false),
+];
+
+/// Knight file pairs among the five squares left after placing bishops and queen.
+const CHESS960_KNIGHTS: [(usize, usize); 10] = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+
+/// Decodes a Scharnagl index into a back rank, files A through H.
+pub fn chess960_back_rank(index: u16) -> [PieceType; 8] {
+    let mut rank: [Option<PieceType>; 8] = [None; 8];
+    let mut n = index as usize;
+
+    rank[(n % 4) * 2 + 1] = Some(PieceType::Bishop);
+    n /= 4;
+    rank[(n % 4) * 2] = Some(PieceType::Bishop);
+    n /= 4;
+
+    let queen = n % 6;
+    n /= 6;
+    let empty: Vec<usize> = (0..8).filter(|&file| rank[file].is_none()).collect();
+    rank[empty[queen]] = Some(PieceType::Queen);
+
+    let (first, second) = CHESS960_KNIGHTS[n];
+    let empty: Vec<usize> = (0..8).filter(|&file| rank[file].is_none()).collect();
+    rank[empty[first]] = Some(PieceType::Knight);
+    rank[empty[second]] = Some(PieceType::Knight);
+
+    let empty: Vec<usize> = (0..8).filter(|&file| rank[file].is_none()).collect();
+    rank[empty[0]] = Some(PieceType::Rook(false));
+    rank[empty[1]] = Some(PieceType::King(false));
+    rank[empty[2]] = Some(PieceType::Rook(false));
+
+    rank.map(|piece| piece.unwrap())
+}
+
+/// Row of the given color's back rank in `Board::board`
+pub fn back_rank(color: PieceColor) -> u8 {
+    match color {
+        PieceColor::White => 7,
+        PieceColor::Black => 0,
+    }
+}
+
+/// Returns the destination files of the king and rook when castling, for a rook on
+/// either side of the king.
+pub fn castling_files(king: Coordinates, rook: Coordinates) -> (u8, u8) {
+    if rook.1 > king.1 {
+        (6, 5)
+    } else {
+        (2, 3)
     }
+}
+
+fn build_starting_board(back_rank: [PieceType; 8]) -> [[Option<Piece>; 8]; 8] {
+    let mut board = [[None; 8]; 8];
 
-    board[7][0] = build_piece(PieceType::Rook(false), PieceColor::White, Coordinates(7,0));
-    board[7][1] = build_piece(PieceType::Knight,      PieceColor::White, Coordinates(7,1));
-    board[7][2] = build_piece(PieceType::Bishop,      PieceColor::White, Coordinates(7,2));
-    board[7][3] = build_piece(PieceType::Queen,       PieceColor::White, Coordinates(7,3));
-    board[7][4] = build_piece(PieceType::King(false), PieceColor::White, Coordinates(7,4));
-    board[7][5] = build_piece(PieceType::Bishop,      PieceColor::White, Coordinates(7,5));
-    board[7][6] = build_piece(PieceType::Knight,      PieceColor::White, Coordinates(7,6));
-    board[7][7] = build_piece(PieceType::Rook(false), PieceColor::White, Coordinates(7,7));
+    for i in 0..8u8 {
+        board[0][i as usize] = build_piece(back_rank[i as usize], PieceColor::Black, Coordinates(0,i));
+        board[1][i as usize] = build_piece(PieceType::Pawn(PawnData::default()), PieceColor::Black, Coordinates(1,i));
+        board[6][i as usize] = build_piece(PieceType::Pawn(PawnData::default()), PieceColor::White, Coordinates(6,i));
+        board[7][i as usize] = build_piece(back_rank[i as usize], PieceColor::White, Coordinates(7,i));
+    }
     board
 }
+
+/// Moves `coords` by the given row and column offsets, returning `None` if that leaves the board.
+pub fn offset_coords(coords: Coordinates, row: i8, col: i8) -> Option<Coordinates> {
+    let new_row = coords.0 as i8 + row;
+    let new_col = coords.1 as i8 + col;
+    if (0..8).contains(&new_row) && (0..8).contains(&new_col) {
+        Some(Coordinates(new_row as u8, new_col as u8))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(name: &str) -> Coordinates {
+        let name = name.as_bytes();
+        Coordinates(b'8' - name[1], name[0] - b'a')
+    }
+
+    fn squares(names: &str) -> SquareSet {
+        names.split_whitespace().map(square).collect()
+    }
+
+    /// A board holding only the given pieces
+    fn board_with(pieces: &[(PieceType, PieceColor, &str)]) -> Board {
+        let mut board = Board { board: [[None; 8]; 8] };
+        for &(piece_type, color, name) in pieces {
+            *board.at(square(name)) = build_piece(piece_type, color, square(name));
+        }
+        board
+    }
+
+    #[test]
+    fn empty_squares_attack_nothing() {
+        assert!(Board { board: [[None; 8]; 8] }.attacks_from(square("e4")).is_empty());
+    }
+
+    #[test]
+    fn pawns_attack_diagonally_forward() {
+        let pawn = PieceType::Pawn(PawnData::default());
+        let board = board_with(&[(pawn, PieceColor::White, "a2"), (pawn, PieceColor::Black, "e5"), (pawn, PieceColor::White, "h8")]);
+        assert_eq!(board.attacks_from(square("a2")), squares("b3"));
+        assert_eq!(board.attacks_from(square("e5")), squares("d4 f4"));
+        assert!(board.attacks_from(square("h8")).is_empty());
+    }
+
+    #[test]
+    fn knights_and_kings_stay_on_the_board() {
+        let board = board_with(&[
+            (PieceType::Knight, PieceColor::White, "a1"),
+            (PieceType::Knight, PieceColor::Black, "h5"),
+            (PieceType::King(false), PieceColor::White, "h8"),
+            (PieceType::King(false), PieceColor::Black, "d1"),
+        ]);
+        assert_eq!(board.attacks_from(square("a1")), squares("b3 c2"));
+        assert_eq!(board.attacks_from(square("h5")), squares("g7 f6 f4 g3"));
+        assert_eq!(board.attacks_from(square("h8")), squares("g8 g7 h7"));
+        assert_eq!(board.attacks_from(square("d1")), squares("c1 c2 d2 e2 e1"));
+    }
+
+    #[test]
+    fn sliders_stop_at_the_first_piece_of_either_color() {
+        let board = board_with(&[
+            (PieceType::Rook(true), PieceColor::White, "a1"),
+            (PieceType::Knight, PieceColor::White, "a4"),
+            (PieceType::Pawn(PawnData::default()), PieceColor::Black, "c1"),
+            (PieceType::Bishop, PieceColor::Black, "c8"),
+            (PieceType::Queen, PieceColor::White, "d4"),
+            (PieceType::Pawn(PawnData::default()), PieceColor::White, "d6"),
+            (PieceType::Pawn(PawnData::default()), PieceColor::Black, "f6"),
+        ]);
+        assert_eq!(board.attacks_from(square("a1")), squares("a2 a3 a4 b1 c1"));
+        assert_eq!(board.attacks_from(square("c8")), squares("b7 a6 d7 e6 f5 g4 h3"));
+        assert_eq!(
+            board.attacks_from(square("d4")),
+            squares("d5 d6 d3 d2 d1 a4 b4 c4 e4 f4 g4 h4 c5 b6 a7 e5 f6 c3 b2 a1 e3 f2 g1")
+        );
+    }
+}
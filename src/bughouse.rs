@@ -0,0 +1,120 @@
+// Imports
+use std::fmt::Display;
+use std::io;
+
+use crate::errors::*;
+use crate::game::*;
+use crate::moves::*;
+use crate::piece::*;
+use crate::variant::*;
+
+// Custom BughouseMatch Type Data
+
+/// Two boards played side by side by two teams. White on board 1 partners Black on
+/// board 2 and the other way around; every capture goes into the partner's pocket.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct BughouseMatch {
+    pub boards: [Game<Bughouse>; 2],
+}
+
+// Custom BughouseMatch Type Traits
+
+impl Display for BughouseMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, board) in self.boards.iter().enumerate() {
+            writeln!(f, "Board {}, {} to move", index + 1, board.turn)?;
+            write!(f, "{board}")?;
+        }
+        Ok(())
+    }
+}
+
+// Impl BughouseMatch
+
+impl BughouseMatch {
+    /// Plays a move on board 0 or 1 and hands any captured piece to the partner
+    pub fn play(&mut self, board: usize, mv: Move) -> Result<GameResult, GameError> {
+        let game = &self.boards[board];
+        let color = game.turn;
+        let captured = game.captured_piece(mv).map(|piece| game.pocket_piece(piece));
+
+        let result = self.boards[board].play(mv)?;
+        if let Some(piece_type) = captured {
+            self.boards[1 - board].pocket_mut(color.swap()).add(piece_type);
+        }
+        Ok(result)
+    }
+
+    /// The first finished board and its result, if any board has finished
+    pub fn status(&self) -> Option<(usize, GameStatus)> {
+        self.boards
+            .iter()
+            .map(|board| board.status())
+            .enumerate()
+            .find(|(_, status)| status.is_over())
+    }
+
+    pub fn game_loop(&mut self) {
+        loop {
+            println!("{self}");
+            println!("Board and move, e.g. \"1 e2e4\" or \"2 N@f3\" (0 to quit)");
+
+            let mut input = String::new();
+            if io::stdin().read_line(&mut input).is_err() || input.trim() == "0" || input.is_empty() {
+                break;
+            }
+
+            let mut words = input.split_whitespace();
+            let board = match words.next().and_then(|word| word.parse::<usize>().ok()) {
+                Some(board @ 1..=2) => board - 1,
+                _ => {
+                    println!("Board must be 1 or 2");
+                    continue;
+                },
+            };
+            let mv = match words.next().map(|word| word.parse::<Move>()) {
+                Some(Ok(mv)) => mv,
+                _ => {
+                    println!("{}", ConversionError);
+                    continue;
+                },
+            };
+
+            if let Err(error) = self.play(board, mv) {
+                println!("{error}");
+                continue;
+            }
+
+            if let Some((board, status)) = self.status() {
+                println!("{self}");
+                println!("Board {}: {status}", board + 1);
+                if let Some(winner) = status.winner() {
+                    let team = if (board == 0) == (winner == PieceColor::White) { 1 } else { 2 };
+                    println!("Team {team} wins the match");
+                }
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captures_go_to_the_partner_on_the_other_board() {
+        let mut bughouse = BughouseMatch::default();
+        for mv in ["e2e4", "d7d5", "e4d5", "d8d5"] {
+            bughouse.play(0, mv.parse().unwrap()).unwrap();
+        }
+        // White on board 1 partners Black on board 2
+        assert_eq!(bughouse.boards[1].pocket(PieceColor::Black).to_string(), "p");
+        assert_eq!(bughouse.boards[1].pocket(PieceColor::White).to_string(), "p");
+        assert!(bughouse.boards[0].pocket(PieceColor::White).is_empty());
+        assert!(bughouse.boards[0].pocket(PieceColor::Black).is_empty());
+
+        bughouse.play(1, "P@e4".parse().unwrap()).unwrap();
+        assert!(bughouse.boards[1].pocket(PieceColor::White).is_empty());
+    }
+}
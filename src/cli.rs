@@ -0,0 +1,242 @@
+// Imports
+use std::error::Error;
+use std::str::FromStr;
+
+use crate::errors::*;
+use crate::variant::*;
+
+/// Every command: its name, usage and what it does
+const COMMANDS: [(&str, &str, &str); 14] = [
+    ("play", "play [<variant>]", "Play a game at the terminal, the default command"),
+    ("resume", "resume [<save file>]", "Carry on a saved game, by default the one saved automatically"),
+    ("host", "host [<variant>]", "Host a game against a player on another computer, who joins it"),
+    ("join", "join <address>", "Join a hosted game, e.g. join 192.168.1.20 or join localhost:7878"),
+    ("uci", "uci", "Talk to a chess GUI over the Universal Chess Interface"),
+    ("perft", "perft <depth> [<variant>]", "Count the legal move paths <depth> plies deep, split by first move"),
+    ("analyze", "analyze [<fen or pgn>]", "Show the engine's best lines until Enter is pressed"),
+    ("annotate", "annotate <pgn file>", "Mark the inaccuracies, mistakes and blunders of every game"),
+    ("pgn", "pgn convert <pgn file>", "Rewrite games as tidy PGN, UCI position commands or FENs with results"),
+    ("fen", "fen [<fen or pgn>]", "Show a position with its FEN and status"),
+    ("bench", "bench [<depth>]", "Search a fixed set of positions, for comparing search changes"),
+    ("tune", "tune <positions file>", "Fit the evaluation weights to FENs labelled with results"),
+    ("match", "match", "Play two engine configurations against each other"),
+    ("help", "help [<command>]", "Show this help, or a command's"),
+];
+
+/// Variant names `play` and `perft` take, which also work as commands that play them
+pub const VARIANTS: [&str; 9] =
+    ["standard", "kingofthehill", "threecheck", "horde", "antichess", "atomic", "racingkings", "crazyhouse", "bughouse"];
+
+/// Commands that search with the engine
+const ENGINE_COMMANDS: &[&str] = &["play", "resume", "uci", "analyze", "annotate", "bench", "tune", "match"];
+
+/// Every option: its name, the value it takes (`None` for a switch), the commands it
+/// applies to and what it does
+const OPTIONS: [(&str, Option<&str>, &[&str], &str); 33] = [
+    ("--fen", Some("<fen>"), &["play", "host", "perft", "analyze", "fen"], "Start from this position instead of the usual one"),
+    ("--chess960", Some("<0-959>|random"), &["play", "host", "perft", "analyze", "fen"], "Start from a Chess960 setup, by number or at random"),
+    ("--seed", Some("<n>"), &["play", "host", "perft", "analyze", "fen"], "Pick the random Chess960 setup with this seed, to get the same one again"),
+    ("--moves", Some("<moves>"), &["play", "host", "perft", "analyze", "fen"], "Play these moves first, e.g. \"e4 e5 Nf3\""),
+    ("--engine", Some("white|black"), &["play"], "Have the engine play this side"),
+    ("--time", Some("<control>"), &["play", "host", "match"], "Play with a clock, e.g. 5+3 (minutes + increment seconds) or 40/90+30"),
+    ("--color", Some("white|black"), &["host"], "Side the host plays, white by default"),
+    ("--port", Some("<port>"), &["host"], "Port to wait for the other player on, 7878 by default"),
+    ("--depth", Some("<plies>"), &["play", "resume", "analyze", "annotate"], "How deep the engine searches"),
+    ("--skill", Some("<0-20>"), &["play", "resume", "uci"], "Weaken the engine to this level"),
+    ("--elo", Some("<rating>"), &["play", "resume", "uci"], "Weaken the engine to about this rating"),
+    ("--book", Some("<file>"), &["play", "resume", "uci", "match"], "Play openings from a Polyglot book"),
+    ("--syzygy", Some("<dirs>"), ENGINE_COMMANDS, "Play endgames from Syzygy tablebases"),
+    ("--nnue", Some("<file>"), ENGINE_COMMANDS, "Evaluate with a network instead of counting material"),
+    ("--params", Some("<file>"), ENGINE_COMMANDS, "Load tuned evaluation weights"),
+    ("--pieces", Some("ascii|unicode"), &["play", "resume", "host", "join", "fen"], "Draw pieces as letters or chess glyphs"),
+    ("--no-color", None, &["play", "resume", "host", "join", "fen"], "Leave out colors, as does setting NO_COLOR"),
+    ("--view", Some("white|black|auto"), &["play", "resume", "host", "join", "fen"], "Side drawn at the bottom, by default the player's own"),
+    ("--labels", None, &["play", "resume", "host", "join", "fen"], "Label the ranks and files on all four sides"),
+    ("--review", None, &["play", "resume"], "Annotate the game once it is over"),
+    ("--autosave", Some("<file>"), &["play", "resume"], "Save the game here after every move, rust_chess.save by default"),
+    ("--no-autosave", None, &["play", "resume"], "Don't save the game after every move"),
+    ("--lines", Some("<n>"), &["analyze"], "Number of best lines shown"),
+    ("--to", Some("pgn|uci|fen"), &["pgn"], "What to convert games to, pgn by default"),
+    ("--out", Some("<file>"), &["annotate", "pgn", "tune"], "Write the result here instead of to stdout"),
+    ("--epochs", Some("<n>"), &["tune"], "Passes over the positions, 100 by default"),
+    ("--engine1", Some("<settings>"), &["match"], "First engine, e.g. name=new,params=new.txt,depth=6, over the engine options"),
+    ("--engine2", Some("<settings>"), &["match"], "Second engine, in the same form"),
+    ("--games", Some("<n>"), &["match"], "Games to play, 100 by default"),
+    ("--concurrency", Some("<n>"), &["match"], "Games played at once"),
+    ("--openings", Some("<file>"), &["match"], "FEN or EPD openings, one per line"),
+    ("--pgn", Some("<file>"), &["match"], "Save the games here"),
+    ("--sprt", Some("<elo0,elo1>"), &["match"], "Stop once a sequential probability ratio test decides"),
+];
+
+// Custom Args Type Data
+
+/// Command-line arguments split into positional arguments (the command first), option
+/// values and switches
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Args {
+    positional: Vec<String>,
+    values: Vec<(&'static str, String)>,
+    switches: Vec<&'static str>,
+}
+
+// Custom VariantCommand Type Traits
+
+/// A command that runs in whichever variant is named, see `with_variant`
+pub trait VariantCommand {
+    fn run<V: Variant>(self) -> Result<(), Box<dyn Error>>;
+}
+
+// Impl Args
+
+impl Args {
+    /// Reads `args` as given after the program name. Options take their value from the
+    /// next argument or after `=`, as in `--depth=6`; `-h` is short for `--help`.
+    pub fn parse(args: &[String]) -> Result<Args, CliError> {
+        let mut parsed = Args::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with('-') || arg == "-" {
+                parsed.positional.push(arg.clone());
+                continue;
+            }
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_owned())),
+                None => (arg.as_str(), None),
+            };
+            let name = if name == "-h" { "--help" } else { name };
+            if name == "--help" {
+                parsed.switches.push("--help");
+                continue;
+            }
+            let Some(&(option, value, _, _)) = OPTIONS.iter().find(|(option, ..)| *option == name) else {
+                return Err(CliError::UnknownOption(name.to_owned()));
+            };
+            match (value, inline) {
+                (None, None) => parsed.switches.push(option),
+                (None, Some(_)) => return Err(CliError::InvalidValue(option.to_owned(), arg.clone(), "no value")),
+                (Some(_), Some(value)) => parsed.values.push((option, value)),
+                (Some(_), None) => match args.next() {
+                    Some(value) => parsed.values.push((option, value.clone())),
+                    None => return Err(CliError::MissingValue(option.to_owned())),
+                },
+            }
+        }
+        parsed.check_options()?;
+        Ok(parsed)
+    }
+
+    /// Errors on the first option the command doesn't take. Unknown commands are left to
+    /// be reported when run, and nothing is checked when help is asked for.
+    fn check_options(&self) -> Result<(), CliError> {
+        let command = match self.command() {
+            None => "play",
+            Some(variant) if VARIANTS.contains(&variant) => "play",
+            Some(command) => command,
+        };
+        if self.has("--help") || command == "help" || !COMMANDS.iter().any(|(name, ..)| *name == command) {
+            return Ok(());
+        }
+        let given = self.values.iter().map(|(option, _)| *option).chain(self.switches.iter().copied());
+        for option in given {
+            let (_, _, commands, _) = OPTIONS.iter().find(|(name, ..)| *name == option).expect("parsed options are listed");
+            if !commands.contains(&command) {
+                return Err(CliError::NotForCommand(option.to_owned(), command.to_owned()));
+            }
+        }
+        Ok(())
+    }
+
+    /// The first positional argument
+    pub fn command(&self) -> Option<&str> {
+        self.positional(0)
+    }
+
+    /// The positional argument at `index`, where the command is 0
+    pub fn positional(&self, index: usize) -> Option<&str> {
+        self.positional.get(index).map(String::as_str)
+    }
+
+    /// The value of `option`, the last one given if it is repeated
+    pub fn value(&self, option: &str) -> Option<&str> {
+        self.values.iter().rev().find(|(name, _)| *name == option).map(|(_, value)| value.as_str())
+    }
+
+    pub fn has(&self, switch: &str) -> bool {
+        self.switches.contains(&switch)
+    }
+
+    /// The value of `option` parsed, or an error naming what was `expected`
+    pub fn parsed<T: FromStr>(&self, option: &str, expected: &'static str) -> Result<Option<T>, CliError> {
+        self.value(option)
+            .map(|value| value.parse().map_err(|_| CliError::InvalidValue(option.to_owned(), value.to_owned(), expected)))
+            .transpose()
+    }
+}
+
+// Functions
+
+/// Usage for every command, or only for `command` along with the options it takes
+pub fn help(command: Option<&str>) -> Result<String, CliError> {
+    let mut text = String::new();
+    let options: Vec<_> = match command {
+        None => {
+            text.push_str("Usage: rust_chess [<command>] [<options>]\n\nCommands:\n");
+            for (_, usage, description) in COMMANDS {
+                text.push_str(&format!("  {usage:<26} {description}\n"));
+            }
+            text.push_str(&format!("\nVariants: {}\n", VARIANTS.join(", ")));
+            OPTIONS.iter().collect()
+        },
+        Some(command) => {
+            let command = if VARIANTS.contains(&command) { "play" } else { command };
+            let Some((name, usage, description)) = COMMANDS.iter().find(|(name, ..)| *name == command) else {
+                return Err(CliError::UnknownCommand(command.to_owned()));
+            };
+            text.push_str(&format!("Usage: rust_chess {usage} [<options>]\n\n{description}\n"));
+            OPTIONS.iter().filter(|(_, _, commands, _)| commands.contains(name)).collect()
+        },
+    };
+
+    text.push_str("\nOptions:\n");
+    for (option, value, _, description) in options {
+        let option = format!("{option} {}", value.unwrap_or(""));
+        text.push_str(&format!("  {option:<26} {description}\n"));
+    }
+    text.push_str(&format!("  {:<26} {}\n", "-h, --help", "Show help"));
+    Ok(text)
+}
+
+/// Runs `command` in the variant `name`, standard chess when there is none. Bughouse is
+/// played on two boards, so it is not one of them.
+pub fn with_variant(name: Option<&str>, command: impl VariantCommand) -> Result<(), Box<dyn Error>> {
+    match name.unwrap_or("standard") {
+        "standard" => command.run::<Standard>(),
+        "kingofthehill" => command.run::<KingOfTheHill>(),
+        "threecheck" => command.run::<ThreeCheck>(),
+        "horde" => command.run::<Horde>(),
+        "antichess" => command.run::<Antichess>(),
+        "atomic" => command.run::<Atomic>(),
+        "racingkings" => command.run::<RacingKings>(),
+        "crazyhouse" => command.run::<Crazyhouse>(),
+        other => Err(CliError::UnknownVariant(other.to_owned()).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Args, CliError> {
+        Args::parse(&args.split_whitespace().map(str::to_owned).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn options_must_belong_to_the_command() {
+        assert_eq!(parse("perft 2 --engine white --port 9"), Err(CliError::NotForCommand("--engine".to_owned(), "perft".to_owned())));
+        assert_eq!(parse("--port 9"), Err(CliError::NotForCommand("--port".to_owned(), "play".to_owned())));
+        assert!(parse("fen --labels --no-color").unwrap().has("--labels"));
+        assert!(parse("atomic --engine black --depth 4").is_ok());
+        assert!(parse("perft --port 9 --help").is_ok());
+        assert!(parse("nonsense --port 9").is_ok());
+    }
+}
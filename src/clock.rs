@@ -0,0 +1,304 @@
+// Imports
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::errors::*;
+use crate::piece::*;
+use crate::variant::*;
+
+/// Time kept back for reading the move and printing it, so the engine never flags by a hair
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+/// Moves the remaining time is spread over when the time control has no move count
+const EXPECTED_MOVES: u32 = 30;
+
+// Custom TimeControl Type Data
+
+/// Time each player starts with, the increment added after every move and optionally the
+/// number of moves after which the base time is added again (classical `40/90+30`)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TimeControl {
+    pub base: Duration,
+    pub increment: Duration,
+    pub moves: Option<u32>,
+}
+
+// Custom TimeControl Type Traits
+
+impl FromStr for TimeControl {
+    type Err = ConversionError;
+
+    /// Reads `[moves/]minutes[+increment seconds]`, e.g. `5+3` or `40/90+30`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (moves, rest) = match s.trim().split_once('/') {
+            Some((moves, rest)) => (Some(moves.parse::<u32>().map_err(|_| ConversionError)?), rest),
+            None => (None, s.trim()),
+        };
+        let (minutes, increment) = rest.split_once('+').unwrap_or((rest, "0"));
+        let minutes = minutes.parse::<f64>().map_err(|_| ConversionError)?;
+        let increment = increment.parse::<f64>().map_err(|_| ConversionError)?;
+        if minutes <= 0.0 || increment < 0.0 || moves == Some(0) {
+            return Err(ConversionError);
+        }
+
+        Ok(TimeControl {
+            base: Duration::from_secs_f64(minutes * 60.0),
+            increment: Duration::from_secs_f64(increment),
+            moves,
+        })
+    }
+}
+
+impl Display for TimeControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(moves) = self.moves {
+            write!(f, "{moves}/")?;
+        }
+        write!(f, "{}+{}", self.base.as_secs_f64() / 60.0, self.increment.as_secs_f64())
+    }
+}
+
+// Custom Clock Type Data
+
+/// A chess clock: each side's remaining time, with at most one side running
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Clock {
+    pub control: TimeControl,
+    remaining: [Duration; 2],
+    moves_played: [u32; 2],
+    running: Option<(PieceColor, Instant)>,
+}
+
+// Custom Clock Type Traits
+
+impl Display for Clock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let format = |time: Duration| format!("{}:{:02}", time.as_secs() / 60, time.as_secs() % 60);
+        write!(f, "White {}  Black {}", format(self.remaining(PieceColor::White)), format(self.remaining(PieceColor::Black)))
+    }
+}
+
+// Impl Clock
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Clock {
+        Clock {
+            control,
+            remaining: [control.base; 2],
+            moves_played: [0; 2],
+            running: None,
+        }
+    }
+
+    /// A clock part way through a game, with the time each side has left and the moves
+    /// each has made, indexed White then Black
+    pub fn resume(control: TimeControl, remaining: [Duration; 2], moves_played: [u32; 2]) -> Clock {
+        Clock { control, remaining, moves_played, running: None }
+    }
+
+    /// Time left for `color`, counting down live while its clock runs
+    pub fn remaining(&self, color: PieceColor) -> Duration {
+        let remaining = self.remaining[color_index(color)];
+        match self.running {
+            Some((running, since)) if running == color => remaining.saturating_sub(since.elapsed()),
+            _ => remaining,
+        }
+    }
+
+    pub fn is_flagged(&self, color: PieceColor) -> bool {
+        self.remaining(color).is_zero()
+    }
+
+    /// Starts `color`'s clock, if it isn't running already
+    pub fn start(&mut self, color: PieceColor) {
+        if self.running.is_none_or(|(running, _)| running != color) {
+            self.stop();
+            self.running = Some((color, Instant::now()));
+        }
+    }
+
+    /// Stops the running clock after its side moved, adding the increment unless the side
+    /// already ran out of time
+    pub fn stop(&mut self) {
+        let Some((color, _)) = self.running else {
+            return;
+        };
+        let remaining = self.remaining(color);
+        self.running = None;
+
+        let index = color_index(color);
+        self.remaining[index] = remaining;
+        if remaining.is_zero() {
+            return;
+        }
+        self.remaining[index] += self.control.increment;
+        self.moves_played[index] += 1;
+        if self.control.moves.is_some_and(|moves| self.moves_played[index].is_multiple_of(moves)) {
+            self.remaining[index] += self.control.base;
+        }
+    }
+
+    /// Stops the running clock without counting a move, as while a network game waits
+    /// for the other player to reconnect
+    pub fn pause(&mut self) {
+        if let Some((color, _)) = self.running {
+            self.remaining[color_index(color)] = self.remaining(color);
+            self.running = None;
+        }
+    }
+
+    /// This clock as it stood when the running side's clock started, without the time
+    /// since, as kept with each move to take back
+    pub fn before_running(&self) -> Clock {
+        Clock { running: None, ..*self }
+    }
+
+    /// Sets the time `color` has left to what another clock shows, such as the network
+    /// opponent's own
+    pub fn sync(&mut self, color: PieceColor, remaining: Duration) {
+        self.remaining[color_index(color)] = remaining;
+        if let Some((running, since)) = &mut self.running {
+            if *running == color {
+                *since = Instant::now();
+            }
+        }
+    }
+
+    /// Moves `color` has left until the next time control, if the control has a move count
+    pub fn moves_to_go(&self, color: PieceColor) -> Option<u32> {
+        self.control.moves.map(|moves| moves - self.moves_played[color_index(color)] % moves)
+    }
+
+    /// How long `color` may think about its next move
+    pub fn limits(&self, color: PieceColor) -> TimeLimits {
+        TimeLimits::new(self.remaining(color), self.control.increment, self.moves_to_go(color))
+    }
+}
+
+// Custom TimeLimits Type Data
+
+/// How long to think about one move. The search finishes its current depth while under
+/// `soft`, stretched when the position looks unstable, and is cut off at `hard`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TimeLimits {
+    pub soft: Duration,
+    pub hard: Duration,
+}
+
+// Impl TimeLimits
+
+impl TimeLimits {
+    /// Aims to spend the remaining time split over the moves to go, plus most of the
+    /// increment. A new depth takes several times as long as the last one, so the soft
+    /// limit stops short of that target.
+    pub fn new(remaining: Duration, increment: Duration, moves_to_go: Option<u32>) -> TimeLimits {
+        let usable = remaining.saturating_sub(MOVE_OVERHEAD);
+        let moves = moves_to_go.unwrap_or(EXPECTED_MOVES).clamp(1, EXPECTED_MOVES);
+        let target = (usable / moves + increment * 3 / 4).min(usable);
+        let soft = target * 6 / 10;
+        let hard = (target * 3).min(usable / 2).max(soft);
+        TimeLimits { soft, hard }
+    }
+
+    /// Exactly `time` for the move, as UCI's `go movetime`
+    pub fn fixed(time: Duration) -> TimeLimits {
+        let time = time.saturating_sub(MOVE_OVERHEAD).max(Duration::from_millis(1));
+        TimeLimits { soft: time, hard: time }
+    }
+
+    /// Whether to stop before starting another depth. The soft limit grows by half when the
+    /// best move just changed, and again when the score dropped since the last depth.
+    pub fn past_soft(&self, elapsed: Duration, best_move_changed: bool, score_drop: i32) -> bool {
+        let mut percent = 100;
+        if best_move_changed {
+            percent += 50;
+        }
+        if score_drop >= 30 {
+            percent += 50;
+        }
+        if score_drop >= 100 {
+            percent += 50;
+        }
+        elapsed >= (self.soft * percent / 100).min(self.hard)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    /// Plays one move for `color` on `clock`, taking next to no time
+    fn play(clock: &mut Clock, color: PieceColor) {
+        clock.start(color);
+        clock.stop();
+    }
+
+    #[test]
+    fn sudden_death_spreads_the_time_over_the_expected_moves() {
+        let limits = TimeLimits::new(secs(60), Duration::ZERO, None);
+        // 59.95 s usable over 30 moves
+        assert_eq!((limits.soft.as_millis(), limits.hard.as_millis()), (1198, 5994));
+        // Too little time left to think at all
+        assert_eq!(TimeLimits::new(Duration::from_millis(40), Duration::ZERO, None), TimeLimits { soft: Duration::ZERO, hard: Duration::ZERO });
+
+        let mut clock = Clock::new("5".parse().unwrap());
+        play(&mut clock, PieceColor::White);
+        assert!((secs(299)..=secs(300)).contains(&clock.remaining(PieceColor::White)));
+        assert_eq!(clock.remaining(PieceColor::Black), secs(300));
+        assert_eq!(clock.moves_to_go(PieceColor::White), None);
+    }
+
+    #[test]
+    fn increments_are_mostly_spent_and_added_after_each_move() {
+        let limits = TimeLimits::new(secs(10), secs(2), None);
+        // A 331 ms share and 1.5 s of the increment, with the hard limit held to half the clock
+        assert_eq!((limits.soft.as_millis(), limits.hard.as_millis()), (1098, 4975));
+
+        let mut clock = Clock::new("5+3".parse().unwrap());
+        play(&mut clock, PieceColor::White);
+        assert!((secs(302)..=secs(303)).contains(&clock.remaining(PieceColor::White)));
+
+        // Not once the flag has fallen
+        let mut clock = Clock::resume("5+3".parse().unwrap(), [Duration::ZERO, secs(300)], [10, 10]);
+        play(&mut clock, PieceColor::White);
+        assert!(clock.is_flagged(PieceColor::White));
+    }
+
+    #[test]
+    fn moves_to_go_share_out_the_time_until_the_control_adds_it_again() {
+        let limits = TimeLimits::new(secs(60), Duration::ZERO, Some(2));
+        assert_eq!((limits.soft.as_millis(), limits.hard.as_millis()), (17985, 29975));
+        // More moves to go than expected are planned as the usual number
+        assert_eq!(TimeLimits::new(secs(60), Duration::ZERO, Some(40)), TimeLimits::new(secs(60), Duration::ZERO, None));
+
+        let mut clock = Clock::new("2/1".parse().unwrap());
+        assert_eq!(clock.moves_to_go(PieceColor::White), Some(2));
+        play(&mut clock, PieceColor::White);
+        assert_eq!(clock.moves_to_go(PieceColor::White), Some(1));
+        assert!(clock.remaining(PieceColor::White) <= secs(60));
+        play(&mut clock, PieceColor::White);
+        // The second move reaches the control, so the base time comes back
+        assert_eq!(clock.moves_to_go(PieceColor::White), Some(2));
+        assert!((secs(119)..=secs(120)).contains(&clock.remaining(PieceColor::White)));
+        assert_eq!(clock.limits(PieceColor::Black), TimeLimits::new(secs(60), Duration::ZERO, Some(2)));
+    }
+
+    #[test]
+    fn the_soft_limit_stretches_for_unstable_searches_up_to_the_hard_one() {
+        let limits = TimeLimits { soft: secs(1), hard: secs(3) };
+        assert!(!limits.past_soft(Duration::from_millis(999), false, 0));
+        assert!(limits.past_soft(secs(1), false, 29));
+        assert!(!limits.past_soft(Duration::from_millis(1200), true, 0));
+        assert!(!limits.past_soft(Duration::from_millis(1900), true, 30));
+        assert!(limits.past_soft(secs(2), true, 30));
+        assert!(!limits.past_soft(Duration::from_millis(2400), true, 100));
+        assert!(limits.past_soft(Duration::from_millis(2500), true, 100));
+
+        let limits = TimeLimits { soft: secs(1), hard: Duration::from_millis(1200) };
+        assert!(limits.past_soft(Duration::from_millis(1200), true, 100));
+    }
+}
@@ -0,0 +1,814 @@
+// Imports
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::book::*;
+use crate::clock::*;
+use crate::eval::*;
+use crate::game::*;
+use crate::moves::*;
+use crate::nnue::*;
+use crate::piece::*;
+use crate::rng::*;
+use crate::skill::*;
+use crate::tablebase::*;
+use crate::tt::*;
+use crate::variant::*;
+
+/// Score for delivering mate right now, mates further away score a little less
+pub const MATE_SCORE: i32 = 30_000;
+/// Score for a tablebase win, below any mate the search finds itself
+pub const TABLEBASE_WIN_SCORE: i32 = MATE_SCORE - 1_000;
+/// Scores beyond this are mates or tablebase results, which depend on the ply they were found at
+const DECISIVE_SCORE: i32 = TABLEBASE_WIN_SCORE - 1_000;
+/// Deepest a timed search goes, it normally runs out of time long before
+pub const MAX_DEPTH: u8 = 64;
+/// Deepest ply any line is followed to, check extensions and captures included
+const MAX_PLY: i32 = 128;
+/// Half width of the first window around the last depth's score
+const ASPIRATION_WINDOW: i32 = 30;
+
+// Custom Engine Type Data
+
+/// A computer player: plays from the opening book while it can, perfectly from the
+/// endgame tablebases once few pieces are left, and searches in between.
+/// With a single thread, the default, a search from the same table always plays out the same.
+#[derive(Debug, Clone)]
+pub struct Engine {
+    pub book: Option<Book>,
+    pub tablebase: Option<Arc<Tablebase>>,
+    /// Search depth in plies
+    pub depth: u8,
+    pub rng: Rng,
+    /// Shared by every search thread, and by clones of the engine
+    pub tt: Arc<TranspositionTable>,
+    /// Search threads, 1 keeps searches deterministic
+    pub threads: usize,
+    /// Weights of the hand-crafted evaluation
+    pub params: Arc<EvalParams>,
+    pub network: Option<Arc<Network>>,
+    /// Evaluate with `network` when one is loaded, with `params` otherwise
+    pub use_network: bool,
+    /// Positions visited by the current or last search
+    pub nodes: u64,
+    /// Playing strength of `best_move`, full by default
+    pub skill: Skill,
+    /// Lines searched and reported, each with a different first move
+    pub multi_pv: usize,
+    /// Set from another thread to end the current search early, keeping the last full depth
+    pub stop: Arc<AtomicBool>,
+}
+
+/// When a search stops: at `depth`, or when time or nodes run out if it has limits
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SearchLimits {
+    pub depth: u8,
+    pub time: Option<TimeLimits>,
+    /// Nodes over all threads, checked in batches so the search may go a little past it
+    pub nodes: Option<u64>,
+}
+
+/// Progress of a search after each completed depth
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchInfo {
+    pub depth: u8,
+    /// Centipawns for the side to move, or a mate score (see `mate_in`)
+    pub score: i32,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    /// Principal variation, the best line found
+    pub pv: Vec<Move>,
+    /// The best `Engine::multi_pv` lines, best first, the first being `score` and `pv`
+    pub lines: Vec<SearchLine>,
+}
+
+/// One line of a multi-PV search: the score of its first move and the moves expected to follow
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchLine {
+    pub score: i32,
+    pub pv: Vec<Move>,
+}
+
+// Custom Engine Type Traits
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine {
+            book: None,
+            tablebase: None,
+            depth: 4,
+            rng: Rng::from_time(),
+            tt: Arc::new(TranspositionTable::default()),
+            threads: 1,
+            params: Arc::new(EvalParams::default()),
+            network: None,
+            use_network: false,
+            nodes: 0,
+            skill: Skill::default(),
+            multi_pv: 1,
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+// Custom Searcher Type Data
+
+/// One search thread: shares the transposition table and stop flag with the others,
+/// and keeps its own node count and root move
+struct Searcher<'a> {
+    tt: &'a TranspositionTable,
+    tablebase: Option<&'a Tablebase>,
+    stop: &'a AtomicBool,
+    /// Nodes of every thread, added to in batches
+    total_nodes: &'a AtomicU64,
+    nodes: u64,
+    root_move: Option<Move>,
+    /// Hard time limit, only the main thread watches the clock
+    deadline: Option<Instant>,
+    /// Node limit, also only watched by the main thread
+    node_limit: Option<u64>,
+    /// Set while the main thread searches the first depth, which runs to the end whatever
+    /// the stop flag says so that there is always a move to play
+    unstoppable: bool,
+    /// Root moves left out, as the first moves of earlier multi-PV lines
+    excluded: Vec<Move>,
+    /// Two quiet moves per ply that recently caused a beta cutoff, tried early by siblings
+    killers: [[Option<Move>; 2]; MAX_PLY as usize],
+    params: &'a EvalParams,
+    network: Option<&'a Network>,
+    /// The network's accumulator for the position at each ply of the current line
+    accumulators: Vec<Accumulator>,
+    /// The best line of each quiescence node on the current line, by ply. Only kept by
+    /// `quiet_leaf`.
+    quiet_lines: Option<Vec<Vec<Move>>>,
+}
+
+// Impl SearchLimits
+
+impl SearchLimits {
+    pub fn depth(depth: u8) -> SearchLimits {
+        SearchLimits { depth, time: None, nodes: None }
+    }
+
+    pub fn time(time: TimeLimits) -> SearchLimits {
+        SearchLimits { depth: MAX_DEPTH, time: Some(time), nodes: None }
+    }
+}
+
+// Impl Engine
+
+impl Engine {
+    /// The move the engine would play, `None` if the game is already over
+    pub fn best_move<V: Variant>(&mut self, game: &Game<V>) -> Option<Move> {
+        self.best_move_within(game, SearchLimits::depth(self.depth))
+    }
+
+    /// The move the engine would play when searching no further than `limits`.
+    /// Below full skill the tablebases are left out, so endgames can still be lost.
+    pub fn best_move_within<V: Variant>(&mut self, game: &Game<V>, limits: SearchLimits) -> Option<Move> {
+        if let Some(mv) = self.book_move(game) {
+            return Some(mv);
+        }
+        if self.skill.is_limited() {
+            return self.skilled_move(game, limits);
+        }
+        if let Some((mv, _)) = self.tablebase.as_ref().and_then(|tablebase| tablebase.best_move(game)) {
+            return Some(mv);
+        }
+        self.search_with(game, limits, |_| {}).0
+    }
+
+    /// A weighted random move from the opening book, if the position is in it
+    pub fn book_move<V: Variant>(&mut self, game: &Game<V>) -> Option<Move> {
+        self.book.as_ref()?.weighted_move(game, &mut self.rng)
+    }
+
+    /// Searches to a fixed depth and returns the best move with its score for the side to move
+    pub fn search<V: Variant>(&mut self, game: &Game<V>, depth: u8) -> (Option<Move>, i32) {
+        self.search_with(game, SearchLimits::depth(depth), |_| {})
+    }
+
+    /// Searches one ply deeper at a time until `limits`, calling `report` after each depth.
+    /// Earlier depths fill the transposition table, which orders the moves of later ones.
+    /// A depth cut off by the hard time limit is thrown away, the last full depth counts.
+    /// With more than one thread the helpers search the same position alongside, Lazy SMP
+    /// style, and only share what they find through the transposition table.
+    pub fn search_with<V: Variant>(&mut self, game: &Game<V>, limits: SearchLimits, mut report: impl FnMut(&SearchInfo)) -> (Option<Move>, i32) {
+        let start = Instant::now();
+        self.tt.new_search();
+        self.nodes = 0;
+
+        if game.legal_moves().is_empty() {
+            return (None, terminal_score(game, 0));
+        }
+
+        let depth = limits.depth.max(1);
+        let stop = &*self.stop;
+        stop.store(false, Ordering::Relaxed);
+        let total_nodes = AtomicU64::new(0);
+        let tt = &*self.tt;
+        let tablebase = self.tablebase.as_deref();
+        let params = &*self.params;
+        let network = self.network.as_deref().filter(|_| self.use_network);
+
+        let best = thread::scope(|scope| {
+            for id in 1..self.threads.max(1) {
+                let total_nodes = &total_nodes;
+                scope.spawn(move || {
+                    let mut searcher = Searcher::new(game, tt, tablebase, params, network, stop, total_nodes);
+                    let mut score = 0;
+                    // Half the helpers start a ply deeper, so the threads spread over two depths
+                    for depth in (1 + id % 2) as u8..=depth {
+                        score = searcher.aspiration(game, depth, score);
+                        if stop.load(Ordering::Relaxed) {
+                            break;
+                        }
+                    }
+                    searcher.flush_nodes();
+                });
+            }
+
+            let mut searcher = Searcher::new(game, tt, tablebase, params, network, stop, &total_nodes);
+            let mut best: (Option<Move>, i32) = (None, 0);
+            let mut lines: Vec<SearchLine> = Vec::new();
+            let line_count = self.multi_pv.clamp(1, game.legal_moves().len());
+            for depth in 1..=depth {
+                // Each line searches the root without the first moves of the lines before it
+                let mut new_lines = Vec::new();
+                searcher.excluded.clear();
+                searcher.unstoppable = depth == 1;
+                for line in 0..line_count {
+                    let previous = lines.get(line).map_or(best.1, |line| line.score);
+                    let score = searcher.aspiration(game, depth, previous);
+                    searcher.flush_nodes();
+                    if searcher.stopped() {
+                        break;
+                    }
+                    let pv = searcher.principal_variation(game, depth);
+                    searcher.excluded.extend(pv.first());
+                    new_lines.push(SearchLine { score, pv });
+                }
+                if searcher.stopped() {
+                    break;
+                }
+                searcher.unstoppable = false;
+                new_lines.sort_by_key(|line| -line.score);
+                lines = new_lines;
+                let (score, pv) = (lines[0].score, lines[0].pv.clone());
+                let previous = best;
+                best = (pv.first().copied(), score);
+                report(&SearchInfo {
+                    depth,
+                    score,
+                    nodes: total_nodes.load(Ordering::Relaxed),
+                    elapsed: start.elapsed(),
+                    pv,
+                    lines: lines.clone(),
+                });
+
+                // A forced mate within the searched depth won't change by searching deeper, once a
+                // depth that started out knowing of it, and so reduced nothing, agrees
+                let mate_found = mate_in(score).is_some_and(|moves| moves.unsigned_abs() * 2 <= depth as u32) && score == previous.1;
+                if mate_found && line_count == 1 {
+                    break;
+                }
+                if let Some(time) = limits.time {
+                    let (best_move_changed, score_drop) = match depth {
+                        1 => (false, 0),
+                        _ => (best.0 != previous.0, previous.1 - score),
+                    };
+                    if time.past_soft(start.elapsed(), best_move_changed, score_drop) {
+                        break;
+                    }
+                    // The first depth is unstoppable, so the deadline only applies from here
+                    searcher.deadline = Some(start + time.hard);
+                }
+                if limits.nodes.is_some_and(|limit| total_nodes.load(Ordering::Relaxed) >= limit) {
+                    break;
+                }
+                searcher.node_limit = limits.nodes;
+            }
+            stop.store(true, Ordering::Relaxed);
+            best
+        });
+        self.nodes = total_nodes.load(Ordering::Relaxed);
+        best
+    }
+}
+
+// Impl Searcher
+
+impl<'a> Searcher<'a> {
+    fn new<V: Variant>(
+        root: &Game<V>,
+        tt: &'a TranspositionTable,
+        tablebase: Option<&'a Tablebase>,
+        params: &'a EvalParams,
+        network: Option<&'a Network>,
+        stop: &'a AtomicBool,
+        total_nodes: &'a AtomicU64,
+    ) -> Searcher<'a> {
+        let mut accumulators = Vec::new();
+        if let Some(network) = network {
+            accumulators = vec![Accumulator::default(); MAX_PLY as usize + 1];
+            accumulators[0] = network.refresh(&root.board);
+        }
+        Searcher {
+            tt,
+            tablebase,
+            stop,
+            total_nodes,
+            nodes: 0,
+            root_move: None,
+            deadline: None,
+            node_limit: None,
+            unstoppable: false,
+            excluded: Vec::new(),
+            killers: [[None; 2]; MAX_PLY as usize],
+            params,
+            network,
+            accumulators,
+            quiet_lines: None,
+        }
+    }
+
+    /// Evaluation of `game`, the position at `ply` of the current line
+    fn evaluate<V: Variant>(&self, game: &Game<V>, ply: i32) -> i32 {
+        match self.network {
+            Some(network) => network.evaluate(&self.accumulators[ply as usize], game.turn) + pocket_material(game),
+            None => evaluate(game, self.params),
+        }
+    }
+
+    /// Updates the network's accumulator for `after`, reached by a move from `game` at `ply`
+    fn make<V: Variant>(&mut self, game: &Game<V>, after: &Game<V>, ply: i32) {
+        if let Some(network) = self.network {
+            let ply = ply as usize;
+            self.accumulators[ply + 1] = network.update(&self.accumulators[ply], &game.board, &after.board);
+        }
+    }
+
+    /// Adds this thread's nodes to the total, and stops every thread once past the deadline
+    /// or the node limit
+    fn flush_nodes(&mut self) {
+        let total = self.total_nodes.fetch_add(self.nodes, Ordering::Relaxed) + self.nodes;
+        self.nodes = 0;
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) || self.node_limit.is_some_and(|limit| total >= limit) {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn visit(&mut self) {
+        self.nodes += 1;
+        if self.nodes >= 1024 {
+            self.flush_nodes();
+        }
+    }
+
+    fn stopped(&self) -> bool {
+        !self.unstoppable && self.stop.load(Ordering::Relaxed)
+    }
+
+    /// Searches the root in a narrow window around the last depth's score, widening it on
+    /// the side the score fell outside until the score lands within
+    fn aspiration<V: Variant>(&mut self, game: &Game<V>, depth: u8, previous: i32) -> i32 {
+        if depth < 4 || previous.abs() >= DECISIVE_SCORE {
+            return self.negamax(game, depth, 0, -MATE_SCORE - 1, MATE_SCORE + 1, false);
+        }
+
+        let mut delta = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = (previous - delta, previous + delta);
+        loop {
+            let score = self.negamax(game, depth, 0, alpha, beta, false);
+            if self.stopped() {
+                return score;
+            }
+            if score <= alpha {
+                alpha = (score - delta).max(-MATE_SCORE - 1);
+            } else if score >= beta {
+                beta = (score + delta).min(MATE_SCORE + 1);
+            } else {
+                return score;
+            }
+            delta *= 2;
+        }
+    }
+
+    /// Principal variation search: the first move gets the full window, the others a null
+    /// window that only proves them worse, searched again in full if they turn out better.
+    /// `after_null` stops two null moves in a row.
+    fn negamax<V: Variant>(&mut self, game: &Game<V>, mut depth: u8, ply: i32, mut alpha: i32, beta: i32, after_null: bool) -> i32 {
+        self.visit();
+        if ply > 0 && game.halfmove_clock >= 100 {
+            return 0;
+        }
+        if ply >= MAX_PLY - 1 {
+            return self.evaluate(game, ply);
+        }
+
+        let pv_node = beta - alpha > 1;
+        let in_check = game.is_king_in_check(game.turn);
+        if in_check && ply < MAX_DEPTH as i32 {
+            depth += 1;
+        }
+
+        let key = game.position_key();
+        let entry = self.tt.probe(key);
+        if let Some(entry) = entry.filter(|entry| ply > 0 && !pv_node && entry.depth >= depth) {
+            let score = score_from_tt(entry.score, ply);
+            match entry.bound {
+                Bound::Exact => return score,
+                Bound::Lower if score >= beta => return score,
+                Bound::Upper if score <= alpha => return score,
+                _ => {},
+            }
+        }
+
+        if V::outcome(game).is_some() {
+            return terminal_score(game, ply);
+        }
+        if ply > 0 {
+            if let Some(wdl) = self.tablebase.and_then(|tablebase| tablebase.probe_wdl(game)) {
+                return match wdl {
+                    Wdl::Win => TABLEBASE_WIN_SCORE - ply,
+                    Wdl::Loss => -TABLEBASE_WIN_SCORE + ply,
+                    _ => 0,
+                };
+            }
+        }
+        if depth == 0 {
+            return self.quiescence(game, ply, alpha, beta);
+        }
+        let mut moves = ordered_moves(game, entry.and_then(|entry| entry.best_move), self.killers[ply as usize]);
+        if ply == 0 {
+            moves.retain(|mv| !self.excluded.contains(mv));
+        }
+
+        let static_eval = self.evaluate(game, ply);
+        if !pv_node && !in_check && beta.abs() < DECISIVE_SCORE {
+            // Reverse futility: far enough above beta that a shallow search won't fall below
+            if depth <= 3 && static_eval - 120 * depth as i32 >= beta {
+                return static_eval;
+            }
+
+            // Razoring: far enough below alpha that only a capture could save it
+            if depth <= 2 && static_eval + 300 * depth as i32 <= alpha {
+                let score = self.quiescence(game, ply, alpha, alpha + 1);
+                if score <= alpha {
+                    return score;
+                }
+            }
+
+            // Null move: if passing still holds beta, a real move would too
+            if V::NULL_MOVE_PRUNING && !after_null && depth >= 3 && static_eval >= beta && has_pieces(game) {
+                let reduction = 2 + depth / 4;
+                self.make(game, game, ply);
+                let score = -self.negamax(&null_move(game), (depth - 1).saturating_sub(reduction), ply + 1, -beta, -beta + 1, true);
+                if self.stopped() {
+                    return 0;
+                }
+                if score >= beta {
+                    return if score >= DECISIVE_SCORE { beta } else { score };
+                }
+            }
+        }
+
+        let original_alpha = alpha;
+        let mut best_score = -MATE_SCORE - 1;
+        let mut best_move = None;
+        // Legal moves so far, the illegal ones are only found out when played
+        let mut legal = 0;
+        for mv in moves {
+            let quiet = game.captured_piece(mv).is_none() && mv.promotion.is_none();
+            let Some(after) = game.position_after(mv) else {
+                continue;
+            };
+            let index = legal;
+            legal += 1;
+            let gives_check = after.is_king_in_check(after.turn);
+            let ordinary = index > 0 && quiet && !in_check && !gives_check;
+
+            // Futility: a quiet move near the leaves won't make up a large deficit
+            if ordinary && !pv_node && depth <= 2 && alpha.abs() < DECISIVE_SCORE && static_eval + 150 * depth as i32 <= alpha {
+                continue;
+            }
+            self.make(game, &after, ply);
+
+            let score = if index == 0 {
+                -self.negamax(&after, depth - 1, ply + 1, -beta, -alpha, false)
+            } else {
+                // Late move reductions: quiet moves ordered late are searched shallower first
+                let reduction = match ordinary && !pv_node && depth >= 3 && index >= 3 && alpha.abs() < DECISIVE_SCORE {
+                    true => (1 + (index as u8) / 8 + depth / 8).min(depth - 2),
+                    false => 0,
+                };
+                let mut score = -self.negamax(&after, depth - 1 - reduction, ply + 1, -alpha - 1, -alpha, false);
+                if reduction > 0 && score > alpha {
+                    score = -self.negamax(&after, depth - 1, ply + 1, -alpha - 1, -alpha, false);
+                }
+                if score > alpha && score < beta {
+                    score = -self.negamax(&after, depth - 1, ply + 1, -beta, -alpha, false);
+                }
+                score
+            };
+            // A stopped search returns made up scores, which must not reach the table
+            if self.stopped() {
+                return 0;
+            }
+            if score > best_score {
+                best_score = score;
+                best_move = Some(mv);
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                let killers = &mut self.killers[ply as usize];
+                if quiet && killers[0] != Some(mv) {
+                    *killers = [Some(mv), killers[0]];
+                }
+                break;
+            }
+        }
+
+        if legal == 0 {
+            return terminal_score(game, ply);
+        }
+        if ply == 0 {
+            self.root_move = best_move;
+            // The root's entry keeps the best move over every root move, for the next depth
+            if !self.excluded.is_empty() {
+                return best_score;
+            }
+        }
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.tt.store(key, best_move, score_to_tt(best_score, ply), depth, bound);
+        best_score
+    }
+
+    /// Searches captures and promotions only until the position is quiet, so the
+    /// evaluation is never taken in the middle of an exchange. The side to move may
+    /// "stand pat" on the evaluation instead, unless it is in check. Out of check, moves are
+    /// only checked for legality when tried, so a stalemate by pins goes unnoticed here.
+    fn quiescence<V: Variant>(&mut self, game: &Game<V>, ply: i32, mut alpha: i32, beta: i32) -> i32 {
+        self.visit();
+        if let Some(lines) = &mut self.quiet_lines {
+            lines[ply as usize].clear();
+        }
+        if ply >= MAX_PLY - 1 {
+            return self.evaluate(game, ply);
+        }
+
+        if V::outcome(game).is_some() {
+            return terminal_score(game, ply);
+        }
+        let moves = ordered_moves(game, None, [None; 2]);
+        if moves.is_empty() {
+            return terminal_score(game, ply);
+        }
+
+        let in_check = game.is_king_in_check(game.turn);
+        let mut best_score = -MATE_SCORE - 1;
+        if !in_check {
+            best_score = self.evaluate(game, ply);
+            if best_score >= beta {
+                return best_score;
+            }
+            alpha = alpha.max(best_score);
+        }
+
+        let mut legal = false;
+        for mv in moves {
+            // Out of check only captures and promotions are tried, and no captures that lose material
+            if !in_check && (game.captured_piece(mv).is_none() && mv.promotion.is_none() || game.see(mv) < 0) {
+                continue;
+            }
+            let Some(after) = game.position_after(mv) else {
+                continue;
+            };
+            legal = true;
+            self.make(game, &after, ply);
+            let score = -self.quiescence(&after, ply + 1, -beta, -alpha);
+            if self.stopped() {
+                return 0;
+            }
+            if score > best_score {
+                if let Some(lines) = &mut self.quiet_lines {
+                    let (line, deeper) = lines[ply as usize..].split_first_mut().unwrap();
+                    line.clear();
+                    line.push(mv);
+                    line.extend_from_slice(&deeper[0]);
+                }
+            }
+            best_score = best_score.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        // In check every move was tried, so none being legal means there are none
+        if in_check && !legal {
+            return terminal_score(game, ply);
+        }
+        best_score
+    }
+
+    /// This thread's best root move, then the best moves stored in the transposition table.
+    /// The root move is kept apart as other threads may overwrite the root's table entry.
+    fn principal_variation<V: Variant>(&self, game: &Game<V>, depth: u8) -> Vec<Move> {
+        let mut pv = Vec::new();
+        let mut game = *game;
+        let mut next = self.root_move;
+        while let Some(mv) = next {
+            if pv.len() >= depth as usize || !game.legal_moves().contains(&mv) || game.play_unchecked(mv).is_err() {
+                break;
+            }
+            pv.push(mv);
+            next = self.tt.probe(game.position_key()).and_then(|entry| entry.best_move);
+        }
+        pv
+    }
+}
+
+// Functions
+
+/// Runs the engine's quiescence search on `game` with the hand-written evaluation and
+/// returns the position its best line ends in. Texel tuning fits the weights to these.
+pub fn quiet_leaf<V: Variant>(game: &Game<V>, params: &EvalParams) -> Game<V> {
+    let (tt, stop, total_nodes) = (TranspositionTable::new(0), AtomicBool::new(false), AtomicU64::new(0));
+    let mut searcher = Searcher::new(game, &tt, None, params, None, &stop, &total_nodes);
+    searcher.quiet_lines = Some(vec![Vec::new(); MAX_PLY as usize]);
+    searcher.quiescence(game, 0, -MATE_SCORE - 1, MATE_SCORE + 1);
+
+    let lines = searcher.quiet_lines.take().unwrap_or_default();
+    let mut leaf = *game;
+    for &mv in &lines[0] {
+        if leaf.play_unchecked(mv).is_err() {
+            break;
+        }
+    }
+    leaf
+}
+
+/// Moves to mate for a mate score, negative when the side to move is getting mated
+pub fn mate_in(score: i32) -> Option<i32> {
+    if score.abs() < TABLEBASE_WIN_SCORE {
+        return None;
+    }
+    let plies = MATE_SCORE - score.abs();
+    Some(if score > 0 { (plies + 1) / 2 } else { -(plies + 1) / 2 })
+}
+
+/// Mate and tablebase scores are stored relative to the position, not the root
+fn score_to_tt(score: i32, ply: i32) -> i32 {
+    if score >= DECISIVE_SCORE {
+        score + ply
+    } else if score <= -DECISIVE_SCORE {
+        score - ply
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: i32) -> i32 {
+    if score >= DECISIVE_SCORE {
+        score - ply
+    } else if score <= -DECISIVE_SCORE {
+        score + ply
+    } else {
+        score
+    }
+}
+
+/// Score for a position with no moves left, from the side to move's point of view
+fn terminal_score<V: Variant>(game: &Game<V>, ply: i32) -> i32 {
+    let status = V::outcome(game).unwrap_or_else(|| V::no_moves_status(game));
+    match status.winner() {
+        Some(color) if color == game.turn => MATE_SCORE - ply,
+        Some(_) => -MATE_SCORE + ply,
+        None => 0,
+    }
+}
+
+/// Pseudo-legal moves with the transposition table's move first, then captures that don't
+/// lose material, most valuable victims first, then the killer moves and other quiet moves,
+/// and captures that lose material last. Whether a move is legal is left to the search.
+fn ordered_moves<V: Variant>(game: &Game<V>, tt_move: Option<Move>, killers: [Option<Move>; 2]) -> Vec<Move> {
+    let mut moves = V::filter_legal(game, game.pseudo_legal_moves());
+    moves.sort_by_cached_key(|&mv| {
+        if Some(mv) == tt_move {
+            return i32::MIN;
+        }
+        match game.captured_piece(mv) {
+            Some(captured) => {
+                let attacker = game.board.get(mv.start).map_or(0, |piece| piece_value(piece.piece_type));
+                let see = match piece_value(captured.piece_type) >= attacker {
+                    true => 0,
+                    false => game.see(mv),
+                };
+                match see >= 0 {
+                    true => -10 * piece_value(captured.piece_type) + attacker / 10 - 1_000,
+                    false => -see,
+                }
+            },
+            None if killers[0] == Some(mv) => -2,
+            None if killers[1] == Some(mv) => -1,
+            None => 0,
+        }
+    });
+    moves
+}
+
+/// The position with the side to move passing, for null move pruning
+fn null_move<V: Variant>(game: &Game<V>) -> Game<V> {
+    let mut after = *game;
+    after.en_passant = None;
+    after.halfmove_clock += 1;
+    after.turn = after.turn.swap();
+    after
+}
+
+/// Whether the side to move has more than pawns and a king, without which passing
+/// often is the best move (zugzwang)
+fn has_pieces<V: Variant>(game: &Game<V>) -> bool {
+    game.pieces(game.turn).any(|piece| !matches!(piece.piece_type, PieceType::Pawn(_) | PieceType::King(_)))
+}
+
+/// Balance of the pieces held for dropping, for the side to move
+fn pocket_material<V: Variant>(game: &Game<V>) -> i32 {
+    [(game.turn, 1), (game.turn.swap(), -1)]
+        .into_iter()
+        .map(|(color, sign)| sign * game.pocket(color).pieces().map(piece_value).sum::<i32>())
+        .sum()
+}
+
+pub fn piece_value(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::Pawn(_) => 100,
+        PieceType::Knight => 320,
+        PieceType::Bishop => 330,
+        PieceType::Rook(_) => 500,
+        PieceType::Queen => 900,
+        PieceType::King(_) => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Kiwipete, a middlegame full of captures, checks and castling
+    const POSITION: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    #[test]
+    fn single_thread_searches_repeat() {
+        let game: Game = Game::from_fen(POSITION).unwrap();
+        let mut results = Vec::new();
+        for _ in 0..2 {
+            let mut engine = Engine { threads: 1, ..Engine::default() };
+            let (best, score) = engine.search(&game, 3);
+            results.push((best, score, engine.nodes));
+        }
+        assert!(results[0].0.is_some());
+        assert!(results[0].2 > 0);
+        assert_eq!(results[0], results[1]);
+    }
+
+    #[test]
+    fn multi_threaded_search_returns_a_legal_move() {
+        let game: Game = Game::from_fen(POSITION).unwrap();
+        let mut engine = Engine { threads: 4, ..Engine::default() };
+        let (best, _) = engine.search(&game, 2);
+        assert!(game.legal_moves().contains(&best.unwrap()));
+    }
+
+    #[test]
+    fn pruned_searches_still_find_mates() {
+        // Mate in two with a quiet first move, 1. Kc5, and one with a sacrifice, 1. Nf6+ gxf6 2. Bxf7#
+        for (fen, first) in [
+            ("1Q6/8/8/k2K4/8/8/8/8 w - - 0 1", None),
+            ("r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1", Some("d5f6")),
+        ] {
+            let game: Game = Game::from_fen(fen).unwrap();
+            let (best, score) = Engine::default().search(&game, 4);
+            assert_eq!(mate_in(score), Some(2), "{fen}");
+            if let Some(first) = first {
+                assert_eq!(best, first.parse().ok());
+            }
+        }
+    }
+
+    #[test]
+    fn reductions_do_not_hide_the_shortest_mate() {
+        // Mate in three with quiet king moves such as 1. Kd5 Ka5 2. Kc5, where checks take a move longer
+        let game: Game = Game::from_fen("1Q6/8/k7/4K3/8/8/8/8 w - - 11 7").unwrap();
+        let (_, score) = Engine::default().search(&game, 7);
+        assert_eq!(mate_in(score), Some(3));
+    }
+}
@@ -1,14 +1,16 @@
-use std::{error::Error, fmt::Display, i8};
+use std::{error::Error, fmt::Display};
 
 // GameError
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GameError {
     GenericError,
     NoPieceOnStartSquare,
     SameColorCapture,
     InvalidMove,
     InvalidMoveCheck,
+    InvalidDrop,
     // TODO: Add MORE
 }
 
@@ -32,11 +34,524 @@ impl Display for GameError {
             GameError::InvalidMoveCheck => {
                 write!(f, "King is in check, cannot move")
             },
+            GameError::InvalidDrop => {
+                write!(f, "Cannot drop that piece there")
+            },
+        }
+    }
+}
+
+// FenError
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FenError {
+    MissingField,
+    InvalidBoard,
+    InvalidTurn,
+    InvalidCastling,
+    InvalidEnPassant,
+    InvalidClock,
+}
+
+impl Error for FenError {}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FenError::MissingField => {
+                write!(f, "FEN is missing the board or side to move")
+            },
+            FenError::InvalidBoard => {
+                write!(f, "FEN piece placement is not a valid board")
+            },
+            FenError::InvalidTurn => {
+                write!(f, "FEN side to move must be 'w' or 'b'")
+            },
+            FenError::InvalidCastling => {
+                write!(f, "FEN castling field does not match the board")
+            },
+            FenError::InvalidEnPassant => {
+                write!(f, "FEN en passant square is not valid")
+            },
+            FenError::InvalidClock => {
+                write!(f, "FEN move counters must be numbers")
+            },
+        }
+    }
+}
+
+// BookError
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BookError {
+    Unreadable,
+    InvalidLength,
+}
+
+impl Error for BookError {}
+
+impl Display for BookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BookError::Unreadable => {
+                write!(f, "Opening book file could not be read")
+            },
+            BookError::InvalidLength => {
+                write!(f, "Opening book is not made of 16 byte Polyglot entries")
+            },
+        }
+    }
+}
+
+// TablebaseError
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TablebaseError {
+    Unreadable,
+    NoTables,
+}
+
+impl Error for TablebaseError {}
+
+impl Display for TablebaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TablebaseError::Unreadable => {
+                write!(f, "Tablebase directory could not be read")
+            },
+            TablebaseError::NoTables => {
+                write!(f, "No Syzygy .rtbw or .rtbz files found in the tablebase path")
+            },
+        }
+    }
+}
+
+// NnueError
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NnueError {
+    Unreadable,
+    InvalidHeader,
+    UnsupportedVersion(u32),
+    InvalidSize,
+}
+
+impl Error for NnueError {}
+
+impl Display for NnueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NnueError::Unreadable => {
+                write!(f, "Network file could not be read")
+            },
+            NnueError::InvalidHeader => {
+                write!(f, "Network file does not start with the RCNN header")
+            },
+            NnueError::UnsupportedVersion(version) => {
+                write!(f, "Network file version {version} is not supported")
+            },
+            NnueError::InvalidSize => {
+                write!(f, "Network file size does not match its hidden layer size")
+            },
+        }
+    }
+}
+
+// ParamsError
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ParamsError {
+    Unreadable,
+    Unwritable,
+    InvalidLine(usize),
+}
+
+impl Error for ParamsError {}
+
+impl Display for ParamsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParamsError::Unreadable => {
+                write!(f, "Evaluation parameter file could not be read")
+            },
+            ParamsError::Unwritable => {
+                write!(f, "Evaluation parameter file could not be written")
+            },
+            ParamsError::InvalidLine(line) => {
+                write!(f, "Evaluation parameter file line {line} is not a known parameter")
+            },
         }
     }
 }
 
-// GameResult 
+// TuneError
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TuneError {
+    Unreadable,
+    InvalidLine(usize),
+    NoPositions,
+}
+
+impl Error for TuneError {}
+
+impl Display for TuneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TuneError::Unreadable => {
+                write!(f, "Tuning positions file could not be read")
+            },
+            TuneError::InvalidLine(line) => {
+                write!(f, "Tuning positions line {line} is not a FEN followed by a result")
+            },
+            TuneError::NoPositions => {
+                write!(f, "Tuning positions file has no positions")
+            },
+        }
+    }
+}
+
+// MatchError
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MatchError {
+    /// An engine description had an unknown or malformed `key=value` setting
+    InvalidEngine(String),
+    InvalidSprt,
+    Params(ParamsError),
+    Network(NnueError),
+    Tablebase(TablebaseError),
+    Book(BookError),
+    OpeningsUnreadable,
+    InvalidOpening(usize),
+    NoOpenings,
+    PgnUnwritable,
+}
+
+impl Error for MatchError {}
+
+impl Display for MatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatchError::InvalidEngine(setting) => {
+                write!(f, "Engine setting {setting} is not one of name, params, nnue, syzygy, book, depth, hash or threads")
+            },
+            MatchError::InvalidSprt => {
+                write!(f, "SPRT needs elo0,elo1 with elo0 below elo1, optionally followed by alpha,beta")
+            },
+            MatchError::Params(error) => {
+                write!(f, "{error}")
+            },
+            MatchError::Network(error) => {
+                write!(f, "{error}")
+            },
+            MatchError::Tablebase(error) => {
+                write!(f, "{error}")
+            },
+            MatchError::Book(error) => {
+                write!(f, "{error}")
+            },
+            MatchError::OpeningsUnreadable => {
+                write!(f, "Openings file could not be read")
+            },
+            MatchError::InvalidOpening(line) => {
+                write!(f, "Openings file line {line} is not a valid FEN")
+            },
+            MatchError::NoOpenings => {
+                write!(f, "Openings file has no positions")
+            },
+            MatchError::PgnUnwritable => {
+                write!(f, "PGN file could not be written")
+            },
+        }
+    }
+}
+
+// PgnError
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PgnError {
+    Unreadable,
+    Unwritable,
+    InvalidTag,
+    UnterminatedComment,
+    NoGame,
+    InvalidFen,
+    /// Holds the move as written
+    IllegalMove(String),
+    /// Holds the Variant tag
+    UnsupportedVariant(String),
+}
+
+impl Error for PgnError {}
+
+impl Display for PgnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgnError::Unreadable => {
+                write!(f, "PGN file could not be read")
+            },
+            PgnError::Unwritable => {
+                write!(f, "PGN file could not be written")
+            },
+            PgnError::InvalidTag => {
+                write!(f, "PGN tag is not of the form [Name \"value\"]")
+            },
+            PgnError::UnterminatedComment => {
+                write!(f, "PGN comment or variation is never closed")
+            },
+            PgnError::NoGame => {
+                write!(f, "PGN text has no game in it")
+            },
+            PgnError::InvalidFen => {
+                write!(f, "PGN FEN tag is not a valid position")
+            },
+            PgnError::IllegalMove(san) => {
+                write!(f, "PGN move {san} is not legal in its position")
+            },
+            PgnError::UnsupportedVariant(variant) => {
+                write!(f, "PGN variant {variant} is not supported")
+            },
+        }
+    }
+}
+
+// CliError
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum CliError {
+    UnknownCommand(String),
+    UnknownOption(String),
+    UnknownVariant(String),
+    /// Holds the option missing its value
+    MissingValue(String),
+    /// Holds the option and the value it was given, then what it expects
+    InvalidValue(String, String, &'static str),
+    /// Holds the command and what it is missing
+    MissingArgument(String, &'static str),
+    Unwritable(String),
+    /// Holds an option and what it can't be used with
+    Conflicting(String, String),
+    /// Holds an option and the command that doesn't take it
+    NotForCommand(String, String),
+}
+
+impl Error for CliError {}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::UnknownCommand(command) => {
+                write!(f, "Unknown command {command}, see --help")
+            },
+            CliError::UnknownOption(option) => {
+                write!(f, "Unknown option {option}, see --help")
+            },
+            CliError::UnknownVariant(variant) => {
+                write!(f, "Unknown variant {variant}, see --help")
+            },
+            CliError::MissingValue(option) => {
+                write!(f, "Option {option} needs a value")
+            },
+            CliError::InvalidValue(option, value, expected) => {
+                write!(f, "Option {option} was given {value} but needs {expected}")
+            },
+            CliError::MissingArgument(command, argument) => {
+                write!(f, "{command} needs {argument}")
+            },
+            CliError::Unwritable(path) => {
+                write!(f, "{path} could not be written")
+            },
+            CliError::Conflicting(option, other) => {
+                write!(f, "Option {option} can't be used with {other}")
+            },
+            CliError::NotForCommand(option, command) => {
+                write!(f, "Option {option} is not used by {command}, see --help {command}")
+            },
+        }
+    }
+}
+
+// SaveError
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum SaveError {
+    Unreadable,
+    Unwritable,
+    NotASave,
+    UnsupportedVersion(u32),
+    /// Holds the line number
+    InvalidLine(usize),
+    MissingField(&'static str),
+    /// Holds the variant the save is for
+    WrongVariant(String),
+    InvalidFen,
+    InvalidClock,
+    /// Holds the move as written
+    IllegalMove(String),
+}
+
+impl Error for SaveError {}
+
+impl Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Unreadable => {
+                write!(f, "Saved game file could not be read")
+            },
+            SaveError::Unwritable => {
+                write!(f, "Saved game file could not be written")
+            },
+            SaveError::NotASave => {
+                write!(f, "Saved game file does not start with a rust_chess save header")
+            },
+            SaveError::UnsupportedVersion(version) => {
+                write!(f, "Saved game version {version} is newer than this version of rust_chess reads")
+            },
+            SaveError::InvalidLine(line) => {
+                write!(f, "Saved game line {line} is not of the form key: value")
+            },
+            SaveError::MissingField(field) => {
+                write!(f, "Saved game has no {field}")
+            },
+            SaveError::WrongVariant(variant) => {
+                write!(f, "Saved game is {variant}, not the variant being played")
+            },
+            SaveError::InvalidFen => {
+                write!(f, "Saved game start is not a valid FEN")
+            },
+            SaveError::InvalidClock => {
+                write!(f, "Saved game time control or clock times are not valid")
+            },
+            SaveError::IllegalMove(san) => {
+                write!(f, "Saved game move {san} is not legal in its position")
+            },
+        }
+    }
+}
+
+// CommandError
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum CommandError {
+    /// Holds input that is neither a command nor a legal move
+    Unknown(String),
+    /// Holds the command and what it is missing
+    MissingArgument(&'static str, &'static str),
+    InvalidSquare(String),
+    NoPiece(String),
+    NothingToUndo,
+    NothingToRedo,
+    Unreadable(String),
+    Unwritable(String),
+    Pgn(PgnError),
+    Save(SaveError),
+    /// A move typed while the network opponent is to move
+    NotYourMove,
+    /// Holds a command that can't be used in a network game
+    Unavailable(String),
+}
+
+impl Error for CommandError {}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::Unknown(input) => {
+                write!(f, "{input} is not a command or a legal move, type help for the commands")
+            },
+            CommandError::MissingArgument(command, argument) => {
+                write!(f, "{command} needs {argument}")
+            },
+            CommandError::InvalidSquare(square) => {
+                write!(f, "{square} is not a square")
+            },
+            CommandError::NoPiece(square) => {
+                write!(f, "There is no piece of the side to move on {square}")
+            },
+            CommandError::NothingToUndo => {
+                write!(f, "There are no moves to take back")
+            },
+            CommandError::NothingToRedo => {
+                write!(f, "There are no taken back moves to play again")
+            },
+            CommandError::Unreadable(path) => {
+                write!(f, "{path} could not be read")
+            },
+            CommandError::Unwritable(path) => {
+                write!(f, "{path} could not be written")
+            },
+            CommandError::Pgn(error) => {
+                write!(f, "{error}")
+            },
+            CommandError::Save(error) => {
+                write!(f, "{error}")
+            },
+            CommandError::NotYourMove => {
+                write!(f, "It is your opponent's move")
+            },
+            CommandError::Unavailable(command) => {
+                write!(f, "{command} is not available in a network game")
+            },
+        }
+    }
+}
+
+// NetError
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum NetError {
+    /// Holds the port
+    CannotListen(u16),
+    /// Holds the address
+    CannotConnect(String),
+    NotAGame,
+    /// Holds the other side's protocol version
+    UnsupportedVersion(u32),
+    Disconnected,
+    Save(SaveError),
+}
+
+impl Error for NetError {}
+
+impl Display for NetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetError::CannotListen(port) => {
+                write!(f, "Cannot listen for a network game on port {port}")
+            },
+            NetError::CannotConnect(address) => {
+                write!(f, "Cannot connect to a network game at {address}")
+            },
+            NetError::NotAGame => {
+                write!(f, "The other side is not a rust_chess network game")
+            },
+            NetError::UnsupportedVersion(version) => {
+                write!(f, "The other side speaks network protocol version {version}, which this version of rust_chess does not")
+            },
+            NetError::Disconnected => {
+                write!(f, "The network connection was lost")
+            },
+            NetError::Save(error) => {
+                write!(f, "The game sent over the network is unreadable: {error}")
+            },
+        }
+    }
+}
+
+// GameResult
 
 #[derive(Debug)]
 pub enum GameResult {
@@ -46,9 +561,11 @@ pub enum GameResult {
     Promotion,
     Normal,
     Capture,
+    Drop,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConversionError;
 
 impl Display for ConversionError {
@@ -66,23 +583,9 @@ pub trait Convertable {
 impl Convertable for i8 {
     fn convert_to_u8(&self) -> Result<u8, Box<dyn Error>> {
         if *self < 0 {
-            return Err(Box::new(ConversionError));
-        } else {
-            return Ok(*self as u8)
-        }
-    }
-}
-
-trait Ceiling {
-    fn ceil(self, max: u8) -> u8;
-}
-
-impl Ceiling for u8 {
-    fn ceil(self, max: u8) -> u8 {
-        if self > max {
-            max
+            Err(Box::new(ConversionError))
         } else {
-            self
+            Ok(*self as u8)
         }
     }
 }
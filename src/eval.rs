@@ -0,0 +1,158 @@
+// Imports
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::errors::*;
+use crate::game::*;
+use crate::piece::*;
+use crate::variant::*;
+
+/// Piece names in parameter files, in `EvalParams` order
+const KIND_NAMES: [&str; 6] = ["pawn", "knight", "bishop", "rook", "queen", "king"];
+/// Material values plus a square table per piece kind
+pub const PARAM_COUNT: usize = 5 + 6 * 64;
+
+// Custom EvalParams Type Data
+
+/// Weights of the hand-crafted evaluation: a value per piece kind (kings have none) and a
+/// bonus per piece kind and square, from White's side of the board with rank 8 first.
+/// Black's pieces use the same tables mirrored.
+/// The defaults count material only. `tune` fits them to game results, and they are read
+/// from and written to a plain text file of `value <piece> <centipawns>` and
+/// `square <piece> <64 centipawns>` lines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalParams {
+    pub values: [i32; 5],
+    pub squares: [[i32; 64]; 6],
+}
+
+// Custom EvalParams Type Traits
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        EvalParams {
+            values: [100, 320, 330, 500, 900],
+            squares: [[0; 64]; 6],
+        }
+    }
+}
+
+impl Display for EvalParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "# rust_chess evaluation parameters")?;
+        for (name, value) in KIND_NAMES.iter().zip(self.values) {
+            writeln!(f, "value {name} {value}")?;
+        }
+        for (name, squares) in KIND_NAMES.iter().zip(&self.squares) {
+            let squares: Vec<String> = squares.iter().map(|bonus| bonus.to_string()).collect();
+            writeln!(f, "square {name} {}", squares.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for EvalParams {
+    type Err = ParamsError;
+
+    /// Reads the text format written by `Display`. Parameters left out keep their defaults.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut params = EvalParams::default();
+        for (number, line) in s.lines().enumerate() {
+            let invalid = || ParamsError::InvalidLine(number + 1);
+            let mut words = line.split_whitespace();
+            let (Some(field), Some(name)) = (words.next(), words.next()) else {
+                continue;
+            };
+            if field.starts_with('#') {
+                continue;
+            }
+            let kind = KIND_NAMES.iter().position(|kind| *kind == name).ok_or_else(invalid)?;
+            let numbers = words
+                .map(|word| word.parse::<i32>())
+                .collect::<Result<Vec<i32>, _>>()
+                .map_err(|_| invalid())?;
+            match (field, numbers.as_slice()) {
+                ("value", &[value]) if kind < 5 => params.values[kind] = value,
+                ("square", squares) if squares.len() == 64 => params.squares[kind].copy_from_slice(squares),
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(params)
+    }
+}
+
+// Impl EvalParams
+
+impl EvalParams {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<EvalParams, ParamsError> {
+        fs::read_to_string(path).map_err(|_| ParamsError::Unreadable)?.parse()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ParamsError> {
+        fs::write(path, self.to_string()).map_err(|_| ParamsError::Unwritable)
+    }
+
+    /// The parameters as one flat list, values first, for tuning
+    pub fn to_vec(&self) -> Vec<i32> {
+        self.values.iter().chain(self.squares.iter().flatten()).copied().collect()
+    }
+
+    /// The parameter at `index` of `to_vec`
+    pub fn get(&self, index: usize) -> i32 {
+        match index {
+            0..5 => self.values[index],
+            _ => self.squares[(index - 5) / 64][(index - 5) % 64],
+        }
+    }
+
+    pub fn from_slice(params: &[i32]) -> EvalParams {
+        let mut result = EvalParams::default();
+        result.values.copy_from_slice(&params[..5]);
+        for (squares, chunk) in result.squares.iter_mut().zip(params[5..].chunks(64)) {
+            squares.copy_from_slice(chunk);
+        }
+        result
+    }
+}
+
+// Functions
+
+/// Evaluation in centipawns for the side to move, pieces held for dropping included
+pub fn evaluate<V: Variant>(game: &Game<V>, params: &EvalParams) -> i32 {
+    let white: i32 = features(game).map(|(index, weight)| weight * params.get(index)).sum();
+    match game.turn {
+        PieceColor::White => white,
+        PieceColor::Black => -white,
+    }
+}
+
+/// The evaluation's inputs for `game`: for each parameter that applies, its index in
+/// `EvalParams::to_vec` and how often it counts for White (positive) or Black (negative)
+pub fn features<V: Variant>(game: &Game<V>) -> impl Iterator<Item = (usize, i32)> + '_ {
+    let on_board = game.board.board.iter().flatten().flatten().flat_map(|piece| {
+        let (sign, square) = match piece.color {
+            PieceColor::White => (1, piece.coordinates),
+            PieceColor::Black => (-1, Coordinates(7 - piece.coordinates.0, piece.coordinates.1)),
+        };
+        let kind = kind_index(piece.piece_type);
+        let value = (kind < 5).then_some((kind, sign));
+        value.into_iter().chain([(5 + kind * 64 + (square.0 * 8 + square.1) as usize, sign)])
+    });
+    let in_pockets = [(PieceColor::White, 1), (PieceColor::Black, -1)].into_iter().flat_map(move |(color, sign)| {
+        game.pocket(color).pieces().map(move |piece_type| (kind_index(piece_type), sign))
+    });
+    on_board.chain(in_pockets)
+}
+
+fn kind_index(piece_type: PieceType) -> usize {
+    match piece_type {
+        PieceType::Pawn(_) => 0,
+        PieceType::Knight => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook(_) => 3,
+        PieceType::Queen => 4,
+        PieceType::King(_) => 5,
+    }
+}
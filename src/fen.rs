@@ -0,0 +1,387 @@
+// Imports
+use crate::board::*;
+use crate::errors::*;
+use crate::game::*;
+use crate::moves::*;
+use crate::piece::*;
+use crate::square_set::*;
+use crate::variant::*;
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+// Impl FEN conversion for Game
+
+impl<V: Variant> Game<V> {
+    /// Parses a FEN string. The castling field may use classical `KQkq`, X-FEN
+    /// (`KQkq` for the outermost rook, file letters otherwise) or Shredder-FEN
+    /// (file letters only, e.g. `HAha`). Non-classical castling rights turn on Chess960 rules.
+    /// The move counters are optional and default to `0 1`.
+    pub fn from_fen(fen: &str) -> Result<Game<V>, FenError> {
+        let mut fields = fen.split_whitespace();
+        let placement = fields.next().ok_or(FenError::MissingField)?;
+        let turn = fields.next().ok_or(FenError::MissingField)?;
+        let castling = fields.next().unwrap_or("-");
+        let en_passant = fields.next().unwrap_or("-");
+        let halfmove = fields.next().unwrap_or("0");
+        let fullmove = fields.next().unwrap_or("1");
+
+        let (placement, pockets) = split_pockets(placement);
+        let (board, promoted) = parse_placement(placement)?;
+        let mut game = Game {
+            board,
+            promoted,
+            ..Game::default()
+        };
+        game.parse_pockets(pockets)?;
+
+        game.turn = match turn {
+            "w" => PieceColor::White,
+            "b" => PieceColor::Black,
+            _ => return Err(FenError::InvalidTurn),
+        };
+
+        game.parse_castling(castling)?;
+        game.parse_en_passant(en_passant)?;
+
+        game.halfmove_clock = halfmove.parse().map_err(|_| FenError::InvalidClock)?;
+        let fullmove: u16 = fullmove.parse().map_err(|_| FenError::InvalidClock)?;
+        game.move_num = fullmove.max(1).saturating_sub(1) * 2;
+        if game.turn == PieceColor::Black {
+            game.move_num += 1;
+        }
+        game.turn_num = game.move_num / 2;
+
+        Ok(game)
+    }
+
+    /// Writes the position as X-FEN, which is plain FEN for classical positions
+    pub fn to_fen(&self) -> String {
+        self.fen_string(false)
+    }
+
+    /// Writes the position as Shredder-FEN, naming castling rooks by file
+    pub fn to_shredder_fen(&self) -> String {
+        self.fen_string(true)
+    }
+
+    fn fen_string(&self, shredder: bool) -> String {
+        let mut placement = String::new();
+        for (row_num, row) in self.board.board.iter().enumerate() {
+            let mut empty = 0;
+            for piece in row {
+                match piece {
+                    None => empty += 1,
+                    Some(piece) => {
+                        if empty > 0 {
+                            placement.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        placement.push_str(&piece.to_string());
+                        if V::POCKETS && self.promoted.contains(piece.coordinates) {
+                            placement.push('~');
+                        }
+                    },
+                }
+            }
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+            }
+            if row_num != 7 {
+                placement.push('/');
+            }
+        }
+        if V::POCKETS {
+            let white = self.pocket(PieceColor::White).to_string().to_ascii_uppercase();
+            let black = self.pocket(PieceColor::Black);
+            placement.push_str(&format!("[{white}{black}]"));
+        }
+
+        let turn = match self.turn {
+            PieceColor::White => "w",
+            PieceColor::Black => "b",
+        };
+
+        let mut castling = self.castling_field(PieceColor::White, shredder);
+        castling.push_str(&self.castling_field(PieceColor::Black, shredder));
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
+        let en_passant = match self.en_passant {
+            Some(coords) => array_notation_to_chess_notation(coords),
+            None => "-".to_owned(),
+        };
+
+        format!(
+            "{placement} {turn} {castling} {en_passant} {} {}",
+            self.halfmove_clock,
+            self.move_num / 2 + 1
+        )
+    }
+
+    fn castling_field(&self, color: PieceColor, shredder: bool) -> String {
+        let row = back_rank(color);
+        let mut field = String::new();
+        let Some(king) = self.back_rank_piece(color, |piece_type| piece_type == PieceType::King(false)) else {
+            return field;
+        };
+        let king = king.coordinates;
+
+        let rooks: Vec<u8> = (0..8u8)
+            .rev()
+            .filter(|&file| {
+                matches!(self.board.get(Coordinates(row, file)),
+                    Some(piece) if piece.color == color && piece.piece_type == PieceType::Rook(false))
+            })
+            .collect();
+
+        // Kingside rights come first, then queenside, as in "KQkq" and "HAha"
+        for file in rooks.iter().filter(|&&file| file > king.1).chain(rooks.iter().filter(|&&file| file < king.1)) {
+            let kingside = *file > king.1;
+            let outermost = !(0..8u8).any(|other| {
+                let further = if kingside { other > *file } else { other < *file };
+                further && matches!(self.board.get(Coordinates(row, other)),
+                    Some(piece) if piece.color == color && matches!(piece.piece_type, PieceType::Rook(_)))
+            });
+
+            let letter = if shredder || !outermost {
+                (b'A' + file) as char
+            } else if kingside {
+                'K'
+            } else {
+                'Q'
+            };
+
+            field.push(match color {
+                PieceColor::White => letter,
+                PieceColor::Black => letter.to_ascii_lowercase(),
+            });
+        }
+        field
+    }
+
+    fn back_rank_piece(&self, color: PieceColor, is_wanted: impl Fn(PieceType) -> bool) -> Option<Piece> {
+        let row = back_rank(color);
+        (0..8u8)
+            .filter_map(|file| self.board.get(Coordinates(row, file)))
+            .find(|piece| piece.color == color && is_wanted(piece.piece_type))
+    }
+
+    fn parse_pockets(&mut self, pockets: &str) -> Result<(), FenError> {
+        for letter in pockets.chars().filter(|&letter| letter != '-') {
+            let color = if letter.is_ascii_uppercase() {
+                PieceColor::White
+            } else {
+                PieceColor::Black
+            };
+            let piece_type = piece_type_from_letter(letter)
+                .filter(|piece_type| !matches!(piece_type, PieceType::King(_)))
+                .ok_or(FenError::InvalidBoard)?;
+            self.pocket_mut(color).add(piece_type);
+        }
+        Ok(())
+    }
+
+    fn parse_castling(&mut self, castling: &str) -> Result<(), FenError> {
+        if castling == "-" || !V::CASTLING {
+            return Ok(());
+        }
+
+        for letter in castling.chars() {
+            let color = if letter.is_ascii_uppercase() {
+                PieceColor::White
+            } else {
+                PieceColor::Black
+            };
+            let row = back_rank(color);
+            let king = self
+                .back_rank_piece(color, |piece_type| matches!(piece_type, PieceType::King(_)))
+                .ok_or(FenError::InvalidCastling)?
+                .coordinates;
+
+            let is_own_rook = |file: u8| {
+                matches!(self.board.get(Coordinates(row, file)),
+                    Some(piece) if piece.color == color && matches!(piece.piece_type, PieceType::Rook(_)))
+            };
+            let rook_file = match letter.to_ascii_uppercase() {
+                'K' => ((king.1 + 1)..8).rev().find(|&file| is_own_rook(file)),
+                'Q' => (0..king.1).find(|&file| is_own_rook(file)),
+                file @ 'A'..='H' => Some(file as u8 - b'A').filter(|&file| file != king.1 && is_own_rook(file)),
+                _ => None,
+            }
+            .ok_or(FenError::InvalidCastling)?;
+
+            if !matches!(letter.to_ascii_uppercase(), 'K' | 'Q') ||
+               king.1 != 4 || (rook_file != 0 && rook_file != 7) {
+                self.chess960 = true;
+            }
+
+            self.at(king).as_mut().unwrap().piece_type = PieceType::King(false);
+            self.at(Coordinates(row, rook_file)).as_mut().unwrap().piece_type = PieceType::Rook(false);
+        }
+        Ok(())
+    }
+
+    fn parse_en_passant(&mut self, en_passant: &str) -> Result<(), FenError> {
+        if en_passant == "-" {
+            return Ok(());
+        }
+
+        let target = chess_notation_to_array_notation(en_passant).map_err(|_| FenError::InvalidEnPassant)?;
+        // The target is the square the pawn that just double moved passed over
+        let target_row = match self.turn {
+            PieceColor::White => 2,
+            PieceColor::Black => 5,
+        };
+        if en_passant.len() != 2 || target.0 != target_row {
+            return Err(FenError::InvalidEnPassant);
+        }
+
+        self.en_passant = Some(target);
+        Ok(())
+    }
+}
+
+// Functions
+
+/// Splits Crazyhouse pockets off the piece placement. Both `.../RNBQKBNR[Qp]`
+/// and a ninth rank `.../RNBQKBNR/Qp` are accepted.
+fn split_pockets(placement: &str) -> (&str, &str) {
+    if let Some((board, pockets)) = placement.split_once('[') {
+        return (board, pockets.trim_end_matches(']'));
+    }
+    match placement.match_indices('/').nth(7) {
+        Some((index, _)) => (&placement[..index], &placement[index + 1..]),
+        None => (placement, ""),
+    }
+}
+
+/// Reads the piece placement field, along with any squares marked as promoted with `~`
+fn parse_placement(placement: &str) -> Result<(Board, SquareSet), FenError> {
+    let mut board = Board {
+        board: [[None; 8]; 8],
+    };
+    let mut promoted = SquareSet::default();
+
+    let rows: Vec<&str> = placement.split('/').collect();
+    if rows.len() != 8 {
+        return Err(FenError::InvalidBoard);
+    }
+
+    for (row, rank) in rows.iter().enumerate() {
+        let mut col: u8 = 0;
+        for letter in rank.chars() {
+            if let Some(empty) = letter.to_digit(10) {
+                // Checked before adding, so runs of digits can't overflow
+                if !(1..=8).contains(&empty) || col as u32 + empty > 8 {
+                    return Err(FenError::InvalidBoard);
+                }
+                col += empty as u8;
+                continue;
+            }
+            if letter == '~' {
+                if col == 0 {
+                    return Err(FenError::InvalidBoard);
+                }
+                promoted.insert(Coordinates(row as u8, col - 1));
+                continue;
+            }
+            if col > 7 {
+                return Err(FenError::InvalidBoard);
+            }
+
+            let coords = Coordinates(row as u8, col);
+            let piece = piece_from_letter(letter, coords).ok_or(FenError::InvalidBoard)?;
+            // Pawns can never stand on their promotion rank (Horde has White pawns on the first)
+            let promotion_row = match piece.color {
+                PieceColor::White => 0,
+                PieceColor::Black => 7,
+            };
+            if matches!(piece.piece_type, PieceType::Pawn(_)) && row == promotion_row {
+                return Err(FenError::InvalidBoard);
+            }
+            *board.at(coords) = Some(piece);
+            col += 1;
+        }
+        if col != 8 {
+            return Err(FenError::InvalidBoard);
+        }
+    }
+    Ok((board, promoted))
+}
+
+/// Builds a piece from its FEN letter. Kings and rooks start without castling rights
+/// and pawns count as moved unless they stand on their starting row (or behind it).
+pub fn piece_from_letter(letter: char, coords: Coordinates) -> Option<Piece> {
+    let color = if letter.is_ascii_uppercase() {
+        PieceColor::White
+    } else {
+        PieceColor::Black
+    };
+    // Horde pawns on the first rank may double move as well
+    let start_rows = match color {
+        PieceColor::White => [6, 7],
+        PieceColor::Black => [1, 0],
+    };
+    let piece_type = match letter.to_ascii_lowercase() {
+        'p' => PieceType::Pawn(PawnData { has_moved: !start_rows.contains(&coords.0) }),
+        'n' => PieceType::Knight,
+        'b' => PieceType::Bishop,
+        'r' => PieceType::Rook(true),
+        'q' => PieceType::Queen,
+        'k' => PieceType::King(true),
+        _ => return None,
+    };
+    build_piece(piece_type, color, coords)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perft::*;
+
+    #[test]
+    fn malformed_placements_are_errors() {
+        for fen in [
+            "99999999999999999999999999999/8/8/8/8/8/8/8 w - - 0 1",
+            "9/8/8/8/8/8/8/8 w - - 0 1",
+            "0k7/8/8/8/8/8/8/7K w - - 0 1",
+            "k6K1/8/8/8/8/8/8/8 w - - 0 1",
+        ] {
+            assert_eq!(Game::<Standard>::from_fen(fen).err(), Some(FenError::InvalidBoard), "{fen}");
+        }
+    }
+
+    #[test]
+    fn chess960_setups_decode_from_their_index() {
+        let game = Game::<Standard>::chess960(518);
+        assert_eq!(game.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(perft(&game, 3), 8902);
+
+        let game = Game::<Standard>::chess960(0);
+        assert_eq!(game.to_fen(), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1");
+        assert_eq!(game.to_shredder_fen(), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1");
+        assert_eq!(Game::<Standard>::chess960(959).to_shredder_fen(), "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w CAca - 0 1");
+    }
+
+    #[test]
+    fn chess960_castling_round_trips_and_counts() {
+        let fen = "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9";
+        let game = Game::<Standard>::from_fen(fen).unwrap();
+        assert_eq!(game.to_shredder_fen(), fen);
+        assert_eq!(game.to_fen(), "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9");
+        assert_eq!(Game::<Standard>::from_fen(&game.to_fen()).unwrap().to_shredder_fen(), fen);
+        let counts: Vec<u64> = (1..=3).map(|depth| perft(&game, depth)).collect();
+        assert_eq!(counts, [21, 528, 12189]);
+
+        // A rook inside the outer one is named by its file in X-FEN
+        let fen = "1k6/8/8/8/8/8/8/RK1R3R w D - 0 1";
+        let game = Game::<Standard>::from_fen(fen).unwrap();
+        assert_eq!(game.to_fen(), fen);
+        let castle: Move = "b1d1".parse().unwrap();
+        assert!(game.legal_moves().contains(&castle));
+        let mut castled = game;
+        castled.play(castle).unwrap();
+        assert_eq!(castled.to_fen(), "1k6/8/8/8/8/8/8/R4RKR b - - 1 1");
+    }
+}
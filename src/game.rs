@@ -4,195 +4,248 @@ use std::error::Error;
 use std::io;
 
 use crate::board::*;
+use crate::engine::*;
 use crate::piece::*;
 use crate::errors::*;
+use crate::moves::*;
+use crate::pocket::*;
+use crate::render::*;
+use crate::rng::*;
+use crate::square_set::*;
+use crate::variant::*;
 
 // Game struct
 
 #[derive(Debug, Copy, Clone)]
-pub struct Game {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Game<V: Variant = Standard> {
     pub board: Board,
     pub turn: PieceColor,
-    pub move_num: u8,
-    pub turn_num: u8,
+    pub move_num: u16,
+    pub turn_num: u16,
+    /// Square a pawn skipped over with a double move on the previous turn
+    pub en_passant: Option<Coordinates>,
+    /// Half moves since the last capture or pawn move, for the fifty move rule
+    pub halfmove_clock: u16,
+    /// Castling follows Chess960 notation only (king moves onto its rook)
+    pub chess960: bool,
+    /// Rules and state of the variant being played
+    pub variant: V,
+    /// Captured pieces held for dropping, indexed White then Black (Crazyhouse and Bughouse)
+    pub pockets: [Pocket; 2],
+    /// Squares holding pieces that were promoted from pawns
+    pub promoted: SquareSet,
+}
+
+// GameStatus
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GameStatus {
+    Ongoing,
+    /// Holds the winning color
+    Checkmate(PieceColor),
+    Stalemate,
+    FiftyMoveRule,
+    InsufficientMaterial,
+    /// A variant specific win, e.g. three checks or a king on the hill
+    VariantWin(PieceColor),
+    VariantDraw,
+}
+
+impl GameStatus {
+    pub fn is_over(&self) -> bool {
+        *self != GameStatus::Ongoing
+    }
+
+    pub fn winner(&self) -> Option<PieceColor> {
+        match self {
+            GameStatus::Checkmate(color) | GameStatus::VariantWin(color) => Some(*color),
+            _ => None,
+        }
+    }
+}
+
+impl Display for GameStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameStatus::Ongoing => write!(f, "Game in progress"),
+            GameStatus::Checkmate(color) => write!(f, "Checkmate, {color} Wins!"),
+            GameStatus::Stalemate => write!(f, "Stalemate, the game is a draw"),
+            GameStatus::FiftyMoveRule => write!(f, "Fifty move rule, the game is a draw"),
+            GameStatus::InsufficientMaterial => write!(f, "Insufficient material, the game is a draw"),
+            GameStatus::VariantWin(color) => write!(f, "{color} Wins!"),
+            GameStatus::VariantDraw => write!(f, "The game is a draw"),
+        }
+    }
 }
 
 // Impl Traits for Game
 
-impl Default for Game {
+impl<V: Variant> Default for Game<V> {
     fn default() -> Self {
         Game {
-            board: Board::default(),
+            board: V::starting_board(),
             turn: PieceColor::White,
             move_num: 0,
             turn_num: 0,
+            en_passant: None,
+            halfmove_clock: 0,
+            chess960: false,
+            variant: V::default(),
+            pockets: [Pocket::default(); 2],
+            promoted: SquareSet::default(),
         }
     }
 }
 
-impl Display for Game {
+impl<V: Variant> Display for Game<V> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // write!(f, "{}       Turn: {}\nTurn Number: {}\n", self.board, self.turn, self.turn_num)
-        write!(f, "{}", self.board)
+        write!(f, "{}", self.render(&RenderOptions::default(), None))
     }
 }
 
-pub fn coordinate_from_input() -> Result<Option<Coordinates>, Box<dyn Error>> {
-    let mut input = String::new();
+/// Asks which piece a pawn should promote to, defaulting to a queen
+pub fn promotion_from_input(choices: &[PieceType]) -> PieceType {
+    let letters: String = choices.iter().map(|&piece_type| piece_type_letter(piece_type)).collect();
+    println!("Promote to which piece? ({letters})");
 
-    io::stdin()
-        .read_line(&mut input)?;
-
-    if input == "0".to_owned() {
-        return Ok(None);
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input).is_err() {
+        return PieceType::Queen;
     }
 
-    let coords = chess_notation_to_array_notation(&input[..])?;
-    Ok(Some(coords))
+    input
+        .trim()
+        .chars()
+        .next()
+        .and_then(piece_type_from_letter)
+        .filter(|piece_type| choices.contains(piece_type))
+        .unwrap_or(PieceType::Queen)
 }
 
 // Impl the actual Game
 
-impl Game {
-    pub fn game_loop(&mut self) {
-        'game_loop: loop {
-            println!("{}", self.board);
-
-            let mut input = String::new();
-
-            println!("Print or Move");
-            io::stdin()
-                .read_line(&mut input)
-                .expect("IO error");
+impl<V: Variant> Game<V> {
+    /// Starts a Chess960 game from the setup with the given Scharnagl index (0..960)
+    pub fn chess960(index: u16) -> Game<V> {
+        let mut board = Board::chess960(index);
+        if !V::CASTLING {
+            board.clear_castling_rights();
+        }
+        Game {
+            board,
+            chess960: true,
+            ..Game::default()
+        }
+    }
 
-            if input.to_ascii_lowercase().contains("print") {
-                println!("Which square would you like to debug");
-                let print = coordinate_from_input().unwrap().unwrap();
-                println!("{:?}", self.at(print));
-                continue;
-            }
+    /// Starts a Chess960 game from a setup picked with the given seed
+    pub fn chess960_random(seed: u64) -> Game<V> {
+        Game::chess960(Rng::new(seed).below(960) as u16)
+    }
 
-            let turn_color: PieceColor = if self.move_num % 2 == 0 {
-                PieceColor::White
-            } else {
-                PieceColor::Black
+    /// Plays a move for the side to move after checking it is legal, then passes the turn
+    pub fn play(&mut self, mv: Move) -> Result<GameResult, GameError> {
+        let mv = self.normalize_move(mv);
+        if !self.legal_moves().contains(&mv) {
+            let mut test = *self;
+            return match test.play_unchecked(mv) {
+                Err(error) => Err(error),
+                Ok(_) if test.is_king_in_check(self.turn) => Err(GameError::InvalidMoveCheck),
+                Ok(_) => Err(GameError::InvalidMove),
             };
+        }
+        self.play_unchecked(mv)
+    }
 
-            // TODO check if move out of check is necessary
-            
-            let mut check = self.is_king_in_check(turn_color);
-            
-            println!("Input start square {turn_color}");
-            let start_move = coordinate_from_input();
-            let start_move_coords: Coordinates;
-            match start_move {
-                Ok(opt) => {
-                    match opt {
-                        None => break 'game_loop,
-                        Some(val) => start_move_coords = val,
-                    }
-                },
-                Err(_) => continue 'game_loop,
-            }
-            
-            println!("Input end square {turn_color}");
-            let end_move = coordinate_from_input();
-            let end_move_coords: Coordinates;
-            match end_move {
-                Ok(opt) => {
-                    match opt {
-                        None => break 'game_loop,
-                        Some(val) => end_move_coords = val,
-                    }
-                },
-                Err(_) => continue 'game_loop,
-            }
+    /// Plays a move without checking the king is safe, then passes the turn.
+    /// Only the piece movement rules in `make_move` are enforced.
+    pub fn play_unchecked(&mut self, mv: Move) -> Result<GameResult, GameError> {
+        // Captured promoted pieces are handed on as the pawns they started as
+        let captured = self
+            .captured_piece(mv)
+            .map(|piece| Piece { piece_type: self.pocket_piece(piece), ..piece });
+        let result = match mv.drop {
+            Some(piece_type) => self.drop_piece(piece_type, mv.end)?,
+            None => self.make_move(mv.start, mv.end)?,
+        };
+
+        let start_promoted = self.promoted.contains(mv.start) && mv.drop.is_none();
+        self.promoted.remove(mv.start);
+        self.promoted.remove(mv.end);
+        if let GameResult::Promotion = result {
+            let piece_type = mv.promotion.unwrap_or(PieceType::Queen);
+            self.at(mv.end).as_mut().unwrap().piece_type = piece_type;
+            self.promoted.insert(mv.end);
+        } else if start_promoted {
+            self.promoted.insert(mv.end);
+        }
+        V::after_move(self, mv, captured);
 
-            if let Some(val) = self.at(start_move_coords) {
-                if val.color != turn_color {
-                    println!("Cannot move piece not owned by you");
-                    continue 'game_loop;
-                }
-            } else {
-                println!("Cannot move no piece");
-                continue 'game_loop;
-            }
+        self.turn = self.turn.swap();
+        self.move_num += 1;
+        self.turn_num = self.move_num/2;
+        Ok(result)
+    }
 
-            // TODO if move out of check is necessary, make sure move moves out of check
-            // if it doesn't continue the loop
+    /// Returns true if the move is a pawn reaching the last rank
+    pub fn is_promotion(&self, mv: Move) -> bool {
+        matches!(self.board.get(mv.start).map(|piece| piece.piece_type), Some(PieceType::Pawn(_))) &&
+        (mv.end.0 == 0 || mv.end.0 == 7)
+    }
 
-            if check {
-                let mut test = self.clone();
-                let _ = test.make_move(start_move_coords, end_move_coords);
-                if test.is_king_in_check(turn_color) {
-                    println!("Must move out of check");
-                    continue;
-                }
-            }
+    /// Works out whether the game has ended, and how
+    pub fn status(&self) -> GameStatus {
+        if let Some(status) = V::outcome(self) {
+            return status;
+        }
+        if self.legal_moves().is_empty() {
+            return V::no_moves_status(self);
+        }
+        if self.halfmove_clock >= 100 {
+            return GameStatus::FiftyMoveRule;
+        }
+        if V::insufficient_material(self) {
+            return GameStatus::InsufficientMaterial;
+        }
+        GameStatus::Ongoing
+    }
 
-            // We KNOW:
-            //     Correct piece color
-            //     Not an empty spot
-            //     Not check
-            // We Must:
-            //     Validate the move
-            //     Check for checkmate
-            
-            if let Some(piece) = self.at(start_move_coords) {
-                if let PieceType::Pawn(_) = piece.piece_type {
-                    match turn_color {
-                        PieceColor::Black => {
-                            if start_move_coords.0 > end_move_coords.0 {
-                                println!("Illegal Move");
-                                continue;
-                            }
-                        },
-                        PieceColor::White => {
-                            if start_move_coords.0 < end_move_coords.0 {
-                                println!("Illegal Move");
-                                continue;
-                            }
-                        },
-                    }
+    /// Neither side can mate: bare kings, or a king and a single minor piece against a king
+    pub fn standard_insufficient_material(&self) -> bool {
+        let mut minors = 0;
+        for row in self.board.board {
+            for piece in row.into_iter().flatten() {
+                match piece.piece_type {
+                    PieceType::King(_) => (),
+                    PieceType::Bishop | PieceType::Knight => minors += 1,
+                    _ => return false,
                 }
             }
+        }
+        minors <= 1
+    }
 
-            match self.make_move(start_move_coords, end_move_coords) {
-                Err(error) => {
-                    println!("{error}");
-                    continue;
-                },
-                Ok(game_result) => {
-                    match game_result {
-                        GameResult::Promotion => {
-                            self.handle_promotion(end_move_coords);
-                        }
-                        _ => {},
-                    }
-                }
-            }
+    pub fn pieces(&self, color: PieceColor) -> impl Iterator<Item = Piece> + '_ {
+        self.board.board.iter().flatten().flatten().copied().filter(move |piece| piece.color == color)
+    }
 
-            // checks if move checkmates the enemy king
-            if self.is_king_in_check(turn_color.swap()) && (self.check_checkmate(turn_color) == true) {
-                println!("{turn_color} Wins!");
-                break;
-            }
+    pub fn kings(&self) -> impl Iterator<Item = Piece> + '_ {
+        self.board.board.iter().flatten().flatten().copied().filter(|piece| matches!(piece.piece_type, PieceType::King(_)))
+    }
 
-            self.move_num += 1;
-            self.turn_num = self.move_num/2;
-        }
+    /// Square of the given color's king, if it has one
+    pub fn king(&self, color: PieceColor) -> Option<Coordinates> {
+        self.kings().find(|king| king.color == color).map(|king| king.coordinates)
     }
 
-    pub fn is_king_in_check(&mut self, color: PieceColor) -> bool {
+    pub fn is_king_in_check(&self, color: PieceColor) -> bool {
         for row in self.board.board {
-            for piece_option in row {
-                if let Some(piece) = piece_option {
-                    if let PieceType::King(_) = piece.piece_type {
-                        if color == piece.color {
-                            if self.is_coord_attacked_by_team(color.swap(), piece.coordinates) {
-                                return true;
-                            }
-                        }
+            for piece in row.into_iter().flatten() {
+                if let PieceType::King(_) = piece.piece_type {
+                    if color == piece.color && self.is_coord_attacked_by_team(color.swap(), piece.coordinates) {
+                        return true;
                     }
                 }
             }
@@ -200,56 +253,34 @@ impl Game {
         false
     }
 
-    /// Checks if the king of the opposite color is in checkmate
-    pub fn check_checkmate(&self, turn_color: PieceColor) -> bool {
-        let mut enemy_king_coords = Coordinates(0, 0);
+    pub fn make_move(&mut self, start: Coordinates, end: Coordinates) -> Result<GameResult, GameError> {
+        let moving = self.board.get(start);
+        let result = self.apply_move(start, end)?;
 
-        for row in self.board.board {
-            for piece_option in row {
-                if let Some(piece) = piece_option {
-                    if let PieceType::King(_) = piece.piece_type {
-                        if turn_color.swap() == piece.color {
-                            enemy_king_coords = piece.coordinates;
-                        }
-                    }
-                }
-            }
-        }
-        for i in -1i8..=1 {
-            for j in -1i8..=1 {
-                let mut cloned_board = self.clone();
-                let status = cloned_board.make_move(enemy_king_coords, Coordinates(cmp::max(cmp::min(0, enemy_king_coords.0 as i8 + i), 7) as u8, cmp::max(cmp::min(0, enemy_king_coords.0 as i8 + j), 7) as u8));
-                if let Ok(_) = status {
-                    if !cloned_board.is_king_in_check(turn_color.swap()) {
-                        return false;
-                    }
-                }
+        self.en_passant = match result {
+            GameResult::DoublePawn => Some(Coordinates((start.0 + end.0) / 2, start.1)),
+            _ => None,
+        };
 
-            }
+        let pawn_move = matches!(moving.map(|piece| piece.piece_type), Some(PieceType::Pawn(_)));
+        if pawn_move || matches!(result, GameResult::Capture) {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
         }
-        true
+        Ok(result)
     }
 
-    pub fn handle_promotion(&mut self, coords: Coordinates) {
-        todo!()
-    }
-
-    pub fn make_move(&mut self, start: Coordinates, end: Coordinates) -> Result<GameResult, GameError> {
+    fn apply_move(&mut self, start: Coordinates, end: Coordinates) -> Result<GameResult, GameError> {
         // Function Guards
-        if &None == self.at(start) {
+        let Some(moving) = self.board.get(start) else {
             return Err(GameError::NoPieceOnStartSquare);
-        }
+        };
 
-        // Handled castling here
-        if let PieceType::King(false) = self.at(start).unwrap().piece_type {
-            if let Some(_) = self.at(end) {
-                if let PieceType::Rook(false) = self.at(end).unwrap().piece_type {
-                    if !self.empty_between(start, end) && !self.is_coord_range_attacked_by_team(self.clone().at(start).unwrap().color, start, end){
-                        return Err(GameError::InvalidMove);
-                    }
-                    self.board.swap_pieces(start, end);
-                    return Ok(GameResult::Castle);
-                }
+        // Handled castling here, the king moves onto its own rook
+        if let PieceType::King(false) = moving.piece_type {
+            if let Some(rook) = self.castling_rook(start, end) {
+                return self.castle(start, rook);
             }
         }
 
@@ -260,39 +291,29 @@ impl Game {
         }
 
         // All pawn movement (en pessant, double first) handled here
-        // TODO FORWARD MOVEMENT MUST BE HANDLED BY CALLEE FUNCTION
         if let PieceType::Pawn(data) = self.at(start).unwrap().piece_type {
+            let forward = match moving.color {
+                PieceColor::White => start.0 > end.0,
+                PieceColor::Black => start.0 < end.0,
+            };
+            if !forward {
+                return Err(GameError::InvalidMove);
+            }
+
             // Double First Move
             if (end.0 as i8 - start.0 as i8).abs() == 2 && 
                (end.1 as i8 - start.1 as i8).abs() == 0 &&
-               data.has_moved == false &&
-               self.empty_between(start, end) {
-                   if let Some(piece_1) = &mut self.at(Coordinates(end.0, (end.1 as i8 - 1).abs() as u8)).as_mut() {
-                        match piece_1.piece_type {
-                           PieceType::Pawn(_) => {
-                               self.at(Coordinates(end.0, (end.1 as i8 - 1).abs() as u8)).as_mut().unwrap().piece_type =
-                                   PieceType::Pawn(PawnData { has_moved: true, can_en_pessant: true });
-                           },
-                           _ => (),
-                       }
-                   }
-                   if let Some(piece_2) = &mut self.at(Coordinates(end.0, cmp::min((end.1 as i8 + 1).abs() as u8, 7))).as_mut() {
-                       match piece_2.piece_type {
-                           PieceType::Pawn(_) => {
-                               self.at(Coordinates(end.0, (end.1 as i8 + 1).abs() as u8)).as_mut().unwrap().piece_type =
-                                   PieceType::Pawn(PawnData { has_moved: true, can_en_pessant: true });
-                           },
-                           _ => (),
-                       }
-                   }
+               !data.has_moved &&
+               self.empty_between(start, end) &&
+               self.at(end).is_none() {
                    self.board.move_piece(start, end);
                    return Ok(GameResult::DoublePawn);
             }
             // En Pessant
             else if (end.0 as i8 - start.0 as i8).abs() == 1 && 
                     (end.1 as i8 - start.1 as i8).abs() == 1 && 
-                    data.can_en_pessant == true && 
-                    self.at(end) == &None {
+                    self.en_passant == Some(end) && 
+                    self.at(end).is_none() {
                 if let &mut Some(piece) = self.at(Coordinates(start.0, end.1)) {
                     if let PieceType::Pawn(_) = piece.piece_type {
                         if piece.color != self.at(start).unwrap().color {
@@ -306,10 +327,10 @@ impl Game {
             // Normal Capture
             else if (end.0 as i8 - start.0 as i8).abs() == 1 && 
                     (end.1 as i8 - start.1 as i8).abs() == 1 {
-                if let &mut Some(_) = self.at(end) {
+                if self.at(end).is_some() {
                     self.board.move_piece(start, end);
                     self.at(end).as_mut().unwrap().piece_type =
-                        PieceType::Pawn(PawnData { has_moved: true, can_en_pessant: false });
+                        PieceType::Pawn(PawnData { has_moved: true });
                     if end.0 == 7 || end.0 == 0 {
                         return Ok(GameResult::Promotion)
                     }
@@ -319,10 +340,10 @@ impl Game {
             // Normal Move 
             else if (end.0 as i8 - start.0 as i8).abs() == 1 && 
                     (end.1 as i8 - start.1 as i8).abs() == 0 && 
-                    self.at(end) == &None {
+                    self.at(end).is_none() {
                 self.board.move_piece(start, end);
                 self.at(end).as_mut().unwrap().piece_type =
-                    PieceType::Pawn(PawnData { has_moved: true, can_en_pessant: false });
+                    PieceType::Pawn(PawnData { has_moved: true });
                 if end.0 == 7 || end.0 == 0 {
                     return Ok(GameResult::Promotion)
                 }
@@ -332,9 +353,7 @@ impl Game {
         }
 
         // TODO valid_move should check checkmate, and return check if needed, not just InvalidMove
-        if let Err(error) = self.valid_move(start, end) {
-            return Err(error);
-        }
+        self.valid_move(start, end)?;
 
         match &mut self.at(start).as_mut().unwrap().piece_type {
             PieceType::Pawn(data) => {
@@ -351,7 +370,7 @@ impl Game {
 
         // We know the contained piece is not the same color because it would have returned error
         // otherwise
-        if let Some(_) = self.at(end) {
+        if self.at(end).is_some() {
             self.board.move_piece(start, end);
             Ok(GameResult::Capture)
         } else {
@@ -364,93 +383,83 @@ impl Game {
         self.board.at(coords)
     }
 
-    // Piece exists, is attacking different color (or) None
-    fn valid_move(&mut self, start: Coordinates, end: Coordinates) -> Result<GameResult, GameError> {
-        if let None = self.at(start) {
-            return Err(GameError::NoPieceOnStartSquare);
-        }
+    /// Finds the rook a king on `start` castles with when moved to `end`.
+    /// A king moving onto its own unmoved rook always castles; outside of Chess960
+    /// the classical two square king move (e1 to g1) is accepted as well.
+    pub(crate) fn castling_rook(&self, start: Coordinates, end: Coordinates) -> Option<Coordinates> {
+        let king = self.board.get(start)?;
+        let rook = if !self.chess960 && start.1 == 4 && start.0 == end.0 && (end.1 == 6 || end.1 == 2) {
+            Coordinates(start.0, if end.1 == 6 { 7 } else { 0 })
+        } else {
+            end
+        };
 
-        match self.at(start).unwrap().piece_type {
-            PieceType::Queen => {
-                if self.is_piece_attacking_coordinates(PieceType::Queen, start, end) {
-                    return Ok(GameResult::Normal);
-                } else {
-                    return Err(GameError::InvalidMove);
-                }
-            },
-            PieceType::Bishop => {
-                if self.is_piece_attacking_coordinates(PieceType::Bishop, start, end) {
-                    return Ok(GameResult::Normal);
-                } else {
-                    return Err(GameError::InvalidMove);
-                }
-            },
-            PieceType::Knight => {
-                if self.is_piece_attacking_coordinates(PieceType::Knight, start, end) {
-                    return Ok(GameResult::Normal);
-                } else {
-                    return Err(GameError::InvalidMove);
-                }
-            },
-            PieceType::Rook(val) => {
-                if self.is_piece_attacking_coordinates(PieceType::Rook(val), start, end) {
-                    return Ok(GameResult::Normal);
-                } else {
-                    return Err(GameError::InvalidMove);
-                }
-            },
-            PieceType::King(val) => {
-                let color = self.at(start).unwrap().color.swap();
-                if self.is_piece_attacking_coordinates(PieceType::King(val), start, end) &&
-                   !self.is_coord_attacked_by_team(color, end) {
-                       return Ok(GameResult::Normal);
-                   } else {
-                       return Err(GameError::InvalidMoveCheck)
-                   }
-            },
-            PieceType::Pawn(_) => {
-                // TODO should never be called probably
-            },
+        match self.board.get(rook) {
+            Some(piece) if piece.color == king.color && piece.piece_type == PieceType::Rook(false) => Some(rook),
+            _ => None,
         }
-        Ok(GameResult::Normal)
     }
 
-    pub fn is_coord_range_attacked_by_team(&mut self, color: PieceColor, start: Coordinates, end: Coordinates) -> bool {
-        if !(start.0 == end.0 || start.1 == end.1) {
-            return false;
+    /// Castles under Chess960 rules: every square the king or rook crosses must be empty
+    /// (apart from the two castling pieces), and the king may not leave or pass through
+    /// an attacked square.
+    fn castle(&mut self, king: Coordinates, rook: Coordinates) -> Result<GameResult, GameError> {
+        let color = self.board.get(king).unwrap().color;
+        let back_rank = back_rank(color);
+        if king.0 != back_rank || rook.0 != back_rank {
+            return Err(GameError::InvalidMove);
         }
 
-        if start.0 == end.0 {
-            for square in coord_range(start.1.into(), end.1.into()) {
-                if self.is_coord_attacked_by_team(color, Coordinates(start.0, square as u8)) {
-                    return true;
-                }
+        let (king_file, rook_file) = castling_files(king, rook);
+        let files = [king.1, rook.1, king_file, rook_file];
+        let (low, high) = (*files.iter().min().unwrap(), *files.iter().max().unwrap());
+        for file in low..=high {
+            let square = Coordinates(back_rank, file);
+            if square != king && square != rook && self.board.get(square).is_some() {
+                return Err(GameError::InvalidMove);
             }
         }
 
-        if start.1 == end.1 {
-            for square in coord_range(start.0.into(), end.0.into()) {
-                if self.is_coord_attacked_by_team(color, Coordinates(square as u8, start.1)) {
-                    return true;
-                }
+        // The king's final square is left to the usual legality test once the move is made,
+        // when the rook has reached its own square
+        let mut without = *self;
+        *without.at(king) = None;
+        let crossed = (cmp::min(king.1, king_file)..=cmp::max(king.1, king_file))
+            .filter(|&file| file == king.1 || file != king_file);
+        for file in crossed {
+            if V::king_square_attacked(&without, color, Coordinates(back_rank, file)) {
+                return Err(GameError::InvalidMoveCheck);
             }
         }
-        false
+
+        self.board.castle(king, rook);
+        Ok(GameResult::Castle)
     }
 
-    pub fn is_coord_attacked_by_team(&mut self, color: PieceColor, coords: Coordinates) -> bool {
-        for row in self.board.board {
-            for value in row.iter().filter(|row_val| {
-                if let None = row_val {
-                    return false;
+    // Piece exists, is attacking different color (or) None
+    // Whether the king is left in check is up to the variant, see `Variant::is_legal`
+    fn valid_move(&mut self, start: Coordinates, end: Coordinates) -> Result<GameResult, GameError> {
+        let Some(piece) = *self.at(start) else {
+            return Err(GameError::NoPieceOnStartSquare);
+        };
+
+        match piece.piece_type {
+            // TODO should never be called probably
+            PieceType::Pawn(_) => Ok(GameResult::Normal),
+            _ => {
+                if self.board.attacks_from(start).contains(end) {
+                    Ok(GameResult::Normal)
                 } else {
-                    if row_val.unwrap().color == color {
-                        return true;
-                    }
-                    false
+                    Err(GameError::InvalidMove)
                 }
-            }) {
-                if self.is_piece_attacking_coordinates(value.unwrap().piece_type, value.unwrap().coordinates, coords) {
+            },
+        }
+    }
+
+    pub fn is_coord_attacked_by_team(&self, color: PieceColor, coords: Coordinates) -> bool {
+        for row in self.board.board {
+            for piece in row.into_iter().flatten().filter(|piece| piece.color == color) {
+                if self.board.attacks_from(piece.coordinates).contains(coords) {
                     return true;
                 }
             }
@@ -458,15 +467,107 @@ impl Game {
         false
     }
 
-    pub fn empty_between(&mut self, start: Coordinates, end: Coordinates) -> bool {
+    /// Squares of the `color` pieces attacking `coords`
+    pub fn attackers_of(&self, color: PieceColor, coords: Coordinates) -> SquareSet {
+        attackers_on(&self.board, color, coords)
+    }
+
+    /// Static exchange evaluation: the material `mv` wins or loses in centipawns once both
+    /// sides have recaptured on its square for as long as it pays, always with their least
+    /// valuable attacker. Sliders lined up behind a capturing piece join in as it leaves.
+    /// Quiet moves and drops score what the piece loses by standing on an attacked square.
+    pub fn see(&self, mv: Move) -> i32 {
+        let Some(mut moving) = mv.drop.map(|piece_type| Piece {
+            piece_type,
+            color: self.turn,
+            coordinates: mv.end,
+        }).or_else(|| self.board.get(mv.start)) else {
+            return 0;
+        };
+
+        // Play the move on a scratch board, en passant and promotion included
+        let mut board = self.board;
+        let mut gains = vec![self.captured_piece(mv).map_or(0, |piece| piece_value(piece.piece_type))];
+        if mv.drop.is_none() {
+            if self.board.get(mv.end).is_none() && self.captured_piece(mv).is_some() {
+                *board.at(Coordinates(mv.start.0, mv.end.1)) = None;
+            }
+            *board.at(mv.start) = None;
+        }
+        if let Some(promotion) = mv.promotion {
+            gains[0] += piece_value(promotion) - piece_value(moving.piece_type);
+            moving.piece_type = promotion;
+        }
+        *board.at(mv.end) = Some(Piece { coordinates: mv.end, ..moving });
+
+        let mut on_square = piece_value(moving.piece_type);
+        let mut side = self.turn.swap();
+        let least_valuable = |board: &Board, side| {
+            attackers_on(board, side, mv.end)
+                .into_iter()
+                .filter_map(|square| board.get(square))
+                .min_by_key(|piece| match piece.piece_type {
+                    PieceType::King(_) => i32::MAX,
+                    piece_type => piece_value(piece_type),
+                })
+        };
+        while let Some(attacker) = least_valuable(&board, side) {
+            // The king can only take last, on a square the other side no longer covers
+            if matches!(attacker.piece_type, PieceType::King(_)) {
+                let mut after = board;
+                *after.at(attacker.coordinates) = None;
+                if !attackers_on(&after, side.swap(), mv.end).is_empty() {
+                    break;
+                }
+            }
+
+            gains.push(on_square - gains[gains.len() - 1]);
+            on_square = piece_value(attacker.piece_type);
+            *board.at(attacker.coordinates) = None;
+            *board.at(mv.end) = Some(Piece { coordinates: mv.end, ..attacker });
+            side = side.swap();
+        }
+
+        // Either side may stop recapturing when carrying on would lose more
+        while gains.len() > 1 {
+            let last = gains.pop().unwrap();
+            let previous = gains.last_mut().unwrap();
+            *previous = -(-*previous).max(last);
+        }
+        gains[0]
+    }
+
+    /// Squares of the `color` pieces the opponent could take and come out ahead, as if it
+    /// were the opponent's move
+    pub fn hanging_pieces(&self, color: PieceColor) -> SquareSet {
+        let mut game = *self;
+        game.turn = color.swap();
+        self.board
+            .board
+            .iter()
+            .flatten()
+            .flatten()
+            .filter(|piece| piece.color == color)
+            .map(|piece| piece.coordinates)
+            .filter(|&square| {
+                game.attackers_of(color.swap(), square)
+                    .into_iter()
+                    .any(|attacker| game.see(Move::new(attacker, square)) > 0)
+            })
+            .collect()
+    }
+
+    /// Checks that every square strictly between `start` and `end` on a rank or file is empty.
+    /// Used for castling and double pawn pushes; attack rays come from `Board::attacks_from`.
+    pub fn empty_between(&self, start: Coordinates, end: Coordinates) -> bool {
         // return false if not straight line
-        if !(start.0 == end.0 || start.1 == end.1) {
+        if !(start.0 == end.0 || start.1 == end.1) || start == end {
             return false;
         }
 
         if start.0 == end.0 {
             for square in coord_range(start.1.into(), end.1.into()) {
-                if let Some(_) = self.at(Coordinates(start.0, square as u8)) {
+                if self.board.get(Coordinates(start.0, square as u8)).is_some() {
                     return false;
                 }
             }
@@ -474,64 +575,36 @@ impl Game {
 
         if start.1 == end.1 {
             for square in coord_range(start.0.into(), end.0.into()) {
-                if let Some(_) = self.at(Coordinates(square as u8, start.1)) {
+                if self.board.get(Coordinates(square as u8, start.1)).is_some() {
                     return false;
                 }
             }
         }
         true
     }
-
-    pub fn empty_between_diag(&mut self, start: Coordinates, end: Coordinates) -> bool {
-        // return false if not diagonal
-        if (start.0 as i8 - end.0 as i8).abs() != (start.1 as i8 - end.1 as i8).abs() {
-            return false;
-        }
-        
-        for (s, e) in coord_range(start.0.into(), end.0.into()).zip(coord_range(start.1.into(), end.1.into())) {
-            if let Some(_) = self.at(Coordinates(s as u8, e as u8)) {
-                return false;
-            }
-        }
-        true
-    }
-
-    pub fn is_piece_attacking_coordinates(&mut self, piece: PieceType, piece_coords: Coordinates, attacking_coords: Coordinates) -> bool {
-        match piece{            
-            PieceType::King(_) => {
-                (attacking_coords.0 as i8 - piece_coords.0 as i8).abs() <= 1 &&
-                (attacking_coords.1 as i8 - piece_coords.1 as i8).abs() <= 1
-            },
-            PieceType::Rook(_) => {
-                self.empty_between(piece_coords, attacking_coords)
-            },
-            PieceType::Queen => {
-                self.empty_between(piece_coords, attacking_coords) ||
-                self.empty_between_diag(piece_coords, attacking_coords)
-            },
-            PieceType::Knight=> {
-                ((piece_coords.0 as i8 - attacking_coords.0 as i8).abs() == 2 && (piece_coords.1 as i8 - attacking_coords.1 as i8).abs() == 1) ||
-                ((piece_coords.0 as i8 - attacking_coords.0 as i8).abs() == 1 && (piece_coords.1 as i8 - attacking_coords.1 as i8).abs() == 2)
-            },
-            PieceType::Bishop=> {
-                self.empty_between_diag(piece_coords, attacking_coords)
-            },
-            // Pawn will probably never be called, but in case it will be-
-            // It should cover en pessant, so if the function is used
-            // to determind where any piece can go, it should be highlighted
-            PieceType::Pawn(_) => { false },
-        }
-    }
 }
 
 // Util Functions
 
+/// Squares of the `color` pieces on `board` attacking `coords`
+fn attackers_on(board: &Board, color: PieceColor, coords: Coordinates) -> SquareSet {
+    board
+        .board
+        .iter()
+        .flatten()
+        .flatten()
+        .filter(|piece| piece.color == color && board.attacks_from(piece.coordinates).contains(coords))
+        .map(|piece| piece.coordinates)
+        .collect()
+}
+
 pub fn chess_notation_to_array_notation(chess_not: &str) -> Result<Coordinates, Box<dyn Error>> /* file is columns*/ {
-    let file = chess_not.chars().nth(0).unwrap().to_ascii_uppercase();
+    let mut chars = chess_not.trim().chars();
+    let file = chars.next().ok_or(ConversionError)?.to_ascii_uppercase();
     let file_u8: u8 = u8::try_from(file)?.wrapping_add_signed(-65);
-    let rank = chess_not.chars().nth(1).unwrap().to_digit(10).ok_or(ConversionError)?;
+    let rank = chars.next().and_then(|rank| rank.to_digit(10)).ok_or(ConversionError)?;
 
-    if file_u8 > 7 || rank > 8 {
+    if file_u8 > 7 || !(1..=8).contains(&rank) || chars.next().is_some() {
         return Err(Box::new(ConversionError));
     }
 
@@ -540,6 +613,10 @@ pub fn chess_notation_to_array_notation(chess_not: &str) -> Result<Coordinates,
     Ok(Coordinates(rank_u8, file_u8))
 }
 
+pub fn array_notation_to_chess_notation(coords: Coordinates) -> String {
+    format!("{}{}", (b'a' + coords.1) as char, 8 - coords.0)
+}
+
 pub fn coord_range(first: usize, second: usize) -> Box<dyn Iterator<Item = i32>> {
     if first > second {
         Box::new(((second as i32 + 1)..(first as i32)).rev())
@@ -547,3 +624,63 @@ pub fn coord_range(first: usize, second: usize) -> Box<dyn Iterator<Item = i32>>
         Box::new((first as i32 + 1)..(second as i32)) as Box<dyn Iterator<Item = i32>>
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::piece_value;
+
+    fn see(fen: &str, mv: &str) -> i32 {
+        let game = Game::<Standard>::from_fen(fen).unwrap();
+        game.see(mv.parse().unwrap())
+    }
+
+    #[test]
+    fn exchanges_are_resolved_with_the_least_valuable_attacker() {
+        let (pawn, knight, bishop) = (piece_value(PieceType::Pawn(PawnData::default())), piece_value(PieceType::Knight), piece_value(PieceType::Bishop));
+        // An undefended knight, and a defended pawn that costs the knight taking it
+        assert_eq!(see("4k3/8/8/3n4/4P3/8/8/4K3 w - - 0 1", "e4d5"), knight);
+        assert_eq!(see("4k3/8/2p5/3p4/8/4N3/8/4K3 w - - 0 1", "e3d5"), pawn - knight);
+        // The pawn recaptures rather than the queen, so taking with the bishop loses it
+        assert_eq!(see("4k3/3q4/2p5/3n4/8/8/6B1/4K3 w - - 0 1", "g2d5"), knight - bishop);
+        // A quiet move onto a square the pawn guards loses the piece
+        assert_eq!(see("4k3/8/2p5/8/8/4N3/8/4K3 w - - 0 1", "e3d5"), -knight);
+    }
+
+    #[test]
+    fn exchanges_count_pieces_lined_up_behind() {
+        let (pawn, bishop) = (piece_value(PieceType::Pawn(PawnData::default())), piece_value(PieceType::Bishop));
+        // Doubled rooks win the pawn the lone rook defends, a single rook would lose itself
+        assert_eq!(see("3r2k1/8/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5"), pawn);
+        assert_eq!(see("3r2k1/8/8/3p4/8/8/8/3RK3 w - - 0 1", "d1d5"), pawn - piece_value(PieceType::Rook(false)));
+        // The queen behind the bishop takes back the pawn that took it
+        assert_eq!(see("6k1/8/4p3/3p4/8/1B6/Q7/4K3 w - - 0 1", "b3d5"), 2 * pawn - bishop);
+        // Black's rook behind its own rook joins in as well
+        assert_eq!(see("3r2k1/3r4/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5"), pawn - piece_value(PieceType::Rook(false)));
+    }
+
+    #[test]
+    fn hanging_pieces_can_be_taken_at_a_profit() {
+        // The knight is defended well enough against the bishop, the bishop not at all
+        let game = Game::<Standard>::from_fen("4k3/8/2p5/3n1b2/8/1B6/8/4KR2 b - - 0 1").unwrap();
+        assert_eq!(game.hanging_pieces(PieceColor::Black).into_iter().collect::<Vec<_>>(), [Coordinates(3, 5)]);
+        assert!(game.hanging_pieces(PieceColor::White).is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn bughouse_games_round_trip_through_json() {
+        let mut game = Game::<Bughouse>::default();
+        game.play("e2e4".parse().unwrap()).unwrap();
+        game.pocket_mut(PieceColor::Black).add(PieceType::Knight);
+
+        let json = serde_json::to_string(&game).unwrap();
+        assert!(json.contains(r#""en_passant":"e3""#), "{json}");
+        assert!(json.contains(r#""coordinates":"e4""#), "{json}");
+        let read: Game<Bughouse> = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.to_fen(), game.to_fen());
+        assert_eq!(read.pockets, game.pockets);
+        assert_eq!(read.variant, Bughouse);
+        assert_eq!(serde_json::to_string(&read).unwrap(), json);
+    }
+}
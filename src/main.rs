@@ -2,10 +2,423 @@ pub mod piece;
 pub mod board;
 pub mod game;
 pub mod errors;
+pub mod square_set;
+pub mod fen;
+pub mod rng;
+pub mod moves;
+pub mod variant;
+pub mod pocket;
+pub mod bughouse;
+pub mod book;
+pub mod clock;
+pub mod engine;
+pub mod tablebase;
+pub mod tt;
+pub mod uci;
+pub mod bench;
+pub mod nnue;
+pub mod eval;
+pub mod tune;
+pub mod pgn;
+pub mod selfplay;
+pub mod skill;
+pub mod analysis;
+pub mod annotate;
+pub mod tui;
+pub mod render;
+pub mod perft;
+pub mod cli;
+pub mod session;
+pub mod save;
+pub mod net;
 
+use std::error::Error;
+use std::fs;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::analysis::*;
+use crate::annotate::*;
+use crate::bench::*;
+use crate::book::*;
+use crate::bughouse::*;
+use crate::cli::*;
+use crate::clock::*;
+use crate::engine::*;
+use crate::errors::*;
+use crate::eval::*;
 use crate::game::*;
+use crate::net::*;
+use crate::nnue::*;
+use crate::perft::*;
+use crate::pgn::*;
+use crate::piece::*;
+use crate::render::*;
+use crate::rng::*;
+use crate::save::*;
+use crate::session::*;
+use crate::selfplay::*;
+use crate::skill::*;
+use crate::tablebase::*;
+use crate::tune::*;
+use crate::uci::*;
+use crate::variant::*;
+
+// Custom Play Type Data
+
+/// `play [<variant>]`: a game at the terminal, or with `resume` one saved earlier
+struct Play<'a> {
+    engine: &'a mut Engine,
+    args: &'a Args,
+    engine_color: Option<PieceColor>,
+    clock: Option<Clock>,
+    options: RenderOptions,
+    /// A saved game to carry on, which brings its own players and clock
+    saved: Option<String>,
+}
+
+// Custom Network Type Data
+
+/// `host [<variant>]` and `join <address>`: a game against a player on another computer
+struct NetworkGame<'a> {
+    engine: &'a mut Engine,
+    args: &'a Args,
+    clock: Option<Clock>,
+    options: RenderOptions,
+    /// Who hosts, with the side played here, or the connection and game a joining player gets
+    role: Role,
+}
+
+/// The two ends of a network game
+enum Role {
+    Host(TcpListener, PieceColor),
+    Join(Connection, String),
+}
+
+// Custom Perft Type Data
+
+/// `perft <depth> [<variant>]`: move generation counts for checking against known totals
+struct Perft<'a> {
+    args: &'a Args,
+    depth: u8,
+}
+
+// Custom Play Type Traits
+
+impl VariantCommand for Play<'_> {
+    /// Plays the game, then with `--review` prints it annotated along with how well each
+    /// side played
+    fn run<V: Variant>(self) -> Result<(), Box<dyn Error>> {
+        let mut session = match &self.saved {
+            Some(text) => {
+                let mut session = Session::<V>::from_save(text, self.options)?;
+                if self.args.value("--view").is_none() {
+                    session.options.orientation = Orientation::for_players(session.engine_color);
+                }
+                session
+            },
+            None => Session::new(start_position::<V>(self.args)?, self.engine_color, self.clock, self.options),
+        };
+        if !self.args.has("--no-autosave") {
+            let path = self.args.value("--autosave").unwrap_or(AUTOSAVE_FILE);
+            let unfinished = fs::read_to_string(path).is_ok_and(|text| saved_field(&text, "result").is_ok_and(|result| result.as_deref() == Some("*")));
+            if self.saved.is_none() && unfinished {
+                eprintln!("{path} holds an unfinished game, which `resume` carries on. This game replaces it after the first move.");
+            }
+            session.autosave = Some(PathBuf::from(path));
+        }
+
+        session.game_loop(self.engine);
+        if !self.args.has("--review") || session.pgn.moves.is_empty() {
+            return Ok(());
+        }
+        let depth = self.args.parsed("--depth", "a number of plies")?.unwrap_or(ANNOTATE_DEPTH);
+        let (annotated, summaries) = annotate::<V>(self.engine, &session.pgn, depth, progress())?;
+        println!("{annotated}");
+        print_summaries(&annotated, &summaries);
+        Ok(())
+    }
+}
+
+// Custom Network Type Traits
+
+impl VariantCommand for NetworkGame<'_> {
+    fn run<V: Variant>(self) -> Result<(), Box<dyn Error>> {
+        let (mut session, connection) = match self.role {
+            Role::Host(listener, color) => {
+                let mut session = Session::new(start_position::<V>(self.args)?, None, self.clock, self.options);
+                session.pgn.set_tag(&color.to_string(), "Host");
+                session.pgn.set_tag(&color.swap().to_string(), "Guest");
+                let port = listener.local_addr()?.port();
+                println!("Waiting for an opponent to join on port {port}");
+                let connection = Connection::host(listener, color, &session)?;
+                (session, connection)
+            },
+            Role::Join(connection, game) => (Session::<V>::from_save(&game, self.options).map_err(NetError::Save)?, connection),
+        };
+        // Each player sees the board from their own side
+        if self.args.value("--view").is_none() {
+            session.options.orientation = Orientation::for_players(Some(connection.color.swap()));
+        }
+        println!("Playing {} against {}", connection.color, connection.color.swap());
+        session.network_loop(connection, self.engine);
+        Ok(())
+    }
+}
+
+// Custom Perft Type Traits
+
+impl VariantCommand for Perft<'_> {
+    fn run<V: Variant>(self) -> Result<(), Box<dyn Error>> {
+        let game = start_position::<V>(self.args)?;
+        let start = Instant::now();
+        let mut total = 0;
+        for (mv, nodes) in divide(&game, self.depth) {
+            println!("{mv}: {nodes}");
+            total += nodes;
+        }
+        let seconds = start.elapsed().as_secs_f64();
+        println!();
+        println!("Nodes: {total}");
+        println!("Time: {seconds:.3}s ({} nps)", (total as f64 / seconds.max(0.001)) as u64);
+        Ok(())
+    }
+}
+
+// Functions
 
 fn main() {
-    let mut thing = Game::default();
-    thing.game_loop();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(error) = Args::parse(&args).map_err(Box::from).and_then(|args| run(&args)) {
+        eprintln!("{error}");
+        process::exit(1);
+    }
+}
+
+/// Runs the command `args` names, standard chess at the terminal when there is none
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    if args.has("--help") || args.command() == Some("help") {
+        let command = match args.command() {
+            Some("help") => args.positional(1),
+            command => command,
+        };
+        print!("{}", help(command)?);
+        return Ok(());
+    }
+
+    let mut engine = engine_from_args(args)?;
+    let engine_color = match args.value("--engine").map(str::to_ascii_lowercase).as_deref() {
+        Some("white") => Some(PieceColor::White),
+        Some("black") => Some(PieceColor::Black),
+        Some(other) => return Err(CliError::InvalidValue("--engine".to_owned(), other.to_owned(), "white or black").into()),
+        None => None,
+    };
+    let clock = args
+        .parsed::<TimeControl>("--time", "a time control like 5+3 (minutes + increment seconds)")?
+        .map(Clock::new);
+
+    // The player's own pieces are drawn at the bottom, or the side to move's with two players
+    let pieces = args.parsed("--pieces", "ascii or unicode")?.unwrap_or_default();
+    let mut options = RenderOptions::for_stdout(pieces, !args.has("--no-color"));
+    options.orientation = args.parsed("--view", "white, black or auto")?.unwrap_or(Orientation::for_players(engine_color));
+    options.labels_both_sides = args.has("--labels");
+
+    match args.command() {
+        None | Some("play") if args.positional(1) == Some("bughouse") => BughouseMatch::default().game_loop(),
+        Some("bughouse") => BughouseMatch::default().game_loop(),
+        None | Some("play") => {
+            with_variant(args.positional(1), Play { engine: &mut engine, args, engine_color, clock, options, saved: None })?
+        },
+        Some(variant) if VARIANTS.contains(&variant) => {
+            with_variant(Some(variant), Play { engine: &mut engine, args, engine_color, clock, options, saved: None })?
+        },
+        Some("resume") => {
+            let path = args.positional(1).unwrap_or(AUTOSAVE_FILE);
+            let text = fs::read_to_string(path).map_err(|_| SaveError::Unreadable)?;
+            let variant = saved_variant(&text)?;
+            with_variant(Some(&variant), Play { engine: &mut engine, args, engine_color, clock, options, saved: Some(text) })?
+        },
+        Some("host") => {
+            let color = match args.value("--color").map(str::to_ascii_lowercase).as_deref() {
+                None | Some("white") => PieceColor::White,
+                Some("black") => PieceColor::Black,
+                Some(other) => return Err(CliError::InvalidValue("--color".to_owned(), other.to_owned(), "white or black").into()),
+            };
+            let port = args.parsed("--port", "a port number")?.unwrap_or(DEFAULT_PORT);
+            let listener = TcpListener::bind(("0.0.0.0", port)).map_err(|_| NetError::CannotListen(port))?;
+            let role = Role::Host(listener, color);
+            with_variant(args.positional(1), NetworkGame { engine: &mut engine, args, clock, options, role })?
+        },
+        Some("join") => {
+            let address = args.positional(1).ok_or(CliError::MissingArgument("join".to_owned(), "the host's address"))?;
+            let (connection, game) = Connection::join(address)?;
+            let variant = saved_variant(&game).map_err(NetError::Save)?;
+            let role = Role::Join(connection, game);
+            with_variant(Some(&variant), NetworkGame { engine: &mut engine, args, clock, options, role })?
+        },
+        Some("uci") => uci_loop(&mut engine),
+        Some("perft") => {
+            let depth = args.positional(1).ok_or(CliError::MissingArgument("perft".to_owned(), "a depth"))?;
+            let depth = depth
+                .parse()
+                .map_err(|_| CliError::InvalidValue("perft".to_owned(), depth.to_owned(), "a depth in plies"))?;
+            with_variant(args.positional(2), Perft { args, depth })?
+        },
+        Some("analyze") => {
+            let game = match args.positional(1) {
+                Some(position) => load_position(position)?,
+                None => start_position(args)?,
+            };
+            engine.multi_pv = args.parsed("--lines", "a number of lines")?.unwrap_or(1).clamp(1, MAX_MULTI_PV);
+            analyze(&mut engine, &game, args.parsed("--depth", "a number of plies")?.unwrap_or(MAX_DEPTH));
+        },
+        Some("annotate") => {
+            let path = args.positional(1).ok_or(CliError::MissingArgument("annotate".to_owned(), "a PGN file"))?;
+            let depth = args.parsed("--depth", "a number of plies")?.unwrap_or(ANNOTATE_DEPTH);
+            annotate_file(&mut engine, path, depth, args.value("--out"))?;
+        },
+        Some("pgn") => {
+            if args.positional(1) != Some("convert") {
+                return Err(CliError::MissingArgument("pgn".to_owned(), "the subcommand convert").into());
+            }
+            let path = args.positional(2).ok_or(CliError::MissingArgument("pgn convert".to_owned(), "a PGN file"))?;
+            let text = fs::read_to_string(path).map_err(|_| PgnError::Unreadable)?;
+            let converted = convert_games(&text, args.parsed("--to", "pgn, uci or fen")?.unwrap_or_default())?;
+            match args.value("--out") {
+                Some(out) => fs::write(out, converted).map_err(|_| CliError::Unwritable(out.to_owned()))?,
+                None => print!("{converted}"),
+            }
+        },
+        Some("fen") => {
+            let game = match args.positional(1) {
+                Some(position) => load_position(position)?,
+                None => start_position(args)?,
+            };
+            println!("{}", game.render(&options, None));
+            println!("{}", game.to_fen());
+            println!("{}", game.status());
+        },
+        Some("bench") => {
+            let depth = args.positional(1).map(|depth| {
+                depth.parse().map_err(|_| CliError::InvalidValue("bench".to_owned(), depth.to_owned(), "a depth in plies"))
+            });
+            bench(&mut engine, depth.transpose()?.unwrap_or(BENCH_DEPTH));
+        },
+        // Fits the evaluation weights to game results, starting from `--params` and writing
+        // back over it by default
+        Some("tune") => {
+            let path = args
+                .positional(1)
+                .ok_or(CliError::MissingArgument("tune".to_owned(), "a file of FEN positions labelled with results"))?;
+            let epochs = args.parsed("--epochs", "a number of passes")?.unwrap_or(100);
+            let out = args.value("--out").or(args.value("--params")).unwrap_or("eval_params.txt");
+            let positions = load_positions(path)?;
+            println!("Tuning on {} positions", positions.len());
+            let tuned = tune(&engine.params, &positions, epochs, |epoch, error| println!("Epoch {epoch}: error {error:.6}"));
+            tuned.save(out)?;
+            println!("Wrote {out}");
+        },
+        Some("match") => {
+            match_from_args(args, clock)?;
+        },
+        Some(command) => return Err(CliError::UnknownCommand(command.to_owned()).into()),
+    }
+    Ok(())
+}
+
+/// The variant a saved game is for, named as on the command line
+fn saved_variant(text: &str) -> Result<String, SaveError> {
+    // `Variant::NAME`s such as "King of the Hill" are written without spaces or dashes on the command line
+    let variant = saved_field(text, "variant")?.ok_or(SaveError::MissingField("variant"))?;
+    Ok(match variant.to_ascii_lowercase().replace([' ', '-'], "") {
+        variant if variant == "chess960" => "standard".to_owned(),
+        variant => variant,
+    })
+}
+
+/// The engine with the book, tablebases, network, weights, strength and depth the
+/// options ask for
+fn engine_from_args(args: &Args) -> Result<Engine, Box<dyn Error>> {
+    let mut engine = Engine::default();
+    if let Some(path) = args.value("--book") {
+        engine.book = Some(Book::open(path)?);
+    }
+    if let Some(paths) = args.value("--syzygy") {
+        engine.tablebase = Some(Arc::new(Tablebase::open(paths)?));
+    }
+    if let Some(path) = args.value("--nnue") {
+        engine.network = Some(Arc::new(Network::open(path)?));
+        engine.use_network = true;
+    }
+    if let Some(path) = args.value("--params") {
+        engine.params = Arc::new(EvalParams::open(path)?);
+    }
+    if let Some(level) = args.parsed::<u8>("--skill", "a level from 0 to 20")? {
+        engine.skill = Skill::new(level);
+    }
+    if let Some(elo) = args.parsed::<u32>("--elo", "a rating")? {
+        engine.skill = Skill::from_elo(elo);
+    }
+    if let Some(depth) = args.parsed::<u8>("--depth", "a number of plies")? {
+        engine.depth = depth.clamp(1, MAX_DEPTH);
+    }
+    Ok(engine)
+}
+
+/// The position `--fen` or `--chess960` gives, or the usual start, after the moves
+/// `--moves` lists
+fn start_position<V: Variant>(args: &Args) -> Result<Game<V>, Box<dyn Error>> {
+    let mut game = match (args.value("--chess960"), args.value("--fen")) {
+        (Some(_), Some(_)) => return Err(CliError::Conflicting("--chess960".to_owned(), "--fen".to_owned()).into()),
+        (Some(_), None) if V::NAME != Standard::NAME => return Err(CliError::Conflicting("--chess960".to_owned(), V::NAME.to_owned()).into()),
+        (Some("random"), None) => {
+            let seed = args.parsed("--seed", "a number")?.unwrap_or_else(|| Rng::from_time().next_u64());
+            Game::chess960_random(seed)
+        },
+        (Some(value), None) => {
+            let index = value.parse::<u16>().ok().filter(|&index| index < 960);
+            let invalid = || CliError::InvalidValue("--chess960".to_owned(), value.to_owned(), "a setup number from 0 to 959, or random");
+            Game::chess960(index.ok_or_else(invalid)?)
+        },
+        (None, Some(fen)) => Game::from_fen(fen)?,
+        (None, None) => Game::default(),
+    };
+    for text in args.value("--moves").unwrap_or("").split_whitespace() {
+        let mv = parse_san(&game, text).ok_or_else(|| PgnError::IllegalMove(text.to_owned()))?;
+        game.play(mv)?;
+    }
+    Ok(game)
+}
+
+/// Reads `match [--engine1 <settings>] [--engine2 <settings>] [--games <n>] [--concurrency <n>]
+/// [--openings <file>] [--pgn <file>] [--sprt <elo0,elo1[,alpha,beta]>]` along with `--time`.
+/// `--params`, `--nnue`, `--syzygy` and `--book` set both engines up, before their own settings.
+fn match_from_args(args: &Args, clock: Option<Clock>) -> Result<MatchScore, Box<dyn Error>> {
+    let defaults = EngineConfig {
+        params: args.value("--params").map(PathBuf::from),
+        nnue: args.value("--nnue").map(PathBuf::from),
+        syzygy: args.value("--syzygy").map(str::to_owned),
+        book: args.value("--book").map(PathBuf::from),
+        ..EngineConfig::default()
+    };
+    let engine = |flag: &str, name: &str| {
+        let config = EngineConfig { name: name.to_owned(), ..defaults.clone() };
+        config.with_settings(args.value(flag).unwrap_or(""))
+    };
+    let config = MatchConfig {
+        engines: [engine("--engine1", "engine1")?, engine("--engine2", "engine2")?],
+        openings: match args.value("--openings") {
+            Some(path) => load_openings(path)?,
+            None => default_openings(),
+        },
+        games: args.parsed("--games", "a number of games")?.unwrap_or(100),
+        concurrency: args.parsed("--concurrency", "a number of games")?.unwrap_or(1),
+        time: clock.map(|clock| clock.control),
+        sprt: args.value("--sprt").map(|sprt| sprt.parse::<Sprt>()).transpose()?,
+        pgn: args.value("--pgn").map(PathBuf::from),
+    };
+    Ok(run_match(&config)?)
 }
@@ -0,0 +1,268 @@
+// Imports
+use std::fmt::{Debug, Display};
+use std::str::FromStr;
+
+use crate::board::*;
+use crate::errors::*;
+use crate::game::*;
+use crate::piece::*;
+use crate::variant::*;
+
+// Custom Move Type Data
+
+/// A move from `start` to `end`. Castling is stored as the king moving onto its own rook,
+/// which covers both classical chess and Chess960.
+/// Drops (Crazyhouse, Bughouse) place `drop` from the pocket onto `end`, with `start == end`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Move {
+    pub start: Coordinates,
+    pub end: Coordinates,
+    pub promotion: Option<PieceType>,
+    pub drop: Option<PieceType>,
+}
+
+// Custom Move Type Traits
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(piece_type) = self.drop {
+            return write!(f, "{}@{}", piece_type_letter(piece_type).to_ascii_uppercase(), array_notation_to_chess_notation(self.end));
+        }
+        write!(f, "{}{}", array_notation_to_chess_notation(self.start), array_notation_to_chess_notation(self.end))?;
+        if let Some(piece_type) = self.promotion {
+            write!(f, "{}", piece_type_letter(piece_type))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses coordinate notation such as `e2e4` or `e7e8q`, and drops such as `N@f3`
+impl FromStr for Move {
+    type Err = ConversionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some((piece, square)) = s.split_once('@') {
+            // A bare "@e4" drops a pawn
+            let piece_type = match piece.chars().next() {
+                None => piece_type_from_letter('p'),
+                Some(letter) if piece.len() == 1 => piece_type_from_letter(letter),
+                Some(_) => None,
+            }
+            .filter(|piece_type| !matches!(piece_type, PieceType::King(_)))
+            .ok_or(ConversionError)?;
+            if square.len() != 2 {
+                return Err(ConversionError);
+            }
+            let end = chess_notation_to_array_notation(square).map_err(|_| ConversionError)?;
+            return Ok(Move::drop(piece_type, end));
+        }
+        if !(4..=5).contains(&s.len()) || !s.is_ascii() {
+            return Err(ConversionError);
+        }
+
+        let start = chess_notation_to_array_notation(&s[0..2]).map_err(|_| ConversionError)?;
+        let end = chess_notation_to_array_notation(&s[2..4]).map_err(|_| ConversionError)?;
+        let promotion = match s.chars().nth(4) {
+            None => None,
+            Some(letter) => Some(piece_type_from_letter(letter).ok_or(ConversionError)?),
+        };
+
+        Ok(Move { start, end, promotion, drop: None })
+    }
+}
+
+impl Move {
+    pub fn new(start: Coordinates, end: Coordinates) -> Move {
+        Move { start, end, promotion: None, drop: None }
+    }
+
+    pub fn drop(piece_type: PieceType, end: Coordinates) -> Move {
+        Move { start: end, end, promotion: None, drop: Some(piece_type) }
+    }
+}
+
+// Impl move generation for Game
+
+impl<V: Variant> Game<V> {
+    /// Every move the side to move may play, after the variant's legality rules.
+    /// Empty once the variant has decided the game (e.g. a king reached the hill).
+    pub fn legal_moves(&self) -> Vec<Move> {
+        if V::outcome(self).is_some() {
+            return Vec::new();
+        }
+        self.legal_moves_ignoring_outcome()
+    }
+
+    /// Legal moves without first asking whether the variant has already ended the game
+    pub fn legal_moves_ignoring_outcome(&self) -> Vec<Move> {
+        let moves = self
+            .pseudo_legal_moves()
+            .into_iter()
+            .filter(|&mv| self.position_after(mv).is_some())
+            .collect();
+        V::filter_legal(self, moves)
+    }
+
+    /// The position after a pseudo-legal move, `None` when the move turns out illegal.
+    /// Lets the search check legality only for the moves it gets to.
+    pub fn position_after(&self, mv: Move) -> Option<Game<V>> {
+        let mut after = *self;
+        (after.play_unchecked(mv).is_ok() && V::is_legal(self, mv, &after)).then_some(after)
+    }
+
+    /// Moves that follow each piece's movement rules, ignoring whether the king is left in check.
+    /// Castling moves are included whenever king and rook are unmoved; `make_move` rejects
+    /// the ones with a blocked or attacked path.
+    pub fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        for row in self.board.board {
+            for piece in row.into_iter().flatten().filter(|piece| piece.color == self.turn) {
+                let start = piece.coordinates;
+                match piece.piece_type {
+                    PieceType::Pawn(data) => self.pawn_moves(start, data, &mut moves),
+                    piece_type => {
+                        for end in self.board.attacks_from(start) {
+                            if self.board.get(end).is_none_or(|target| target.color != piece.color) {
+                                moves.push(Move::new(start, end));
+                            }
+                        }
+                        if piece_type == PieceType::King(false) {
+                            self.castling_moves(start, &mut moves);
+                        }
+                    },
+                }
+            }
+        }
+        V::adjust_pseudo_legal(self, &mut moves);
+        moves
+    }
+
+    fn pawn_moves(&self, start: Coordinates, data: PawnData, moves: &mut Vec<Move>) {
+        let forward = match self.turn {
+            PieceColor::White => -1,
+            PieceColor::Black => 1,
+        };
+
+        let mut ends = Vec::new();
+        if let Some(one) = offset_coords(start, forward, 0).filter(|&end| self.board.get(end).is_none()) {
+            ends.push(one);
+            if let Some(two) = offset_coords(one, forward, 0) {
+                if !data.has_moved && self.board.get(two).is_none() {
+                    ends.push(two);
+                }
+            }
+        }
+        for end in self.board.attacks_from(start) {
+            let enemy = self.board.get(end).is_some_and(|target| target.color != self.turn);
+            if enemy || self.en_passant == Some(end) {
+                ends.push(end);
+            }
+        }
+
+        for end in ends {
+            if end.0 == 0 || end.0 == 7 {
+                for &promotion in V::promotion_pieces() {
+                    moves.push(Move { start, end, promotion: Some(promotion), drop: None });
+                }
+            } else {
+                moves.push(Move::new(start, end));
+            }
+        }
+    }
+
+    fn castling_moves(&self, king: Coordinates, moves: &mut Vec<Move>) {
+        for file in 0..8 {
+            let rook = Coordinates(king.0, file);
+            if let Some(piece) = self.board.get(rook) {
+                if piece.color == self.turn && piece.piece_type == PieceType::Rook(false) {
+                    moves.push(Move::new(king, rook));
+                }
+            }
+        }
+    }
+
+    /// Returns true if the move takes an enemy piece, including en passant
+    pub fn is_capture(&self, mv: Move) -> bool {
+        self.captured_piece(mv).is_some()
+    }
+
+    /// The enemy piece a move would take, if any
+    pub fn captured_piece(&self, mv: Move) -> Option<Piece> {
+        let moving = self.board.get(mv.start)?;
+        if let Some(target) = self.board.get(mv.end) {
+            return Some(target).filter(|target| target.color != moving.color);
+        }
+        if matches!(moving.piece_type, PieceType::Pawn(_)) && self.en_passant == Some(mv.end) && mv.start.1 != mv.end.1 {
+            return self.board.get(Coordinates(mv.start.0, mv.end.1));
+        }
+        None
+    }
+
+    /// Rewrites classical castling input (king two squares towards a rook) to the
+    /// king-takes-rook form used by move generation
+    pub fn normalize_move(&self, mv: Move) -> Move {
+        match self.castling_rook(mv.start, mv.end) {
+            Some(rook) => Move { end: rook, ..mv },
+            None => mv,
+        }
+    }
+
+    /// The move as a player would write it: outside of Chess960, castling is shown as
+    /// the king's two square move (e1g1) rather than king takes rook
+    pub fn uci_move(&self, mv: Move) -> Move {
+        let is_castle = self.board.get(mv.start).is_some_and(|piece| piece.piece_type == PieceType::King(false)) &&
+            self.board.get(mv.end).is_some_and(|piece| piece.piece_type == PieceType::Rook(false));
+        if self.chess960 || !is_castle || mv.start.1 != 4 || (mv.end.1 != 0 && mv.end.1 != 7) {
+            return mv;
+        }
+        let (king_file, _) = castling_files(mv.start, mv.end);
+        Move::new(mv.start, Coordinates(mv.start.0, king_file))
+    }
+}
+
+// Functions
+
+pub fn piece_type_letter(piece_type: PieceType) -> char {
+    match piece_type {
+        PieceType::Pawn(_) => 'p',
+        PieceType::Knight => 'n',
+        PieceType::Bishop => 'b',
+        PieceType::Rook(_) => 'r',
+        PieceType::Queen => 'q',
+        PieceType::King(_) => 'k',
+    }
+}
+
+/// Piece type for a letter in either case. Rooks and kings count as moved,
+/// which is what a promoted piece needs.
+pub fn piece_type_from_letter(letter: char) -> Option<PieceType> {
+    match letter.to_ascii_lowercase() {
+        'p' => Some(PieceType::Pawn(PawnData { has_moved: true })),
+        'n' => Some(PieceType::Knight),
+        'b' => Some(PieceType::Bishop),
+        'r' => Some(PieceType::Rook(true)),
+        'q' => Some(PieceType::Queen),
+        'k' => Some(PieceType::King(true)),
+        _ => None,
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_serialize_with_algebraic_squares() {
+        let mv: Move = "e7e8q".parse().unwrap();
+        let json = serde_json::to_string(&mv).unwrap();
+        assert_eq!(json, r#"{"start":"e7","end":"e8","promotion":"Queen","drop":null}"#);
+        assert_eq!(serde_json::from_str::<Move>(&json).unwrap(), mv);
+
+        let drop: Move = "N@f3".parse().unwrap();
+        let json = serde_json::to_string(&drop).unwrap();
+        assert_eq!(json, r#"{"start":"f3","end":"f3","promotion":null,"drop":"Knight"}"#);
+        assert_eq!(serde_json::from_str::<Move>(&json).unwrap(), drop);
+    }
+}
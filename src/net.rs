@@ -0,0 +1,655 @@
+// Imports
+use std::fmt::Display;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::engine::*;
+use crate::errors::*;
+use crate::pgn::*;
+use crate::moves::*;
+use crate::piece::*;
+use crate::session::*;
+use crate::variant::*;
+
+/// Version in the greeting both sides send first. Games are only played between equal
+/// versions, so this goes up whenever a message changes meaning.
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Start of the greeting line, followed by the protocol version
+const GREETING: &str = "rust_chess";
+/// Port `host` listens on and `join` connects to when none is given
+pub const DEFAULT_PORT: u16 = 7878;
+/// How often each side pings the other while the game goes on
+const PING_INTERVAL: Duration = Duration::from_secs(2);
+/// Silence after which the connection counts as lost
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a guest gives each attempt to reconnect
+const RECONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+/// How often the loops check the clock and, while reconnecting, the listener
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Custom Message Type Data
+
+/// A line of the network protocol, or a few lines for `Game`:
+///
+/// ```text
+/// rust_chess 1             greeting, sent by both sides before anything else
+/// color black              the side the guest plays, from the host
+/// game 11                  the game in the save format, on the 11 lines that follow
+/// move e2e4 287.500        a move with the seconds its side had left when it was made
+/// draw                     offers a draw, or accepts the standing offer
+/// resign
+/// flag                     the sender ran out of time
+/// quit                     the sender left the game
+/// sync                     asks the host to send the game again
+/// ping                     keeps the connection alive
+/// ```
+///
+/// The host's game is the one that counts: it is sent whenever a guest connects or
+/// reconnects, and again whenever the two sides disagree about a move. Lines a side
+/// doesn't know are skipped.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    /// Holds the protocol version
+    Hello(u32),
+    Color(PieceColor),
+    /// Holds the game as `Session::to_save` writes it
+    Game(String),
+    /// Holds the move and its side's time left, when there is a clock
+    Move(Move, Option<Duration>),
+    Draw,
+    Resign,
+    Flag,
+    Quit,
+    Sync,
+    Ping,
+}
+
+// Custom Peer Type Data
+
+/// Which end of the connection this is, and so how to reconnect
+#[derive(Debug)]
+pub enum Peer {
+    /// Hosting, with the listener a guest reconnects to
+    Host(TcpListener),
+    /// Joined the host at this address
+    Guest(String),
+}
+
+// Custom Connection Type Data
+
+/// A connection to the other player of a network game
+#[derive(Debug)]
+pub struct Connection {
+    pub peer: Peer,
+    /// The side played at this end
+    pub color: PieceColor,
+    stream: TcpStream,
+    /// Reads the stream until `listen` hands it to its own thread
+    reader: Option<BufReader<TcpStream>>,
+    /// Counts connections, so that a lost one's last events can be told apart
+    generation: u32,
+}
+
+// Custom Event Type Data
+
+/// What the network loop waits for: a line typed at this end or a message from the other
+#[derive(Debug)]
+enum Event {
+    Input(String),
+    InputClosed,
+    /// Holds the connection's generation and the message
+    Received(u32, Message),
+    /// Holds the generation of the connection lost
+    Lost(u32),
+}
+
+// Custom Message Type Traits
+
+impl Display for Message {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Message::Hello(version) => write!(f, "{GREETING} {version}"),
+            Message::Color(color) => write!(f, "color {}", color.to_string().to_ascii_lowercase()),
+            Message::Game(text) => {
+                write!(f, "game {}", text.lines().count())?;
+                for line in text.lines() {
+                    write!(f, "\n{line}")?;
+                }
+                Ok(())
+            },
+            Message::Move(mv, Some(left)) => write!(f, "move {mv} {:.3}", left.as_secs_f64()),
+            Message::Move(mv, None) => write!(f, "move {mv}"),
+            Message::Draw => write!(f, "draw"),
+            Message::Resign => write!(f, "resign"),
+            Message::Flag => write!(f, "flag"),
+            Message::Quit => write!(f, "quit"),
+            Message::Sync => write!(f, "sync"),
+            Message::Ping => write!(f, "ping"),
+        }
+    }
+}
+
+// Impl Connection
+
+impl Connection {
+    /// Waits on `listener` for a guest to play the side other than `color`, greets it and
+    /// sends it `session`'s game. Connections that don't greet back are dropped.
+    pub fn host<V: Variant>(listener: TcpListener, color: PieceColor, session: &Session<V>) -> Result<Connection, NetError> {
+        let port = listener.local_addr().map_or(DEFAULT_PORT, |address| address.port());
+        loop {
+            let (stream, _) = listener.accept().map_err(|_| NetError::CannotListen(port))?;
+            match greet_guest(stream, color.swap(), session) {
+                Ok((stream, reader)) => {
+                    return Ok(Connection { peer: Peer::Host(listener), color, stream, reader: Some(reader), generation: 0 });
+                },
+                Err(error) => eprintln!("{error}"),
+            }
+        }
+    }
+
+    /// Joins the game hosted at `address`, `DEFAULT_PORT` unless it names a port,
+    /// returning the connection and the game the host sent
+    pub fn join(address: &str) -> Result<(Connection, String), NetError> {
+        let address = match address.contains(':') {
+            true => address.to_owned(),
+            false => format!("{address}:{DEFAULT_PORT}"),
+        };
+        let stream = TcpStream::connect(&address).map_err(|_| NetError::CannotConnect(address.clone()))?;
+        let (stream, reader, color, game) = greet_host(stream)?;
+        let connection = Connection { peer: Peer::Guest(address), color, stream, reader: Some(reader), generation: 0 };
+        Ok((connection, game))
+    }
+
+    /// Sends `message`. A failure is left for the listening thread to notice as a lost
+    /// connection.
+    pub fn send(&mut self, message: &Message) -> Result<(), NetError> {
+        writeln!(self.stream, "{message}").map_err(|_| NetError::Disconnected)
+    }
+
+    /// Reads messages on their own thread, passing them on to `events`
+    fn listen(&mut self, events: &Sender<Event>) {
+        let Some(mut reader) = self.reader.take() else {
+            return;
+        };
+        self.generation += 1;
+        let generation = self.generation;
+        let events = events.clone();
+        thread::spawn(move || loop {
+            let event = match receive(&mut reader) {
+                Ok(message) => Event::Received(generation, message),
+                Err(_) => Event::Lost(generation),
+            };
+            let lost = matches!(event, Event::Lost(_));
+            if events.send(event).is_err() || lost {
+                return;
+            }
+        });
+    }
+
+    /// Tries once to get the other player back: a guest connecting within a poll for the
+    /// host, connecting again for a guest. Returns the game a guest is sent.
+    fn reconnect<V: Variant>(&mut self, session: &Session<V>) -> Option<Result<Option<String>, NetError>> {
+        match &self.peer {
+            Peer::Host(listener) => {
+                listener.set_nonblocking(true).ok()?;
+                let Ok((stream, _)) = listener.accept() else {
+                    thread::sleep(POLL_INTERVAL);
+                    return None;
+                };
+                stream.set_nonblocking(false).ok()?;
+                Some(greet_guest(stream, self.color.swap(), session).map(|(stream, reader)| {
+                    self.stream = stream;
+                    self.reader = Some(reader);
+                    None
+                }))
+            },
+            Peer::Guest(address) => {
+                let address = address.to_socket_addrs().ok()?.next()?;
+                let stream = TcpStream::connect_timeout(&address, RECONNECT_TIMEOUT).ok()?;
+                Some(greet_host(stream).map(|(stream, reader, color, game)| {
+                    self.stream = stream;
+                    self.reader = Some(reader);
+                    self.color = color;
+                    Some(game)
+                }))
+            },
+        }
+    }
+}
+
+// Impl Session for network games
+
+impl<V: Variant> Session<V> {
+    /// Plays the game against the player at the other end of `connection` until it ends,
+    /// either player quits or the connection is lost for good. Moves and commands are
+    /// typed a line at a time as in `game_loop`; the opponent's moves are checked with
+    /// `Game::play` before they are made. When the connection drops the clock stops while
+    /// the host waits for the guest to come back and the guest keeps trying to reach it.
+    pub fn network_loop(&mut self, mut connection: Connection, engine: &mut Engine) {
+        let (sender, events) = mpsc::channel();
+        let input = sender.clone();
+        thread::spawn(move || {
+            for line in io::stdin().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if input.send(Event::Input(line.trim().to_owned())).is_err() {
+                    return;
+                }
+            }
+            let _ = input.send(Event::InputClosed);
+        });
+        connection.listen(&sender);
+
+        self.check_end();
+        self.prompt(connection.color);
+        let mut last_ping = Instant::now();
+        while !self.is_over() {
+            let local = connection.color;
+            if let Some(clock) = &mut self.clock {
+                clock.start(self.game.turn);
+            }
+            // Each side calls its own flag, the other's clock being a little behind
+            if self.clock.is_some_and(|clock| clock.is_flagged(local)) {
+                let _ = connection.send(&Message::Flag);
+                self.finish(Some(local.swap()), format!("{local} ran out of time"));
+                break;
+            }
+            if last_ping.elapsed() >= PING_INTERVAL {
+                let _ = connection.send(&Message::Ping);
+                last_ping = Instant::now();
+            }
+
+            let event = match events.recv_timeout(POLL_INTERVAL) {
+                Ok(event) => event,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            let position = self.game.to_fen();
+            match event {
+                Event::Input(line) if line.is_empty() => {},
+                Event::Input(line) => match self.network_command(&line, &mut connection, engine) {
+                    Ok(feedback) if feedback.is_empty() => {},
+                    Ok(feedback) => println!("{feedback}"),
+                    Err(error) => println!("{error}"),
+                },
+                Event::InputClosed => {
+                    let _ = connection.send(&Message::Quit);
+                    self.ending = Some("Game left unfinished".to_owned());
+                },
+                Event::Received(generation, message) if generation == connection.generation => {
+                    if let Some(feedback) = self.receive_message(message, &mut connection) {
+                        println!("{feedback}");
+                    }
+                },
+                Event::Lost(generation) if generation == connection.generation => {
+                    if !self.wait_for_reconnect(&mut connection, &sender, &events) {
+                        self.ending = Some("Game left unfinished".to_owned());
+                        break;
+                    }
+                    self.prompt(connection.color);
+                    continue;
+                },
+                // Left over from a connection that was lost
+                Event::Received(..) | Event::Lost(_) => {},
+            }
+            if !self.is_over() && self.game.to_fen() != position {
+                self.prompt(connection.color);
+            }
+        }
+
+        if let Some(ending) = &self.ending {
+            println!("{}", self.game.render(&self.options, self.last_move()));
+            println!("{ending}");
+        }
+    }
+
+    /// Shows the board and says whose move it is, `local` being the side played here
+    fn prompt(&self, local: PieceColor) {
+        println!("{}", self.game.render(&self.options, self.last_move()));
+        if let Some(clock) = &self.clock {
+            println!("{clock}");
+        }
+        let turn = self.game.turn;
+        match turn == local {
+            true => println!("{turn} to move, your move: a move (e4, Nf3) or a command (help lists them)"),
+            false => println!("{turn} to move, waiting for your opponent. Commands can still be typed."),
+        }
+    }
+
+    /// Runs a line typed at this end, telling the opponent of moves, draw offers,
+    /// resignations and leaving
+    fn network_command(&mut self, input: &str, connection: &mut Connection, engine: &mut Engine) -> Result<String, CommandError> {
+        let local = connection.color;
+        match input.parse()? {
+            Command::Undo | Command::Redo | Command::Load(_) | Command::Hint | Command::Eval => {
+                let name = input.split_whitespace().next().unwrap_or(input);
+                Err(CommandError::Unavailable(name.to_owned()))
+            },
+            Command::Move(_) if self.game.turn != local => Err(CommandError::NotYourMove),
+            Command::Move(text) => {
+                let mv = parse_san(&self.game, &text).ok_or_else(|| CommandError::Unknown(text.clone()))?;
+                let left = self.clock.map(|clock| clock.remaining(local));
+                let sent = self.game.uci_move(mv);
+                self.play(mv).map_err(|_| CommandError::Unknown(text))?;
+                let _ = connection.send(&Message::Move(sent, left));
+                Ok(String::new())
+            },
+            Command::Resign => {
+                self.finish(Some(local.swap()), format!("{local} resigns, {} wins", local.swap()));
+                let _ = connection.send(&Message::Resign);
+                Ok(String::new())
+            },
+            Command::Draw if self.draw_offer == Some(local.swap()) => {
+                self.finish(None, format!("{local} accepts the draw"));
+                let _ = connection.send(&Message::Draw);
+                Ok(String::new())
+            },
+            Command::Draw if self.draw_offer == Some(local) => Ok("Your draw offer stands".to_owned()),
+            Command::Draw => {
+                self.draw_offer = Some(local);
+                let _ = connection.send(&Message::Draw);
+                Ok("You offer a draw".to_owned())
+            },
+            Command::Quit => {
+                let _ = connection.send(&Message::Quit);
+                self.ending = Some("Game left unfinished".to_owned());
+                Ok(String::new())
+            },
+            command => self.execute(command, engine),
+        }
+    }
+
+    /// Acts on a message from the opponent, returning what to tell the player
+    fn receive_message(&mut self, message: Message, connection: &mut Connection) -> Option<String> {
+        let local = connection.color;
+        let remote = local.swap();
+        match message {
+            Message::Move(mv, left) => {
+                let before = self.game;
+                if before.turn == remote {
+                    if let (Some(clock), Some(left)) = (&mut self.clock, left) {
+                        clock.sync(remote, left);
+                    }
+                    if self.play(mv).is_ok() {
+                        return Some(format!("{remote} plays {}", san(&before, before.normalize_move(mv))));
+                    }
+                }
+                // The two games went apart, so the host's is sent again
+                match connection.peer {
+                    Peer::Host(_) => {
+                        let _ = connection.send(&Message::Game(self.to_save()));
+                        Some(format!("{remote} sent {mv}, which is not legal here, so the game was sent to them again"))
+                    },
+                    Peer::Guest(_) => {
+                        let _ = connection.send(&Message::Sync);
+                        Some(format!("{remote} sent {mv}, which is not legal here, so the game was asked for again"))
+                    },
+                }
+            },
+            Message::Draw if self.draw_offer == Some(local) => {
+                self.finish(None, format!("{remote} accepts the draw"));
+                None
+            },
+            Message::Draw => {
+                self.draw_offer = Some(remote);
+                Some(format!("{remote} offers a draw, type draw to accept"))
+            },
+            Message::Resign => {
+                self.finish(Some(local), format!("{remote} resigns, {local} wins"));
+                None
+            },
+            Message::Flag => {
+                self.finish(Some(local), format!("{remote} ran out of time"));
+                None
+            },
+            Message::Quit => {
+                self.ending = Some(format!("{remote} left the game"));
+                None
+            },
+            Message::Sync if matches!(connection.peer, Peer::Host(_)) => {
+                let _ = connection.send(&Message::Game(self.to_save()));
+                None
+            },
+            Message::Game(text) if matches!(connection.peer, Peer::Guest(_)) => Some(match self.sync_to(&text) {
+                Ok(()) => format!("Took the host's game, {} moves in", self.history.len()),
+                Err(error) => {
+                    self.ending = Some(error.to_string());
+                    error.to_string()
+                },
+            }),
+            Message::Hello(_) | Message::Color(_) | Message::Game(_) | Message::Sync | Message::Ping => None,
+        }
+    }
+
+    /// Replaces the game with the one the host sent
+    fn sync_to(&mut self, text: &str) -> Result<(), NetError> {
+        let synced = Session::from_save(text, self.options).map_err(NetError::Save)?;
+        let autosave = self.autosave.take();
+        *self = synced;
+        self.autosave = autosave;
+        Ok(())
+    }
+
+    /// Stops the clock and tries to get the other player back until they are, returning
+    /// `false` if the player here quits first
+    fn wait_for_reconnect(&mut self, connection: &mut Connection, sender: &Sender<Event>, events: &Receiver<Event>) -> bool {
+        if let Some(clock) = &mut self.clock {
+            clock.pause();
+        }
+        match &connection.peer {
+            Peer::Host(_) => println!("The connection was lost, waiting for your opponent to join again (type quit to stop)"),
+            Peer::Guest(address) => println!("The connection was lost, reconnecting to {address} (type quit to stop)"),
+        }
+
+        loop {
+            match events.try_recv() {
+                Ok(Event::Input(line)) if matches!(line.parse(), Ok(Command::Quit)) => return false,
+                Ok(Event::Input(_)) => println!("Not connected to your opponent, type quit to stop waiting"),
+                Ok(Event::InputClosed) => return false,
+                _ => {},
+            }
+            match connection.reconnect(self) {
+                None => {},
+                Some(Ok(game)) => {
+                    if let Some(Err(error)) = game.map(|game| self.sync_to(&game)) {
+                        println!("{error}");
+                        continue;
+                    }
+                    connection.listen(sender);
+                    println!("Your opponent is back");
+                    return true;
+                },
+                // The other side went away again before the game was through
+                Some(Err(NetError::Disconnected)) => {},
+                Some(Err(error)) => println!("{error}"),
+            }
+            if matches!(connection.peer, Peer::Guest(_)) {
+                thread::sleep(RECONNECT_TIMEOUT);
+            }
+        }
+    }
+}
+
+// Functions
+
+/// Greets a guest that connected, then tells it its color and sends it the game
+fn greet_guest<V: Variant>(stream: TcpStream, color: PieceColor, session: &Session<V>) -> Result<(TcpStream, BufReader<TcpStream>), NetError> {
+    let (mut stream, mut reader) = prepare(stream)?;
+    writeln!(stream, "{}", Message::Hello(PROTOCOL_VERSION)).map_err(|_| NetError::Disconnected)?;
+    receive_greeting(&mut reader)?;
+    writeln!(stream, "{}", Message::Color(color)).map_err(|_| NetError::Disconnected)?;
+    writeln!(stream, "{}", Message::Game(session.to_save())).map_err(|_| NetError::Disconnected)?;
+    Ok((stream, reader))
+}
+
+/// Greets the host after connecting, returning the side it gives and the game it sends
+fn greet_host(stream: TcpStream) -> Result<(TcpStream, BufReader<TcpStream>, PieceColor, String), NetError> {
+    let (mut stream, mut reader) = prepare(stream)?;
+    receive_greeting(&mut reader)?;
+    writeln!(stream, "{}", Message::Hello(PROTOCOL_VERSION)).map_err(|_| NetError::Disconnected)?;
+    let Message::Color(color) = receive(&mut reader)? else {
+        return Err(NetError::NotAGame);
+    };
+    let Message::Game(game) = receive(&mut reader)? else {
+        return Err(NetError::NotAGame);
+    };
+    Ok((stream, reader, color, game))
+}
+
+/// Sets `stream` to give up once the other side goes quiet, and splits off a reader
+fn prepare(stream: TcpStream) -> Result<(TcpStream, BufReader<TcpStream>), NetError> {
+    stream.set_read_timeout(Some(DISCONNECT_TIMEOUT)).map_err(|_| NetError::Disconnected)?;
+    let reader = BufReader::new(stream.try_clone().map_err(|_| NetError::Disconnected)?);
+    Ok((stream, reader))
+}
+
+/// Checks the other side's first line is the greeting of this protocol version
+fn receive_greeting(reader: &mut BufReader<TcpStream>) -> Result<(), NetError> {
+    let line = receive_line(reader)?;
+    let version = line.strip_prefix(GREETING).and_then(|version| version.trim().parse().ok()).ok_or(NetError::NotAGame)?;
+    match version {
+        PROTOCOL_VERSION => Ok(()),
+        version => Err(NetError::UnsupportedVersion(version)),
+    }
+}
+
+/// The next message this version knows, skipping other lines
+fn receive(reader: &mut BufReader<TcpStream>) -> Result<Message, NetError> {
+    loop {
+        let line = receive_line(reader)?;
+        let (name, argument) = line.split_once(' ').unwrap_or((&line, ""));
+        let message = match name {
+            GREETING => argument.trim().parse().ok().map(Message::Hello),
+            "color" => match argument.trim() {
+                "white" => Some(Message::Color(PieceColor::White)),
+                "black" => Some(Message::Color(PieceColor::Black)),
+                _ => None,
+            },
+            "game" => {
+                let lines = argument.trim().parse::<usize>().map_err(|_| NetError::NotAGame)?;
+                let mut text = String::new();
+                for _ in 0..lines {
+                    text.push_str(&receive_line(reader)?);
+                    text.push('\n');
+                }
+                Some(Message::Game(text))
+            },
+            "move" => {
+                let mut parts = argument.split_whitespace();
+                let left = |seconds: &str| seconds.parse::<f64>().ok().filter(|seconds| seconds.is_finite() && *seconds >= 0.0);
+                let mv = parts.next().and_then(|mv| mv.parse::<Move>().ok());
+                mv.map(|mv| Message::Move(mv, parts.next().and_then(left).map(Duration::from_secs_f64)))
+            },
+            "draw" => Some(Message::Draw),
+            "resign" => Some(Message::Resign),
+            "flag" => Some(Message::Flag),
+            "quit" => Some(Message::Quit),
+            "sync" => Some(Message::Sync),
+            "ping" => Some(Message::Ping),
+            _ => None,
+        };
+        if let Some(message) = message {
+            return Ok(message);
+        }
+    }
+}
+
+/// A line without its line ending, or `Disconnected` once the stream ends or goes quiet
+fn receive_line(reader: &mut BufReader<TcpStream>) -> Result<String, NetError> {
+    let mut line = String::new();
+    match reader.read_line(&mut line) {
+        Ok(read) if read > 0 => Ok(line.trim_end().to_owned()),
+        _ => Err(NetError::Disconnected),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::*;
+    use crate::render::*;
+    use std::net::Shutdown;
+
+    /// A host playing White and a guest joined to it over the loopback interface
+    fn connected() -> (Session, Connection, Session, Connection) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let host = Session::new(Game::default(), None, None, RenderOptions::default());
+        let hosting = thread::spawn(move || {
+            let connection = Connection::host(listener, PieceColor::White, &host).unwrap();
+            (host, connection)
+        });
+        let (guest_connection, game) = Connection::join(&address).unwrap();
+        let guest = Session::from_save(&game, RenderOptions::default()).unwrap();
+        let (host, host_connection) = hosting.join().unwrap();
+        (host, host_connection, guest, guest_connection)
+    }
+
+    /// The next message on a connection nothing listens to yet
+    fn next(connection: &mut Connection) -> Message {
+        receive(connection.reader.as_mut().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn moves_are_played_on_both_boards_and_illegal_ones_rejected() {
+        let (mut host, mut host_connection, mut guest, mut guest_connection) = connected();
+        let mut engine = Engine::default();
+        assert_eq!(guest_connection.color, PieceColor::Black);
+
+        assert_eq!(host.network_command("e4", &mut host_connection, &mut engine), Ok(String::new()));
+        let message = next(&mut guest_connection);
+        assert_eq!(guest.receive_message(message, &mut guest_connection).as_deref(), Some("White plays e4"));
+        assert_eq!(host.network_command("d4", &mut host_connection, &mut engine), Err(CommandError::NotYourMove));
+        assert_eq!(guest.network_command("e5", &mut guest_connection, &mut engine), Ok(String::new()));
+        let message = next(&mut host_connection);
+        assert_eq!(host.receive_message(message, &mut host_connection).as_deref(), Some("Black plays e5"));
+        assert!(host.network_command("Qh8", &mut host_connection, &mut engine).is_err());
+        assert_eq!(guest.game.to_fen(), host.game.to_fen());
+
+        // A move that isn't legal on the guest's board has it ask for the host's game
+        let position = guest.game.to_fen();
+        host_connection.send(&Message::Move(Move::new(Coordinates(7, 4), Coordinates(5, 4)), None)).unwrap();
+        let message = next(&mut guest_connection);
+        assert!(guest.receive_message(message, &mut guest_connection).unwrap().contains("not legal"));
+        assert_eq!(guest.game.to_fen(), position);
+        assert_eq!(next(&mut host_connection), Message::Sync);
+        assert_eq!(host.receive_message(Message::Sync, &mut host_connection), None);
+        let message = next(&mut guest_connection);
+        assert_eq!(guest.receive_message(message, &mut guest_connection).as_deref(), Some("Took the host's game, 2 moves in"));
+        assert_eq!(guest.game.to_fen(), host.game.to_fen());
+    }
+
+    #[test]
+    fn reconnecting_brings_the_guest_back_to_the_hosts_position() {
+        let (mut host, mut host_connection, mut guest, mut guest_connection) = connected();
+        let mut engine = Engine::default();
+
+        // The move is lost along with the connection
+        host.network_command("e4", &mut host_connection, &mut engine).unwrap();
+        guest_connection.stream.shutdown(Shutdown::Both).unwrap();
+        assert_ne!(guest.game.to_fen(), host.game.to_fen());
+
+        let (host_sender, host_events) = mpsc::channel();
+        let hosting = thread::spawn(move || {
+            assert!(host.wait_for_reconnect(&mut host_connection, &host_sender, &host_events));
+            (host, host_connection, host_sender, host_events)
+        });
+        let (guest_sender, guest_events) = mpsc::channel();
+        assert!(guest.wait_for_reconnect(&mut guest_connection, &guest_sender, &guest_events));
+        let (mut host, mut host_connection, _host_sender, host_events) = hosting.join().unwrap();
+        assert_eq!(guest.game.to_fen(), host.game.to_fen());
+        assert_eq!(guest_connection.color, PieceColor::Black);
+
+        guest.network_command("e5", &mut guest_connection, &mut engine).unwrap();
+        let Ok(Event::Received(generation, message)) = host_events.recv_timeout(DISCONNECT_TIMEOUT) else {
+            panic!("the host heard nothing after reconnecting");
+        };
+        assert_eq!(generation, host_connection.generation);
+        assert_eq!(host.receive_message(message, &mut host_connection).as_deref(), Some("Black plays e5"));
+        assert_eq!(guest.game.to_fen(), host.game.to_fen());
+    }
+}
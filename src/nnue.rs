@@ -0,0 +1,320 @@
+// Imports
+use std::fs;
+use std::path::Path;
+
+use crate::board::*;
+use crate::errors::*;
+use crate::piece::*;
+use crate::square_set::*;
+
+/// First bytes of every network file
+const MAGIC: &[u8; 4] = b"RCNN";
+const VERSION: u32 = 1;
+/// Inputs per perspective: own and enemy pieces, six kinds, 64 squares
+const FEATURES: usize = 2 * 6 * 64;
+/// Largest hidden layer a network file may have
+pub const MAX_HIDDEN: usize = 512;
+/// Clipped ReLU ceiling, the accumulator's fixed point one
+const QA: i32 = 255;
+/// Fixed point one of the output weights
+const QB: i32 = 64;
+/// Network output to centipawns
+const SCALE: i64 = 400;
+/// Largest evaluation a network gives, well clear of mate and tablebase scores
+const MAX_EVALUATION: i64 = 20_000;
+
+// Custom Network Type Data
+
+/// A small quantized network of the "768 -> hidden x 2 -> 1" shape. Every piece switches on
+/// one input per perspective, from the side to move and from the other side with the board
+/// mirrored, each feeding its own half of the hidden layer. The hidden layer is cheap to
+/// update as pieces move (see `Accumulator`) and is squashed by a clipped ReLU before the
+/// output neuron.
+///
+/// The file format, all little endian: `RCNN`, version (u32), hidden size (u32), input
+/// weights (i16, input-major), hidden biases (i16), output weights (i16, side to move's
+/// half first), output bias (i32).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Network {
+    hidden: usize,
+    feature_weights: Vec<i16>,
+    feature_bias: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i32,
+}
+
+// Custom Accumulator Type Data
+
+/// The hidden layer before activation, one half per perspective indexed White then Black.
+/// Moves update it from the previous position's accumulator instead of starting over.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Accumulator {
+    values: [[i16; MAX_HIDDEN]; 2],
+}
+
+// Custom Accumulator Type Traits
+
+impl Default for Accumulator {
+    fn default() -> Self {
+        Accumulator { values: [[0; MAX_HIDDEN]; 2] }
+    }
+}
+
+// Impl Network
+
+impl Network {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Network, NnueError> {
+        let bytes = fs::read(path).map_err(|_| NnueError::Unreadable)?;
+        Network::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Network, NnueError> {
+        if bytes.len() < 12 || &bytes[..4] != MAGIC {
+            return Err(NnueError::InvalidHeader);
+        }
+        let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        if version != VERSION {
+            return Err(NnueError::UnsupportedVersion(version));
+        }
+        let hidden = u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize;
+        if hidden == 0 || hidden > MAX_HIDDEN {
+            return Err(NnueError::InvalidSize);
+        }
+        let expected = 12 + 2 * (FEATURES * hidden + hidden + 2 * hidden) + 4;
+        if bytes.len() != expected {
+            return Err(NnueError::InvalidSize);
+        }
+
+        let mut i16s = bytes[12..expected - 4]
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]));
+        Ok(Network {
+            hidden,
+            feature_weights: i16s.by_ref().take(FEATURES * hidden).collect(),
+            feature_bias: i16s.by_ref().take(hidden).collect(),
+            output_weights: i16s.collect(),
+            output_bias: i32::from_le_bytes(bytes[expected - 4..].try_into().unwrap()),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.extend((self.hidden as u32).to_le_bytes());
+        for value in self.feature_weights.iter().chain(&self.feature_bias).chain(&self.output_weights) {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes.extend(self.output_bias.to_le_bytes());
+        bytes
+    }
+
+    pub fn hidden(&self) -> usize {
+        self.hidden
+    }
+
+    /// Builds the accumulator of `board` from scratch
+    pub fn refresh(&self, board: &Board) -> Accumulator {
+        let mut accumulator = Accumulator::default();
+        for values in &mut accumulator.values {
+            values[..self.hidden].copy_from_slice(&self.feature_bias);
+        }
+        for piece in board.board.iter().flatten().flatten() {
+            self.add_piece(&mut accumulator, *piece);
+        }
+        accumulator
+    }
+
+    /// The accumulator of `after` from that of `before`, touching only the squares that
+    /// changed. Covers any move of any variant: castling, en passant, drops and explosions.
+    pub fn update(&self, accumulator: &Accumulator, before: &Board, after: &Board) -> Accumulator {
+        let mut accumulator = *accumulator;
+        for (old_row, new_row) in before.board.iter().zip(&after.board) {
+            for (old, new) in old_row.iter().zip(new_row) {
+                if old == new {
+                    continue;
+                }
+                if let Some(piece) = old {
+                    self.remove_piece(&mut accumulator, *piece);
+                }
+                if let Some(piece) = new {
+                    self.add_piece(&mut accumulator, *piece);
+                }
+            }
+        }
+        accumulator
+    }
+
+    /// Centipawns for `turn`, the side to move
+    pub fn evaluate(&self, accumulator: &Accumulator, turn: PieceColor) -> i32 {
+        let (us, them) = match turn {
+            PieceColor::White => (&accumulator.values[0], &accumulator.values[1]),
+            PieceColor::Black => (&accumulator.values[1], &accumulator.values[0]),
+        };
+        let (our_weights, their_weights) = self.output_weights.split_at(self.hidden);
+        // Summed in i64, as the weights come from a file and can add up to more than an i32 holds
+        let sum = crelu_dot(&us[..self.hidden], our_weights) + crelu_dot(&them[..self.hidden], their_weights);
+        let centipawns = (sum + self.output_bias as i64) * SCALE / (QA * QB) as i64;
+        centipawns.clamp(-MAX_EVALUATION, MAX_EVALUATION) as i32
+    }
+
+    fn add_piece(&self, accumulator: &mut Accumulator, piece: Piece) {
+        for (perspective, values) in [PieceColor::White, PieceColor::Black].into_iter().zip(&mut accumulator.values) {
+            let weights = self.weights(feature_index(perspective, piece));
+            for (value, weight) in values[..self.hidden].iter_mut().zip(weights) {
+                *value = value.wrapping_add(*weight);
+            }
+        }
+    }
+
+    fn remove_piece(&self, accumulator: &mut Accumulator, piece: Piece) {
+        for (perspective, values) in [PieceColor::White, PieceColor::Black].into_iter().zip(&mut accumulator.values) {
+            let weights = self.weights(feature_index(perspective, piece));
+            for (value, weight) in values[..self.hidden].iter_mut().zip(weights) {
+                *value = value.wrapping_sub(*weight);
+            }
+        }
+    }
+
+    fn weights(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * self.hidden..(feature + 1) * self.hidden]
+    }
+}
+
+// Functions
+
+/// Input switched on by `piece` seen from `perspective`: Black sees the board mirrored,
+/// so both perspectives look at their own pieces from the bottom
+fn feature_index(perspective: PieceColor, piece: Piece) -> usize {
+    let relative = if piece.color == perspective { 0 } else { 1 };
+    let kind = match piece.piece_type {
+        PieceType::Pawn(_) => 0,
+        PieceType::Knight => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook(_) => 3,
+        PieceType::Queen => 4,
+        PieceType::King(_) => 5,
+    };
+    let square = match perspective {
+        PieceColor::White => piece.coordinates,
+        PieceColor::Black => Coordinates(7 - piece.coordinates.0, piece.coordinates.1),
+    };
+    (relative * 6 + kind) * 64 + square_index(square) as usize
+}
+
+/// Sum of clipped ReLU activations times weights, with AVX2 when the CPU has it
+fn crelu_dot(values: &[i16], weights: &[i16]) -> i64 {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        // Safety: the CPU supports AVX2, checked just above
+        return unsafe { crelu_dot_avx2(values, weights) };
+    }
+    crelu_dot_scalar(values, weights)
+}
+
+fn crelu_dot_scalar(values: &[i16], weights: &[i16]) -> i64 {
+    values
+        .iter()
+        .zip(weights)
+        .map(|(&value, &weight)| (value as i32).clamp(0, QA) as i64 * weight as i64)
+        .sum()
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn crelu_dot_avx2(values: &[i16], weights: &[i16]) -> i64 {
+    use std::arch::x86_64::*;
+
+    let zero = _mm256_setzero_si256();
+    let ceiling = _mm256_set1_epi16(QA as i16);
+    let mut sums = _mm256_setzero_si256();
+    let chunks = values.len() / 16;
+    for chunk in 0..chunks {
+        // Safety: both slices hold at least `chunks * 16` values, and loadu takes any alignment
+        let (value, weight) = unsafe {
+            (
+                _mm256_loadu_si256(values.as_ptr().add(chunk * 16) as *const __m256i),
+                _mm256_loadu_si256(weights.as_ptr().add(chunk * 16) as *const __m256i),
+            )
+        };
+        let clipped = _mm256_min_epi16(_mm256_max_epi16(value, zero), ceiling);
+        // Pairs of 16 bit products summed into 32 bit lanes. Each lane takes at most
+        // MAX_HIDDEN / 8 products of 255 * 32768, which still fits.
+        sums = _mm256_add_epi32(sums, _mm256_madd_epi16(clipped, weight));
+    }
+
+    let mut lanes = [0i32; 8];
+    // Safety: `lanes` is 32 bytes, the size of one register
+    unsafe { _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, sums) };
+    lanes.iter().map(|&lane| lane as i64).sum::<i64>() + crelu_dot_scalar(&values[chunks * 16..], &weights[chunks * 16..])
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::*;
+    use crate::variant::*;
+
+    const MATERIAL: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/nnue/material.nnue");
+
+    #[test]
+    fn updates_match_a_fresh_refresh() {
+        let network = Network::open(MATERIAL).unwrap();
+        // Castling both ways, en passant, captures and a promotion
+        let moves = "e2e4 d7d5 e4d5 c7c5 d5c6 g8f6 c6b7 c8d7 b7a8q d8c7 g1f3 e7e5 f1c4 f8d6 e1h1 c7c4 a8b8 d7c8 d2d3 e8h8 d3c4 e5e4 b1c3 e4e3 c1e3";
+        let mut game = Game::<Standard>::default();
+        let mut accumulator = network.refresh(&game.board);
+        for text in moves.split_whitespace() {
+            let before = game.board;
+            game.play(text.parse().unwrap()).expect(text);
+            accumulator = network.update(&accumulator, &before, &game.board);
+            assert_eq!(accumulator, network.refresh(&game.board), "after {text}");
+        }
+    }
+
+    #[test]
+    fn material_network_counts_material() {
+        let network = Network::open(MATERIAL).unwrap();
+        let start = Game::<Standard>::default();
+        let accumulator = network.refresh(&start.board);
+        assert_eq!(network.evaluate(&accumulator, PieceColor::White), network.evaluate(&accumulator, PieceColor::Black));
+
+        let up_a_queen = Game::<Standard>::from_fen("rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        let accumulator = network.refresh(&up_a_queen.board);
+        let score = network.evaluate(&accumulator, PieceColor::White);
+        assert!(score > 700, "{score}");
+        assert_eq!(network.evaluate(&accumulator, PieceColor::Black), -score);
+    }
+
+    #[test]
+    fn large_weights_are_clamped_rather_than_overflowing() {
+        let network = Network {
+            hidden: MAX_HIDDEN,
+            feature_weights: vec![i16::MAX; FEATURES * MAX_HIDDEN],
+            feature_bias: vec![i16::MAX; MAX_HIDDEN],
+            output_weights: vec![i16::MAX; 2 * MAX_HIDDEN],
+            output_bias: i32::MAX,
+        };
+        let accumulator = network.refresh(&Game::<Standard>::default().board);
+        assert_eq!(network.evaluate(&accumulator, PieceColor::White), MAX_EVALUATION as i32);
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn avx2_matches_scalar() {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+        let network = Network::open(MATERIAL).unwrap();
+        let accumulator = network.refresh(&Game::<Standard>::default().board);
+        let mut values: Vec<i16> = accumulator.values[0].to_vec();
+        // Negative, clipped and odd length inputs as well as the network's own
+        values.extend((0..MAX_HIDDEN as i32).map(|i| ((i * 7919) % 1000 - 300) as i16));
+        let weights: Vec<i16> = (0..values.len() as i32).map(|i| ((i * 104_729) % 65_536 - 32_768) as i16).collect();
+        for length in [values.len(), 512, 37, 16, 1] {
+            // Safety: the CPU supports AVX2, checked above
+            let avx2 = unsafe { crelu_dot_avx2(&values[..length], &weights[..length]) };
+            assert_eq!(avx2, crelu_dot_scalar(&values[..length], &weights[..length]), "{length} values");
+        }
+    }
+}
@@ -0,0 +1,43 @@
+// Imports
+use crate::game::*;
+use crate::moves::*;
+use crate::variant::*;
+
+// Functions
+
+/// Counts the leaf nodes of the legal move tree `depth` plies deep, for checking move
+/// generation against known totals
+pub fn perft<V: Variant>(game: &Game<V>, depth: u8) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = game.legal_moves();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    moves
+        .into_iter()
+        .map(|mv| {
+            let mut next = *game;
+            match next.play_unchecked(mv) {
+                Ok(_) => perft(&next, depth - 1),
+                Err(_) => 0,
+            }
+        })
+        .sum()
+}
+
+/// `perft` split by the first move, which is given as a player would write it
+pub fn divide<V: Variant>(game: &Game<V>, depth: u8) -> Vec<(Move, u64)> {
+    game.legal_moves()
+        .into_iter()
+        .map(|mv| {
+            let mut next = *game;
+            let nodes = match next.play_unchecked(mv) {
+                Ok(_) => perft(&next, depth.saturating_sub(1)),
+                Err(_) => 0,
+            };
+            (game.uci_move(mv), nodes)
+        })
+        .collect()
+}
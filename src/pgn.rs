@@ -0,0 +1,513 @@
+// Imports
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::errors::*;
+use crate::game::*;
+use crate::moves::*;
+use crate::piece::*;
+use crate::variant::*;
+
+/// Longest movetext line written, as the PGN standard asks
+const LINE_WIDTH: usize = 79;
+
+// Custom Pgn Type Data
+
+/// One game in Portable Game Notation: tag pairs, then the moves in SAN, each with an
+/// optional comment, and the result (`1-0`, `0-1`, `1/2-1/2` or `*` while unfinished)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pgn {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<PgnMove>,
+    pub result: String,
+}
+
+// Custom PgnMove Type Data
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnMove {
+    /// Full move number, as in FEN
+    pub number: u16,
+    pub color: PieceColor,
+    pub san: String,
+    /// Numeric annotation glyph, e.g. 2 for `?` or 4 for `??`
+    pub nag: Option<u8>,
+    pub comment: Option<String>,
+    /// Alternatives to this move as numbered SAN movetext, see `movetext`
+    pub variations: Vec<String>,
+}
+
+// Custom PgnFormat Type Data
+
+/// What `convert_games` rewrites games as
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum PgnFormat {
+    /// Tidy PGN with every move checked and written in standard SAN
+    #[default]
+    Pgn,
+    /// A UCI `position` command for each game
+    Uci,
+    /// Every position of each finished game as a FEN and the result, as `tune` reads
+    Fen,
+}
+
+// Custom Pgn Type Traits
+
+impl Display for Pgn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, value) in &self.tags {
+            writeln!(f, "[{name} \"{}\"]", value.replace('\\', "\\\\").replace('"', "\\\""))?;
+        }
+        writeln!(f)?;
+
+        // Black's moves get their own number after a comment, or when the game starts with one
+        let mut tokens = Vec::new();
+        let mut numbered = false;
+        for mv in &self.moves {
+            match mv.color {
+                PieceColor::White => tokens.push(format!("{}.", mv.number)),
+                PieceColor::Black if !numbered => tokens.push(format!("{}...", mv.number)),
+                PieceColor::Black => (),
+            }
+            tokens.push(mv.san.clone());
+            tokens.extend(mv.nag.map(|nag| format!("${nag}")));
+            numbered = mv.comment.is_none() && mv.variations.is_empty();
+            if let Some(comment) = &mv.comment {
+                tokens.extend(format!("{{{comment}}}").split_whitespace().map(str::to_owned));
+            }
+            for variation in &mv.variations {
+                tokens.extend(format!("({variation})").split_whitespace().map(str::to_owned));
+            }
+        }
+        tokens.push(self.result.clone());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > LINE_WIDTH {
+                writeln!(f, "{line}")?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        writeln!(f, "{line}")
+    }
+}
+
+impl FromStr for Pgn {
+    type Err = PgnError;
+
+    /// Reads the first game of PGN text, see `read_games`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        read_games(s)?.into_iter().next().ok_or(PgnError::NoGame)
+    }
+}
+
+impl FromStr for PgnFormat {
+    type Err = ConversionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "pgn" => Ok(PgnFormat::Pgn),
+            "uci" => Ok(PgnFormat::Uci),
+            "fen" => Ok(PgnFormat::Fen),
+            _ => Err(ConversionError),
+        }
+    }
+}
+
+// Impl Pgn
+
+impl Pgn {
+    /// An unfinished game starting from `start`, with the seven standard tags unknown and
+    /// `Variant`, `SetUp` and `FEN` tags where the game needs them
+    pub fn new<V: Variant>(start: &Game<V>) -> Pgn {
+        let mut pgn = Pgn {
+            tags: ["Event", "Site", "Date", "Round", "White", "Black"]
+                .into_iter()
+                .map(|name| (name.to_owned(), if name == "Date" { "????.??.??" } else { "?" }.to_owned()))
+                .collect(),
+            moves: Vec::new(),
+            result: "*".to_owned(),
+        };
+        pgn.set_tag("Result", "*");
+        if start.chess960 {
+            pgn.set_tag("Variant", "Chess960");
+        } else if V::NAME != Standard::NAME {
+            pgn.set_tag("Variant", V::NAME);
+        }
+        let fen = start.to_fen();
+        if fen != Game::<V>::default().to_fen() {
+            pgn.set_tag("SetUp", "1");
+            pgn.set_tag("FEN", &fen);
+        }
+        pgn
+    }
+
+    /// Sets a tag, replacing its value if the tag is already there
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value.to_owned(),
+            None => self.tags.push((name.to_owned(), value.to_owned())),
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+    }
+
+    /// Records `mv` played in `before`
+    pub fn push<V: Variant>(&mut self, before: &Game<V>, mv: Move, comment: Option<String>) {
+        self.moves.push(PgnMove {
+            number: before.move_num / 2 + 1,
+            color: before.turn,
+            san: san(before, mv),
+            nag: None,
+            comment,
+            variations: Vec::new(),
+        });
+    }
+
+    /// The position the game starts from and its moves, checked to be legal
+    pub fn replay<V: Variant>(&self) -> Result<(Game<V>, Vec<Move>), PgnError> {
+        let mut start = match self.tag("FEN") {
+            Some(fen) => Game::from_fen(fen).map_err(|_| PgnError::InvalidFen)?,
+            None => Game::default(),
+        };
+        start.chess960 |= self.tag("Variant").is_some_and(|variant| variant.eq_ignore_ascii_case("chess960"));
+
+        let mut game = start;
+        let mut moves = Vec::new();
+        for pgn_move in &self.moves {
+            let mv = parse_san(&game, &pgn_move.san).ok_or_else(|| PgnError::IllegalMove(pgn_move.san.clone()))?;
+            game.play(mv).map_err(|_| PgnError::IllegalMove(pgn_move.san.clone()))?;
+            moves.push(mv);
+        }
+        Ok((start, moves))
+    }
+
+    /// The game replayed and written in `format`
+    pub fn convert<V: Variant>(&self, format: PgnFormat) -> Result<String, PgnError> {
+        let (start, moves) = self.replay::<V>()?;
+        let mut game = start;
+        let mut converted = String::new();
+        match format {
+            PgnFormat::Pgn => {
+                let mut pgn = Pgn { moves: Vec::new(), ..self.clone() };
+                for (mv, old) in moves.into_iter().zip(&self.moves) {
+                    pgn.push(&game, mv, old.comment.clone());
+                    if let Some(new) = pgn.moves.last_mut() {
+                        new.nag = old.nag;
+                        new.variations = old.variations.clone();
+                    }
+                    game.play(mv).map_err(|_| PgnError::IllegalMove(old.san.clone()))?;
+                }
+                converted = format!("{pgn}\n");
+            },
+            PgnFormat::Uci => {
+                converted = match self.tag("FEN") {
+                    Some(_) => format!("position fen {}", start.to_fen()),
+                    None => "position startpos".to_owned(),
+                };
+                if !moves.is_empty() {
+                    converted.push_str(" moves");
+                }
+                for (mv, old) in moves.into_iter().zip(&self.moves) {
+                    converted.push_str(&format!(" {}", game.uci_move(mv)));
+                    game.play(mv).map_err(|_| PgnError::IllegalMove(old.san.clone()))?;
+                }
+                converted.push('\n');
+            },
+            // Unfinished games say nothing about who was better, so they are left out
+            PgnFormat::Fen if self.result == "*" => (),
+            PgnFormat::Fen => {
+                converted.push_str(&format!("{} {}\n", game.to_fen(), self.result));
+                for (mv, old) in moves.into_iter().zip(&self.moves) {
+                    game.play(mv).map_err(|_| PgnError::IllegalMove(old.san.clone()))?;
+                    converted.push_str(&format!("{} {}\n", game.to_fen(), self.result));
+                }
+            },
+        }
+        Ok(converted)
+    }
+
+    /// Ends the game with `winner`, a draw when `None`
+    pub fn set_result(&mut self, winner: Option<PieceColor>) {
+        self.result = result_token(winner).to_owned();
+        self.set_tag("Result", result_token(winner));
+    }
+}
+
+// Functions
+
+/// Reads every game in PGN text. Comments and NAGs are kept with the move before them,
+/// while variations, `!?` annotations and move numbers are skipped. Moves are kept as
+/// written, `Pgn::replay` checks them.
+pub fn read_games(text: &str) -> Result<Vec<Pgn>, PgnError> {
+    let mut games = Vec::new();
+    let mut pgn = Pgn { tags: Vec::new(), moves: Vec::new(), result: "*".to_owned() };
+    let mut in_moves = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '[' => {
+                // Tags after moves start the next game, one that had no result
+                if in_moves {
+                    games.push(finish_game(pgn));
+                    pgn = Pgn { tags: Vec::new(), moves: Vec::new(), result: "*".to_owned() };
+                    in_moves = false;
+                }
+                let (name, value) = read_tag(&mut chars)?;
+                pgn.tags.push((name, value));
+            },
+            '{' => {
+                let comment: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                if let Some(last) = pgn.moves.last_mut() {
+                    let comment = comment.split_whitespace().collect::<Vec<_>>().join(" ");
+                    last.comment = Some(comment);
+                }
+            },
+            ';' => {
+                chars.by_ref().take_while(|&c| c != '\n').for_each(drop);
+            },
+            '(' => {
+                let mut depth = 1;
+                while depth > 0 {
+                    match chars.next().ok_or(PgnError::UnterminatedComment)? {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        '{' => {
+                            chars.by_ref().take_while(|&c| c != '}').for_each(drop);
+                        },
+                        _ => (),
+                    }
+                }
+            },
+            '$' => {
+                let mut nag = String::new();
+                while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                    nag.push(digit);
+                }
+                if let Some(last) = pgn.moves.last_mut() {
+                    last.nag = nag.parse().ok();
+                }
+            },
+            c if c.is_whitespace() => (),
+            c => {
+                let mut token = c.to_string();
+                while let Some(c) = chars.next_if(|&c| !c.is_whitespace() && !"{}();[$".contains(c)) {
+                    token.push(c);
+                }
+                if ["1-0", "0-1", "1/2-1/2", "*"].contains(&token.as_str()) {
+                    pgn.result = token;
+                    games.push(finish_game(pgn));
+                    pgn = Pgn { tags: Vec::new(), moves: Vec::new(), result: "*".to_owned() };
+                    in_moves = false;
+                    continue;
+                }
+                let unnumbered = token.trim_start_matches(|c: char| c.is_ascii_digit());
+                let san = match unnumbered.starts_with('.') {
+                    true => unnumbered.trim_start_matches('.'),
+                    false => &token,
+                };
+                let san = san.trim_end_matches(['!', '?']);
+                if !san.is_empty() {
+                    in_moves = true;
+                    pgn.moves.push(PgnMove {
+                        number: 0,
+                        color: PieceColor::White,
+                        san: san.to_owned(),
+                        nag: None,
+                        comment: None,
+                        variations: Vec::new(),
+                    });
+                }
+            },
+        }
+    }
+    if in_moves || !pgn.tags.is_empty() {
+        games.push(finish_game(pgn));
+    }
+    Ok(games)
+}
+
+/// Reads `Name "value"]` after a tag's opening bracket
+fn read_tag(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<(String, String), PgnError> {
+    let name: String = chars.by_ref().take_while(|&c| c != '"').collect();
+    let mut value = String::new();
+    loop {
+        match chars.next().ok_or(PgnError::InvalidTag)? {
+            '\\' => value.push(chars.next().ok_or(PgnError::InvalidTag)?),
+            '"' => break,
+            c => value.push(c),
+        }
+    }
+    if chars.by_ref().take_while(|&c| c != ']').any(|c| !c.is_whitespace()) || name.trim().is_empty() {
+        return Err(PgnError::InvalidTag);
+    }
+    Ok((name.trim().to_owned(), value))
+}
+
+/// Numbers the moves of a game just read, from its FEN tag, and fills in a missing result
+fn finish_game(mut pgn: Pgn) -> Pgn {
+    let fen: Vec<String> = pgn.tag("FEN").unwrap_or_default().split_whitespace().map(str::to_owned).collect();
+    let mut color = match fen.get(1).map(String::as_str) {
+        Some("b") => PieceColor::Black,
+        _ => PieceColor::White,
+    };
+    let mut number = fen.get(5).and_then(|number| number.parse().ok()).unwrap_or(1);
+    for mv in &mut pgn.moves {
+        (mv.number, mv.color) = (number, color);
+        if color == PieceColor::Black {
+            number += 1;
+        }
+        color = color.swap();
+    }
+    if pgn.result == "*" {
+        pgn.result = pgn.tag("Result").unwrap_or("*").to_owned();
+    }
+    pgn
+}
+
+/// The legal move `text` stands for in `game`, written in SAN (check marks optional,
+/// `0-0` for `O-O` allowed) or in coordinate notation such as `e2e4`
+pub fn parse_san<V: Variant>(game: &Game<V>, text: &str) -> Option<Move> {
+    let text = text.trim().trim_end_matches(['+', '#', '!', '?']);
+    let text = match text {
+        "0-0" => "O-O",
+        "0-0-0" => "O-O-O",
+        text => text,
+    };
+    let legal = game.legal_moves();
+    let found = legal.iter().copied().find(|&mv| san(game, mv).trim_end_matches(['+', '#']) == text);
+    found.or_else(|| {
+        let mv = game.normalize_move(text.parse::<Move>().ok()?);
+        legal.contains(&mv).then_some(mv)
+    })
+}
+
+/// Every game in PGN text written in `format`, each replayed in the variant its
+/// Variant tag names
+pub fn convert_games(text: &str, format: PgnFormat) -> Result<String, PgnError> {
+    let mut converted = String::new();
+    for pgn in read_games(text)? {
+        let variant = pgn.tag("Variant").unwrap_or(Standard::NAME).to_owned();
+        converted.push_str(&match variant.to_ascii_lowercase().as_str() {
+            "standard" | "chess960" => pgn.convert::<Standard>(format),
+            "king of the hill" => pgn.convert::<KingOfTheHill>(format),
+            "three-check" => pgn.convert::<ThreeCheck>(format),
+            "horde" => pgn.convert::<Horde>(format),
+            "antichess" => pgn.convert::<Antichess>(format),
+            "atomic" => pgn.convert::<Atomic>(format),
+            "racing kings" => pgn.convert::<RacingKings>(format),
+            "crazyhouse" => pgn.convert::<Crazyhouse>(format),
+            _ => Err(PgnError::UnsupportedVariant(variant)),
+        }?);
+    }
+    Ok(converted)
+}
+
+/// `1-0`, `0-1`, or `1/2-1/2` for a draw
+pub fn result_token(winner: Option<PieceColor>) -> &'static str {
+    match winner {
+        Some(PieceColor::White) => "1-0",
+        Some(PieceColor::Black) => "0-1",
+        None => "1/2-1/2",
+    }
+}
+
+/// `moves` played from `game` as numbered SAN, e.g. `12... Nf6 13. e5 Nd5`
+pub fn movetext<V: Variant>(game: &Game<V>, moves: &[Move]) -> String {
+    let mut text = Vec::new();
+    let mut game = *game;
+    for (index, &mv) in moves.iter().enumerate() {
+        let number = game.move_num / 2 + 1;
+        match game.turn {
+            PieceColor::White => text.push(format!("{number}.")),
+            PieceColor::Black if index == 0 => text.push(format!("{number}...")),
+            PieceColor::Black => (),
+        }
+        text.push(san(&game, mv));
+        if game.play_unchecked(mv).is_err() {
+            break;
+        }
+    }
+    text.join(" ")
+}
+
+/// Standard Algebraic Notation for a legal `mv` in `game`, e.g. `Nbd7`, `exd6`, `e8=Q+`,
+/// `O-O-O` or `N@f3`, with `+` for check and `#` for checkmate
+pub fn san<V: Variant>(game: &Game<V>, mv: Move) -> String {
+    let mv = game.normalize_move(mv);
+    let mut notation = match (mv.drop, game.board.get(mv.start)) {
+        (Some(piece_type), _) => format!(
+            "{}@{}",
+            piece_type_letter(piece_type).to_ascii_uppercase(),
+            array_notation_to_chess_notation(mv.end),
+        ),
+        (None, Some(piece)) if is_castling(game, piece, mv) => {
+            if mv.end.1 > mv.start.1 { "O-O" } else { "O-O-O" }.to_owned()
+        },
+        (None, Some(piece)) => {
+            let capture = if game.is_capture(mv) { "x" } else { "" };
+            let end = array_notation_to_chess_notation(mv.end);
+            let start = array_notation_to_chess_notation(mv.start);
+            match piece.piece_type {
+                PieceType::Pawn(_) if capture.is_empty() => end,
+                PieceType::Pawn(_) => format!("{}x{end}", &start[..1]),
+                piece_type => {
+                    let letter = piece_type_letter(piece_type).to_ascii_uppercase();
+                    format!("{letter}{}{capture}{end}", disambiguation(game, piece, mv))
+                },
+            }
+        },
+        (None, None) => mv.to_string(),
+    };
+    if let Some(piece_type) = mv.promotion.filter(|_| game.is_promotion(mv)) {
+        notation.push('=');
+        notation.push(piece_type_letter(piece_type).to_ascii_uppercase());
+    }
+
+    let mut after = *game;
+    if after.play_unchecked(mv).is_ok() {
+        if after.status() == GameStatus::Checkmate(game.turn) {
+            notation.push('#');
+        } else if after.is_king_in_check(after.turn) {
+            notation.push('+');
+        }
+    }
+    notation
+}
+
+fn is_castling<V: Variant>(game: &Game<V>, piece: Piece, mv: Move) -> bool {
+    piece.piece_type == PieceType::King(false) &&
+        game.board.get(mv.end).is_some_and(|rook| rook.color == piece.color && rook.piece_type == PieceType::Rook(false))
+}
+
+/// The start file, rank or square that tells `mv` apart from moves of other pieces of the
+/// same kind to the same square
+fn disambiguation<V: Variant>(game: &Game<V>, piece: Piece, mv: Move) -> String {
+    let rivals: Vec<Coordinates> = game
+        .legal_moves()
+        .into_iter()
+        .filter(|other| other.end == mv.end && other.start != mv.start && other.drop.is_none())
+        .filter(|other| {
+            game.board.get(other.start).is_some_and(|rival| {
+                piece_type_letter(rival.piece_type) == piece_type_letter(piece.piece_type) && !is_castling(game, rival, *other)
+            })
+        })
+        .map(|other| other.start)
+        .collect();
+    let start = array_notation_to_chess_notation(mv.start);
+    if rivals.is_empty() {
+        String::new()
+    } else if rivals.iter().all(|rival| rival.1 != mv.start.1) {
+        start[..1].to_owned()
+    } else if rivals.iter().all(|rival| rival.0 != mv.start.0) {
+        start[1..].to_owned()
+    } else {
+        start
+    }
+}
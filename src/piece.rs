@@ -4,6 +4,7 @@ use std::fmt::{Debug, Display};
 // Custom Piece Type Data
 
 #[derive(Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PieceColor {
     Black,
     White,
@@ -19,12 +20,13 @@ impl PieceColor {
 }
 
 #[derive(Debug, Default, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PawnData {
     pub has_moved: bool,
-    pub can_en_pessant: bool,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PieceType {
     Pawn(PawnData),
     Rook(bool),
@@ -38,6 +40,7 @@ pub enum PieceType {
 pub struct Coordinates(pub u8, pub u8);
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Piece {
     pub piece_type: PieceType,
     pub color: PieceColor,
@@ -81,6 +84,23 @@ impl Display for Coordinates {
     }
 }
 
+/// Squares are written in algebraic notation, e.g. `"e4"`
+#[cfg(feature = "serde")]
+impl serde::Serialize for Coordinates {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&crate::game::array_notation_to_chess_notation(*self))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Coordinates {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let square = <std::borrow::Cow<str>>::deserialize(deserializer)?;
+        crate::game::chess_notation_to_array_notation(&square)
+            .map_err(|_| serde::de::Error::invalid_value(serde::de::Unexpected::Str(&square), &"a square such as e4"))
+    }
+}
+
 impl Display for PieceColor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -103,5 +123,22 @@ pub fn build_piece(piece: PieceType, color: PieceColor, coordinates: Coordinates
         coordinates,
     };
     let ret: Option<Piece> = Some(to_build);
-    return ret;
+    ret
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn squares_serialize_in_algebraic_notation() {
+        assert_eq!(serde_json::to_string(&Coordinates(4, 4)).unwrap(), r#""e4""#);
+        assert_eq!(serde_json::from_str::<Coordinates>(r#""a8""#).unwrap(), Coordinates(0, 0));
+        assert!(serde_json::from_str::<Coordinates>(r#""i9""#).is_err());
+
+        let piece = Piece { piece_type: PieceType::Rook(true), color: PieceColor::White, coordinates: Coordinates(7, 0) };
+        let json = serde_json::to_string(&piece).unwrap();
+        assert_eq!(json, r#"{"piece_type":{"Rook":true},"color":"White","coordinates":"a1"}"#);
+        assert_eq!(serde_json::from_str::<Piece>(&json).unwrap(), piece);
+    }
 }
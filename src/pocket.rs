@@ -0,0 +1,217 @@
+// Imports
+use std::fmt::{Debug, Display};
+
+use crate::errors::*;
+use crate::game::*;
+use crate::moves::*;
+use crate::piece::*;
+use crate::variant::*;
+
+/// Pocket piece kinds in display order, a king can never be pocketed
+const POCKET_PIECES: [PieceType; 5] = [
+    PieceType::Queen,
+    PieceType::Rook(true),
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Pawn(PawnData { has_moved: true }),
+];
+
+// Custom Pocket Type Data
+
+/// Captured pieces a Crazyhouse or Bughouse player holds and may drop back onto the board.
+/// `counts` is indexed queen, rook, bishop, knight, pawn.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pocket {
+    pub counts: [u8; 5],
+}
+
+// Custom Pocket Type Traits
+
+impl Display for Pocket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for piece_type in self.pieces() {
+            write!(f, "{}", piece_type_letter(piece_type))?;
+        }
+        Ok(())
+    }
+}
+
+// Impl Pocket
+
+impl Pocket {
+    pub fn add(&mut self, piece_type: PieceType) {
+        if let Some(index) = pocket_index(piece_type) {
+            self.counts[index] += 1;
+        }
+    }
+
+    /// Takes one piece of the given kind out, returning false if there was none
+    pub fn remove(&mut self, piece_type: PieceType) -> bool {
+        match pocket_index(piece_type) {
+            Some(index) if self.counts[index] > 0 => {
+                self.counts[index] -= 1;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    pub fn count(&self, piece_type: PieceType) -> u8 {
+        pocket_index(piece_type).map_or(0, |index| self.counts[index])
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.iter().all(|&count| count == 0)
+    }
+
+    /// Every pocketed piece, repeated by count
+    pub fn pieces(&self) -> impl Iterator<Item = PieceType> + '_ {
+        POCKET_PIECES
+            .iter()
+            .zip(self.counts)
+            .flat_map(|(&piece_type, count)| std::iter::repeat_n(piece_type, count as usize))
+    }
+
+    /// Each kind of piece in the pocket once
+    pub fn kinds(&self) -> impl Iterator<Item = PieceType> + '_ {
+        POCKET_PIECES
+            .iter()
+            .zip(self.counts)
+            .filter(|&(_, count)| count > 0)
+            .map(|(&piece_type, _)| piece_type)
+    }
+}
+
+// Impl drops for Game
+
+impl<V: Variant> Game<V> {
+    pub fn pocket(&self, color: PieceColor) -> &Pocket {
+        &self.pockets[color_index(color)]
+    }
+
+    pub fn pocket_mut(&mut self, color: PieceColor) -> &mut Pocket {
+        &mut self.pockets[color_index(color)]
+    }
+
+    /// The piece type a capture puts in a pocket: promoted pieces go back to being pawns
+    pub fn pocket_piece(&self, piece: Piece) -> PieceType {
+        if self.promoted.contains(piece.coordinates) {
+            PieceType::Pawn(PawnData { has_moved: true })
+        } else {
+            piece.piece_type
+        }
+    }
+
+    /// Places a piece from the side to move's pocket onto an empty square.
+    /// Pawns may not be dropped on the first or last rank.
+    pub fn drop_piece(&mut self, piece_type: PieceType, end: Coordinates) -> Result<GameResult, GameError> {
+        if self.board.get(end).is_some() {
+            return Err(GameError::InvalidDrop);
+        }
+
+        let color = self.turn;
+        let piece_type = match piece_type {
+            PieceType::Pawn(_) if end.0 == 0 || end.0 == 7 => return Err(GameError::InvalidDrop),
+            PieceType::Pawn(_) => {
+                let start_row = match color {
+                    PieceColor::White => 6,
+                    PieceColor::Black => 1,
+                };
+                PieceType::Pawn(PawnData { has_moved: end.0 != start_row })
+            },
+            PieceType::Rook(_) => PieceType::Rook(true),
+            PieceType::King(_) => return Err(GameError::InvalidDrop),
+            other => other,
+        };
+
+        if !self.pocket_mut(color).remove(piece_type) {
+            return Err(GameError::InvalidDrop);
+        }
+
+        *self.at(end) = build_piece(piece_type, color, end);
+        self.en_passant = None;
+        self.halfmove_clock += 1;
+        Ok(GameResult::Drop)
+    }
+
+    /// Every drop the side to move could make, before checking king safety
+    pub fn drop_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        for piece_type in self.pocket(self.turn).kinds() {
+            for row in 0..8u8 {
+                if matches!(piece_type, PieceType::Pawn(_)) && (row == 0 || row == 7) {
+                    continue;
+                }
+                for col in 0..8u8 {
+                    let end = Coordinates(row, col);
+                    if self.board.get(end).is_none() {
+                        moves.push(Move::drop(piece_type, end));
+                    }
+                }
+            }
+        }
+        moves
+    }
+}
+
+// Functions
+
+pub fn pocket_index(piece_type: PieceType) -> Option<usize> {
+    match piece_type {
+        PieceType::Queen => Some(0),
+        PieceType::Rook(_) => Some(1),
+        PieceType::Bishop => Some(2),
+        PieceType::Knight => Some(3),
+        PieceType::Pawn(_) => Some(4),
+        PieceType::King(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pawns_are_never_dropped_on_the_first_or_last_rank() {
+        let mut game = Game::<Crazyhouse>::from_fen("4k3/8/8/8/8/8/8/4K3[P] w - - 0 1").unwrap();
+        let drops = game.drop_moves();
+        assert_eq!(drops.len(), 48);
+        assert!(drops.iter().all(|mv| (1..=6).contains(&mv.end.0)));
+
+        let pawn = PieceType::Pawn(PawnData::default());
+        assert!(matches!(game.drop_piece(pawn, Coordinates(0, 3)), Err(GameError::InvalidDrop)));
+        assert!(matches!(game.drop_piece(pawn, Coordinates(7, 3)), Err(GameError::InvalidDrop)));
+        assert_eq!(game.pocket(PieceColor::White).count(pawn), 1);
+        assert!(matches!(game.drop_piece(pawn, Coordinates(6, 3)), Ok(GameResult::Drop)));
+        assert!(game.pocket(PieceColor::White).is_empty());
+    }
+
+    #[test]
+    fn captured_promoted_pieces_go_back_to_being_pawns() {
+        let mut game = Game::<Crazyhouse>::from_fen("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        for mv in ["b7b8q", "a8b8"] {
+            game.play(mv.parse().unwrap()).unwrap();
+        }
+        let pocket = game.pocket(PieceColor::Black);
+        assert_eq!(pocket.pieces().collect::<Vec<_>>(), vec![PieceType::Pawn(PawnData { has_moved: true })]);
+
+        // The same goes for pieces marked as promoted in a FEN
+        let mut game = Game::<Crazyhouse>::from_fen("4k3/8/8/8/8/8/3q~4/3RK3 w - - 0 1").unwrap();
+        game.play("d1d2".parse().unwrap()).unwrap();
+        assert_eq!(game.pocket(PieceColor::White).to_string(), "p");
+    }
+
+    #[test]
+    fn drops_are_read_with_an_at_sign() {
+        let knight_f3 = Move::drop(PieceType::Knight, Coordinates(5, 5));
+        assert_eq!("N@f3".parse::<Move>().ok(), Some(knight_f3));
+        assert_eq!("@e4".parse::<Move>().ok(), Some(Move::drop(PieceType::Pawn(PawnData { has_moved: true }), Coordinates(4, 4))));
+        assert!("K@f3".parse::<Move>().is_err());
+        assert!("N@f9".parse::<Move>().is_err());
+
+        let game = Game::<Crazyhouse>::from_fen("4k3/8/8/8/8/8/8/4K3[N] w - - 0 1").unwrap();
+        assert!(game.legal_moves().contains(&knight_f3));
+        assert!(!game.legal_moves().contains(&Move::drop(PieceType::Bishop, Coordinates(5, 5))));
+    }
+}
@@ -0,0 +1,292 @@
+// Imports
+use std::env;
+use std::io::{self, IsTerminal};
+use std::str::FromStr;
+
+use crate::board::*;
+use crate::errors::*;
+use crate::game::*;
+use crate::moves::*;
+use crate::piece::*;
+use crate::variant::*;
+
+/// Background colors (256 color palette) of light and dark squares, and of light and dark
+/// squares the last move touched
+const LIGHT_SQUARE: u8 = 187;
+const DARK_SQUARE: u8 = 137;
+const LIGHT_LAST_MOVE: u8 = 186;
+const DARK_LAST_MOVE: u8 = 143;
+/// Background colors of a king in check, a selected piece and the squares it can move to,
+/// and of a piece left hanging
+const CHECK: u8 = 167;
+const SELECTED: u8 = 110;
+const TARGET: u8 = 108;
+const HANGING: u8 = 173;
+/// Foreground colors of White's and Black's pieces
+const WHITE_PIECE: u8 = 231;
+const BLACK_PIECE: u8 = 16;
+
+// Custom PieceStyle Type Data
+
+/// How pieces are drawn: letters (`P`, `n`) or chess glyphs (♙, ♞)
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum PieceStyle {
+    #[default]
+    Ascii,
+    Unicode,
+}
+
+// Custom Orientation Type Data
+
+/// Which side's pieces are drawn at the bottom of the board
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum Orientation {
+    #[default]
+    White,
+    Black,
+    /// Whoever is to move, for two players sharing a screen
+    SideToMove,
+}
+
+// Custom RenderOptions Type Data
+
+/// How boards are drawn. The default is plain ASCII without colors from White's side, as
+/// `Display` prints.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct RenderOptions {
+    pub pieces: PieceStyle,
+    /// Colors squares and highlights with ANSI escapes
+    pub colors: bool,
+    pub orientation: Orientation,
+    /// Labels the ranks and files on the right and top as well as the left and bottom
+    pub labels_both_sides: bool,
+}
+
+// Custom Highlight Type Data
+
+/// Why a square stands out
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Highlight {
+    LastMove,
+    Check,
+    Selected,
+    /// A square the selected piece can move to
+    Target,
+    Cursor,
+    /// A piece the opponent wins material by taking, see `Game::hanging_pieces`
+    Hanging,
+}
+
+// Custom PieceStyle Type Traits
+
+impl FromStr for PieceStyle {
+    type Err = ConversionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ascii" => Ok(PieceStyle::Ascii),
+            "unicode" => Ok(PieceStyle::Unicode),
+            _ => Err(ConversionError),
+        }
+    }
+}
+
+impl FromStr for Orientation {
+    type Err = ConversionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "white" => Ok(Orientation::White),
+            "black" => Ok(Orientation::Black),
+            "auto" => Ok(Orientation::SideToMove),
+            _ => Err(ConversionError),
+        }
+    }
+}
+
+// Impl Orientation
+
+impl Orientation {
+    /// The view for a game where the engine plays `engine_color`: the player's own pieces
+    /// at the bottom, or the side to move's when two people play
+    pub fn for_players(engine_color: Option<PieceColor>) -> Orientation {
+        match engine_color {
+            Some(PieceColor::White) => Orientation::Black,
+            Some(PieceColor::Black) => Orientation::White,
+            None => Orientation::SideToMove,
+        }
+    }
+}
+
+// Impl RenderOptions
+
+impl RenderOptions {
+    /// Options for printing to stdout: colors are dropped when it isn't a terminal or
+    /// `NO_COLOR` is set
+    pub fn for_stdout(pieces: PieceStyle, colors: bool) -> RenderOptions {
+        RenderOptions {
+            pieces,
+            colors: colors && io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()),
+            ..RenderOptions::default()
+        }
+    }
+
+    /// Whether Black is drawn at the bottom while `turn` is to move
+    pub fn is_flipped(&self, turn: PieceColor) -> bool {
+        match self.orientation {
+            Orientation::White => false,
+            Orientation::Black => true,
+            Orientation::SideToMove => turn == PieceColor::Black,
+        }
+    }
+
+    /// These options with the orientation fixed for `turn` to move
+    pub fn facing(&self, turn: PieceColor) -> RenderOptions {
+        let orientation = if self.is_flipped(turn) { Orientation::Black } else { Orientation::White };
+        RenderOptions { orientation, ..*self }
+    }
+
+    /// Lines above the board's top border, one when the files are labelled there
+    pub fn top_lines(&self) -> usize {
+        self.labels_both_sides as usize
+    }
+}
+
+// Impl Piece
+
+impl Piece {
+    /// The piece as a single character in `style`
+    pub fn symbol(&self, style: PieceStyle) -> char {
+        if style == PieceStyle::Ascii {
+            return self.to_string().chars().next().unwrap_or('?');
+        }
+        let glyphs = match self.color {
+            PieceColor::White => ['♔', '♕', '♖', '♗', '♘', '♙'],
+            PieceColor::Black => ['♚', '♛', '♜', '♝', '♞', '♟'],
+        };
+        match self.piece_type {
+            PieceType::King(_) => glyphs[0],
+            PieceType::Queen => glyphs[1],
+            PieceType::Rook(_) => glyphs[2],
+            PieceType::Bishop => glyphs[3],
+            PieceType::Knight => glyphs[4],
+            PieceType::Pawn(_) => glyphs[5],
+        }
+    }
+}
+
+// Impl Board
+
+impl Board {
+    /// The board in a grid of box drawing characters, with `highlights` marking squares.
+    /// Without colors only the cursor and selected piece (in brackets) and empty target
+    /// squares (a dot) can be told apart. Drawn from White's side for `SideToMove`, which
+    /// `Game::render` settles.
+    pub fn render(&self, options: &RenderOptions, highlights: &[(Coordinates, Highlight)]) -> String {
+        let flipped = options.orientation == Orientation::Black;
+        let order = |index: u8| if flipped { 7 - index } else { index };
+        let files: String = (0..8).map(|col| format!("   {}", (b'A' + order(col)) as char)).collect();
+
+        let mut board_str = String::new();
+        if options.labels_both_sides {
+            board_str.push_str(&format!("  {files}\n"));
+        }
+        board_str.push_str("   ┌───┬───┬───┬───┬───┬───┬───┬───┐\n");
+        for line in 0..8u8 {
+            let row = order(line);
+            board_str.push_str(&format!(" {} │", 8 - row));
+            for col in (0..8u8).map(order) {
+                let square = Coordinates(row, col);
+                let marks: Vec<Highlight> = highlights.iter().filter(|&&(at, _)| at == square).map(|&(_, highlight)| highlight).collect();
+                board_str.push_str(&self.render_square(square, options, &marks));
+                board_str.push('│');
+            }
+            if options.labels_both_sides {
+                board_str.push_str(&format!(" {}", 8 - row));
+            }
+            board_str.push('\n');
+            if line != 7 {
+                board_str.push_str("   ├───┼───┼───┼───┼───┼───┼───┼───┤\n");
+            }
+        }
+        board_str.push_str("   └───┴───┴───┴───┴───┴───┴───┴───┘\n");
+        board_str.push_str(&format!("  {files}  \n"));
+        board_str
+    }
+
+    /// One three character cell of the grid
+    fn render_square(&self, square: Coordinates, options: &RenderOptions, marks: &[Highlight]) -> String {
+        let piece = self.get(square);
+        let symbol = match piece {
+            Some(piece) => piece.symbol(options.pieces),
+            None if marks.contains(&Highlight::Target) => '·',
+            None => ' ',
+        };
+        let bracketed = marks.contains(&Highlight::Cursor) || marks.contains(&Highlight::Selected);
+        if !options.colors {
+            return match bracketed {
+                true => format!("[{symbol}]"),
+                false => format!(" {symbol} "),
+            };
+        }
+
+        let light = (square.0 + square.1).is_multiple_of(2);
+        let background = if marks.contains(&Highlight::Check) {
+            CHECK
+        } else if marks.contains(&Highlight::Selected) {
+            SELECTED
+        } else if marks.contains(&Highlight::Target) {
+            TARGET
+        } else if marks.contains(&Highlight::Hanging) {
+            HANGING
+        } else if marks.contains(&Highlight::LastMove) {
+            if light { LIGHT_LAST_MOVE } else { DARK_LAST_MOVE }
+        } else if light {
+            LIGHT_SQUARE
+        } else {
+            DARK_SQUARE
+        };
+        let foreground = match piece.map(|piece| piece.color) {
+            Some(PieceColor::White) => WHITE_PIECE,
+            _ => BLACK_PIECE,
+        };
+        let reverse = if marks.contains(&Highlight::Cursor) { "\x1b[7m" } else { "" };
+        format!("\x1b[1;38;5;{foreground};48;5;{background}m{reverse} {symbol} \x1b[0m")
+    }
+}
+
+// Impl Game
+
+impl<V: Variant> Game<V> {
+    /// The board with the last move and a king in check highlighted, and the pockets
+    /// beside it in variants that have them
+    pub fn render(&self, options: &RenderOptions, last_move: Option<Move>) -> String {
+        let mut highlights = Vec::new();
+        if let Some(mv) = last_move {
+            highlights.extend([(mv.start, Highlight::LastMove), (mv.end, Highlight::LastMove)]);
+        }
+        if let Some(king) = self.king(self.turn).filter(|_| self.is_king_in_check(self.turn)) {
+            highlights.push((king, Highlight::Check));
+        }
+        let options = options.facing(self.turn);
+        let board = self.board.render(&options, &highlights);
+        if !V::POCKETS {
+            return board;
+        }
+
+        // Pockets sit beside the grid, each next to its own side's back rank
+        let (top, bottom) = match options.is_flipped(self.turn) {
+            true => (PieceColor::White, PieceColor::Black),
+            false => (PieceColor::Black, PieceColor::White),
+        };
+        let mut rendered = String::new();
+        for (line_num, line) in board.lines().enumerate() {
+            match line_num.checked_sub(options.top_lines()) {
+                Some(1) => rendered.push_str(&format!("{line}   {top}: {}\n", self.pocket(top))),
+                Some(15) => rendered.push_str(&format!("{line}   {bottom}: {}\n", self.pocket(bottom))),
+                _ => rendered.push_str(&format!("{line}\n")),
+            }
+        }
+        rendered
+    }
+}
@@ -0,0 +1,262 @@
+// Imports
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::clock::*;
+use crate::errors::*;
+use crate::game::*;
+use crate::moves::*;
+use crate::pgn::*;
+use crate::piece::*;
+use crate::render::*;
+use crate::session::*;
+use crate::variant::*;
+
+/// Version in the header line of saved games. A reader takes any version up to its own,
+/// so this goes up only when older readers would get a newer save wrong.
+pub const SAVE_VERSION: u32 = 1;
+/// Start of a saved game's first line, followed by its version
+const SAVE_HEADER: &str = "rust_chess save";
+/// Where games are saved after every move unless `--autosave` says otherwise
+pub const AUTOSAVE_FILE: &str = "rust_chess.save";
+
+// Impl Session for saved games
+
+impl<V: Variant> Session<V> {
+    /// The game as `key: value` lines under a versioned header, for example
+    ///
+    /// ```text
+    /// rust_chess save 1
+    /// variant: Standard
+    /// white: Player
+    /// black: rust_chess
+    /// engine: black
+    /// start: rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1
+    /// moves: 1. e4 e5 2. Nf3
+    /// time: 5+3
+    /// white clock: 287.500
+    /// black clock: 296.125
+    /// result: *
+    /// ```
+    ///
+    /// Clock times are seconds left. `draw offer` and, once the game is over, `ending`
+    /// are only written when they apply.
+    pub fn to_save(&self) -> String {
+        let start = self.start();
+        let moves: Vec<Move> = self.history.iter().map(|&(_, mv, _)| mv).collect();
+        let variant = if start.chess960 && V::NAME == Standard::NAME { "Chess960" } else { V::NAME };
+        let lowercase = |color: PieceColor| color.to_string().to_ascii_lowercase();
+
+        let mut fields = vec![
+            ("variant", variant.to_owned()),
+            ("white", self.pgn.tag("White").unwrap_or("?").to_owned()),
+            ("black", self.pgn.tag("Black").unwrap_or("?").to_owned()),
+            ("engine", self.engine_color.map_or("none".to_owned(), lowercase)),
+            ("start", start.to_fen()),
+            ("moves", movetext(&start, &moves)),
+        ];
+        if let Some(clock) = &self.clock {
+            fields.push(("time", clock.control.to_string()));
+            fields.push(("white clock", format!("{:.3}", clock.remaining(PieceColor::White).as_secs_f64())));
+            fields.push(("black clock", format!("{:.3}", clock.remaining(PieceColor::Black).as_secs_f64())));
+        }
+        if let Some(color) = self.draw_offer {
+            fields.push(("draw offer", lowercase(color)));
+        }
+        fields.push(("result", self.pgn.result.clone()));
+        if let Some(ending) = self.ending.as_ref().filter(|_| self.pgn.result != "*") {
+            fields.push(("ending", ending.clone()));
+        }
+
+        let mut text = format!("{SAVE_HEADER} {SAVE_VERSION}\n");
+        for (key, value) in fields {
+            text.push_str(&format!("{key}: {value}\n"));
+        }
+        text
+    }
+
+    /// Reads a game written by `to_save`, to be drawn with `options`. Unknown keys are
+    /// skipped, so saves from newer versions load as long as their version allows it.
+    pub fn from_save(text: &str, options: RenderOptions) -> Result<Session<V>, SaveError> {
+        let fields = read_save(text)?;
+        let field = |key: &str| fields.iter().find(|(name, _)| name == key).map(|(_, value)| value.as_str());
+
+        let variant = field("variant").ok_or(SaveError::MissingField("variant"))?;
+        let chess960 = V::NAME == Standard::NAME && variant.eq_ignore_ascii_case("chess960");
+        if !variant.eq_ignore_ascii_case(V::NAME) && !chess960 {
+            return Err(SaveError::WrongVariant(variant.to_owned()));
+        }
+        let mut start = Game::<V>::from_fen(field("start").ok_or(SaveError::MissingField("start"))?).map_err(|_| SaveError::InvalidFen)?;
+        start.chess960 |= chess960;
+        let color = |key: &str| match field(key).map(str::to_ascii_lowercase).as_deref() {
+            Some("white") => Some(PieceColor::White),
+            Some("black") => Some(PieceColor::Black),
+            _ => None,
+        };
+
+        let mut session = Session::new(start, color("engine"), None, options);
+        for (tag, key) in [("White", "white"), ("Black", "black")] {
+            session.pgn.set_tag(tag, field(key).unwrap_or("?"));
+        }
+        let moves = field("moves").unwrap_or("").split_whitespace();
+        // Move numbers such as `12.` or `12...` only help people reading the file
+        for text in moves.filter(|token| token.trim_end_matches('.').parse::<u16>().is_err()) {
+            let mv = parse_san(&session.game, text).ok_or_else(|| SaveError::IllegalMove(text.to_owned()))?;
+            session.play(mv).map_err(|_| SaveError::IllegalMove(text.to_owned()))?;
+        }
+
+        if let Some(control) = field("time") {
+            let control: TimeControl = control.parse().map_err(|_| SaveError::InvalidClock)?;
+            let seconds = |key: &str| {
+                field(key)
+                    .and_then(|seconds| seconds.parse::<f64>().ok())
+                    .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+                    .map(Duration::from_secs_f64)
+                    .ok_or(SaveError::InvalidClock)
+            };
+            let moves_played = |color: PieceColor| session.history.iter().filter(|(before, ..)| before.turn == color).count() as u32;
+            let remaining = [seconds("white clock")?, seconds("black clock")?];
+            session.clock = Some(Clock::resume(control, remaining, [moves_played(PieceColor::White), moves_played(PieceColor::Black)]));
+            session.pgn.set_tag("TimeControl", &control.to_string());
+        }
+        session.draw_offer = color("draw offer");
+
+        let winner = match field("result").unwrap_or("*") {
+            "1-0" => Some(Some(PieceColor::White)),
+            "0-1" => Some(Some(PieceColor::Black)),
+            "1/2-1/2" => Some(None),
+            _ => None,
+        };
+        if let Some(winner) = winner.filter(|_| !session.is_over()) {
+            session.finish(winner, field("ending").unwrap_or("The game is over").to_owned());
+        }
+        Ok(session)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SaveError> {
+        write_atomically(path, &self.to_save()).map_err(|_| SaveError::Unwritable)
+    }
+
+    /// Saves to the autosave file, if there is one. A failed autosave is left for the
+    /// next move to retry rather than stopping the game.
+    pub fn autosave(&self) {
+        if let Some(path) = &self.autosave {
+            let _ = self.save(path);
+        }
+    }
+}
+
+// Functions
+
+/// Writes `text` to a temporary file beside `path` and renames it over `path`, so a crash
+/// part way through leaves the old file rather than half of the new one
+pub fn write_atomically<P: AsRef<Path>>(path: P, text: &str) -> io::Result<()> {
+    let path = path.as_ref();
+    let name = path.file_name().ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+    let mut temp_name = name.to_owned();
+    temp_name.push(".tmp");
+    let temp = path.with_file_name(temp_name);
+    let written = fs::write(&temp, text).and_then(|_| fs::rename(&temp, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written
+}
+
+/// Whether `text` looks like a saved game rather than PGN or a FEN
+pub fn is_save(text: &str) -> bool {
+    text.trim_start().starts_with(SAVE_HEADER)
+}
+
+/// The value of `key` in a saved game, `None` when the save has no such field
+pub fn saved_field(text: &str, key: &str) -> Result<Option<String>, SaveError> {
+    Ok(read_save(text)?.into_iter().find(|(name, _)| name == key).map(|(_, value)| value))
+}
+
+/// The `key: value` fields of a saved game after checking its header. Blank lines and
+/// lines starting with `#` are skipped.
+fn read_save(text: &str) -> Result<Vec<(String, String)>, SaveError> {
+    let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+    let (_, header) = lines.next().ok_or(SaveError::NotASave)?;
+    let version = header
+        .trim()
+        .strip_prefix(SAVE_HEADER)
+        .and_then(|version| version.trim().parse::<u32>().ok())
+        .ok_or(SaveError::NotASave)?;
+    if version > SAVE_VERSION {
+        return Err(SaveError::UnsupportedVersion(version));
+    }
+
+    lines
+        .map(|(number, line)| match line.split_once(':') {
+            Some((key, value)) => Ok((key.trim().to_ascii_lowercase(), value.trim().to_owned())),
+            None => Err(SaveError::InvalidLine(number + 1)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_replace_the_file_whole() {
+        let directory = std::env::temp_dir().join(format!("rust_chess_save_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("game.save");
+        let mut session = Session::<Standard>::new(Game::default(), None, None, RenderOptions::default());
+        session.save(&path).unwrap();
+        session.play(parse_san(&session.game, "e4").unwrap()).unwrap();
+        session.save(&path).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), session.to_save());
+        let files: Vec<_> = fs::read_dir(&directory).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        assert_eq!(files, ["game.save"]);
+        assert_eq!(session.save(directory.join("missing").join("game.save")), Err(SaveError::Unwritable));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn saves_load_back_as_they_were() {
+        let control: TimeControl = "5+3".parse().unwrap();
+        let clock = Clock::resume(control, [Duration::from_millis(287_500), Duration::from_millis(296_125)], [0, 0]);
+        let mut session = Session::new(Game::<Standard>::chess960(100), Some(PieceColor::Black), Some(clock), RenderOptions::default());
+        for san in ["e4", "e5", "d4", "exd4"] {
+            session.play(parse_san(&session.game, san).unwrap()).unwrap();
+        }
+        session.draw_offer = Some(PieceColor::Black);
+
+        let text = session.to_save();
+        assert!(text.contains("variant: Chess960\n") && text.contains("draw offer: black\n"));
+        let loaded = Session::<Standard>::from_save(&text, RenderOptions::default()).unwrap();
+        assert_eq!(loaded.to_save(), text);
+        assert!(loaded.start().chess960);
+        assert_eq!(loaded.start().to_fen(), Game::<Standard>::chess960(100).to_fen());
+        assert_eq!(loaded.game.to_fen(), session.game.to_fen());
+        assert_eq!(loaded.history.len(), 4);
+        let clock = loaded.clock.unwrap();
+        assert_eq!(clock.remaining(PieceColor::White), Duration::from_millis(287_500));
+        assert_eq!(clock.remaining(PieceColor::Black), Duration::from_millis(296_125));
+        assert_eq!(loaded.draw_offer, Some(PieceColor::Black));
+        assert_eq!(loaded.pgn.result, "*");
+
+        session.finish(Some(PieceColor::White), "Black resigned".to_owned());
+        let loaded = Session::<Standard>::from_save(&session.to_save(), RenderOptions::default()).unwrap();
+        assert!(loaded.is_over());
+        assert_eq!(loaded.pgn.result, "1-0");
+        assert_eq!(loaded.ending.as_deref(), Some("Black resigned"));
+    }
+
+    #[test]
+    fn saves_from_newer_versions_are_refused() {
+        let session = Session::<Standard>::new(Game::default(), None, None, RenderOptions::default());
+        let newer = session.to_save().replacen(&SAVE_VERSION.to_string(), &(SAVE_VERSION + 1).to_string(), 1);
+        assert_eq!(
+            Session::<Standard>::from_save(&newer, RenderOptions::default()).unwrap_err(),
+            SaveError::UnsupportedVersion(SAVE_VERSION + 1)
+        );
+        assert_eq!(Session::<Standard>::from_save("1. e4 e5", RenderOptions::default()).unwrap_err(), SaveError::NotASave);
+    }
+}
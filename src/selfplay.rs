@@ -0,0 +1,561 @@
+// Imports
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use crate::book::*;
+use crate::clock::*;
+use crate::engine::*;
+use crate::errors::*;
+use crate::eval::*;
+use crate::game::*;
+use crate::moves::*;
+use crate::nnue::*;
+use crate::pgn::*;
+use crate::piece::*;
+use crate::tablebase::*;
+use crate::tt::*;
+
+/// Openings played when no openings file is given, as moves from the starting position
+const DEFAULT_OPENINGS: [&str; 12] = [
+    "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6",
+    "e2e4 e7e5 g1f3 b8c6 f1c4 f8c5",
+    "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6",
+    "e2e4 e7e6 d2d4 d7d5 b1c3 f8b4",
+    "e2e4 c7c6 d2d4 d7d5 e4e5 c8f5",
+    "e2e4 d7d6 d2d4 g8f6 b1c3 g7g6",
+    "d2d4 d7d5 c2c4 e7e6 b1c3 g8f6",
+    "d2d4 d7d5 c2c4 c7c6 g1f3 g8f6",
+    "d2d4 g8f6 c2c4 g7g6 b1c3 f8g7 e2e4 d7d6",
+    "d2d4 g8f6 c2c4 e7e6 g1f3 b7b6",
+    "c2c4 e7e5 b1c3 g8f6 g2g3 d7d5",
+    "g1f3 d7d5 g2g3 g8f6 f1g2 c7c6",
+];
+/// Plies after which a game still going is called a draw
+const MAX_PLIES: usize = 400;
+/// A game is adjudicated won once both engines have agreed for `RESIGN_PLIES` plies in a
+/// row that one side is at least this far ahead
+const RESIGN_SCORE: i32 = 1000;
+const RESIGN_PLIES: usize = 6;
+/// A game is adjudicated drawn after `DRAW_AFTER_PLIES` once both engines have scored it
+/// within this much of equal for `DRAW_PLIES` plies in a row
+const DRAW_SCORE: i32 = 10;
+const DRAW_PLIES: usize = 16;
+const DRAW_AFTER_PLIES: usize = 80;
+
+// Custom EngineConfig Type Data
+
+/// One side of a match, written as comma separated settings such as
+/// `name=tuned,params=tuned.txt,depth=6`. Settings left out keep the engine's defaults.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EngineConfig {
+    pub name: String,
+    pub params: Option<PathBuf>,
+    pub nnue: Option<PathBuf>,
+    /// Tablebase directories, separated as for `Tablebase::open`
+    pub syzygy: Option<String>,
+    /// Opening book whose moves are played while the game is in it
+    pub book: Option<PathBuf>,
+    /// Fixed search depth, or the most a timed search may reach
+    pub depth: Option<u8>,
+    pub hash: usize,
+    pub threads: usize,
+}
+
+// Custom MatchConfig Type Data
+
+/// Everything `run_match` needs. Games are played in pairs from each opening, the engines
+/// swapping colors, and each engine gets a fresh hash table per game.
+#[derive(Debug, Clone)]
+pub struct MatchConfig {
+    pub engines: [EngineConfig; 2],
+    pub openings: Vec<Game>,
+    pub games: usize,
+    /// Games played at the same time
+    pub concurrency: usize,
+    /// Clock for both sides, searches go to a fixed depth without one
+    pub time: Option<TimeControl>,
+    pub sprt: Option<Sprt>,
+    pub pgn: Option<PathBuf>,
+}
+
+// Custom MatchScore Type Data
+
+/// Results from the first engine's side
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct MatchScore {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+// Custom Sprt Type Data
+
+/// A sequential probability ratio test of the hypothesis that the first engine is `elo1`
+/// stronger against the hypothesis that it is `elo0` stronger, with false positive and
+/// false negative rates `alpha` and `beta`. Written `elo0,elo1[,alpha,beta]`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+// Custom SprtResult Type Data
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SprtResult {
+    /// The first engine is no more than `elo0` stronger
+    AcceptH0,
+    /// The first engine is at least `elo1` stronger
+    AcceptH1,
+}
+
+// Custom GameRecord Type Data
+
+/// A finished game and which engine had White
+struct GameRecord {
+    index: usize,
+    pgn: Pgn,
+    winner: Option<PieceColor>,
+    reason: String,
+    first_white: bool,
+}
+
+// Custom EngineConfig Type Traits
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        EngineConfig {
+            name: "rust_chess".to_owned(),
+            params: None,
+            nnue: None,
+            syzygy: None,
+            book: None,
+            depth: None,
+            hash: DEFAULT_HASH_MB,
+            threads: 1,
+        }
+    }
+}
+
+impl FromStr for EngineConfig {
+    type Err = MatchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EngineConfig::default().with_settings(s)
+    }
+}
+
+// Custom MatchScore Type Traits
+
+impl Display for MatchScore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "+{} ={} -{}", self.wins, self.draws, self.losses)
+    }
+}
+
+// Custom Sprt Type Traits
+
+impl FromStr for Sprt {
+    type Err = MatchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let numbers = s
+            .split(',')
+            .map(|number| number.trim().parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| MatchError::InvalidSprt)?;
+        let sprt = match *numbers.as_slice() {
+            [elo0, elo1] => Sprt { elo0, elo1, alpha: 0.05, beta: 0.05 },
+            [elo0, elo1, alpha, beta] => Sprt { elo0, elo1, alpha, beta },
+            _ => return Err(MatchError::InvalidSprt),
+        };
+        let rate = 0.0..0.5;
+        if sprt.elo0 >= sprt.elo1 || !rate.contains(&sprt.alpha) || !rate.contains(&sprt.beta) || sprt.alpha == 0.0 || sprt.beta == 0.0 {
+            return Err(MatchError::InvalidSprt);
+        }
+        Ok(sprt)
+    }
+}
+
+impl Display for Sprt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "elo0 {} elo1 {} alpha {} beta {}", self.elo0, self.elo1, self.alpha, self.beta)
+    }
+}
+
+impl Display for SprtResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SprtResult::AcceptH0 => write!(f, "H0 accepted"),
+            SprtResult::AcceptH1 => write!(f, "H1 accepted"),
+        }
+    }
+}
+
+// Impl EngineConfig
+
+impl EngineConfig {
+    /// These settings changed by the comma separated `key=value` ones in `settings`
+    pub fn with_settings(mut self, settings: &str) -> Result<EngineConfig, MatchError> {
+        for setting in settings.split(',').map(str::trim).filter(|setting| !setting.is_empty()) {
+            let invalid = || MatchError::InvalidEngine(setting.to_owned());
+            let (key, value) = setting.split_once('=').ok_or_else(invalid)?;
+            match key.trim() {
+                "name" => self.name = value.trim().to_owned(),
+                "params" => self.params = Some(PathBuf::from(value.trim())),
+                "nnue" => self.nnue = Some(PathBuf::from(value.trim())),
+                "syzygy" => self.syzygy = Some(value.trim().to_owned()),
+                "book" => self.book = Some(PathBuf::from(value.trim())),
+                "depth" => self.depth = Some(value.trim().parse().ok().filter(|&depth| depth > 0).ok_or_else(invalid)?),
+                "hash" => self.hash = value.trim().parse().ok().filter(|&hash| hash > 0).ok_or_else(invalid)?,
+                "threads" => self.threads = value.trim().parse().ok().filter(|&threads| threads > 0).ok_or_else(invalid)?,
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(self)
+    }
+
+    /// A new engine with these settings, loading its files
+    pub fn engine(&self) -> Result<Engine, MatchError> {
+        let mut engine = Engine {
+            tt: Arc::new(TranspositionTable::new(self.hash)),
+            threads: self.threads,
+            ..Engine::default()
+        };
+        if let Some(depth) = self.depth {
+            engine.depth = depth;
+        }
+        if let Some(path) = &self.params {
+            engine.params = Arc::new(EvalParams::open(path).map_err(MatchError::Params)?);
+        }
+        if let Some(path) = &self.nnue {
+            engine.network = Some(Arc::new(Network::open(path).map_err(MatchError::Network)?));
+            engine.use_network = true;
+        }
+        if let Some(paths) = &self.syzygy {
+            engine.tablebase = Some(Arc::new(Tablebase::open(paths).map_err(MatchError::Tablebase)?));
+        }
+        if let Some(path) = &self.book {
+            engine.book = Some(Book::open(path).map_err(MatchError::Book)?);
+        }
+        Ok(engine)
+    }
+}
+
+// Impl MatchScore
+
+impl MatchScore {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Points per game, a win counting one and a draw half
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games().max(1) as f64
+    }
+
+    /// Variance of the points of one game
+    fn variance(&self) -> f64 {
+        let score = self.score();
+        let games = self.games().max(1) as f64;
+        (self.wins as f64 * (1.0 - score).powi(2) + self.draws as f64 * (0.5 - score).powi(2) + self.losses as f64 * score.powi(2)) / games
+    }
+
+    /// Elo difference of the first engine with the 95% confidence margin either side.
+    /// Infinite while one engine has scored every point.
+    pub fn elo(&self) -> (f64, f64) {
+        let score = self.score();
+        if score == 0.0 || score == 1.0 {
+            return (elo_from_score(score), f64::INFINITY);
+        }
+        let margin = 1.96 * (self.variance() / self.games() as f64).sqrt();
+        let (low, high) = (elo_from_score((score - margin).max(0.0)), elo_from_score((score + margin).min(1.0)));
+        (elo_from_score(score), (high - low) / 2.0)
+    }
+}
+
+// Impl Sprt
+
+impl Sprt {
+    /// Log likelihood ratio of H1 over H0 so far, by the normal approximation to the
+    /// distribution of the match score
+    pub fn llr(&self, score: &MatchScore) -> f64 {
+        let variance = score.variance();
+        if score.games() == 0 || variance == 0.0 {
+            return 0.0;
+        }
+        let (score0, score1) = (score_from_elo(self.elo0), score_from_elo(self.elo1));
+        score.games() as f64 * (score1 - score0) * (2.0 * score.score() - score0 - score1) / (2.0 * variance)
+    }
+
+    /// The LLRs at which the test accepts H0 and H1
+    pub fn bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+
+    pub fn result(&self, score: &MatchScore) -> Option<SprtResult> {
+        let (lower, upper) = self.bounds();
+        let llr = self.llr(score);
+        if llr <= lower {
+            Some(SprtResult::AcceptH0)
+        } else if llr >= upper {
+            Some(SprtResult::AcceptH1)
+        } else {
+            None
+        }
+    }
+}
+
+// Functions
+
+/// Reads one opening position per line as FEN or EPD, skipping empty lines and `#` comments
+pub fn load_openings<P: AsRef<Path>>(path: P) -> Result<Vec<Game>, MatchError> {
+    let text = fs::read_to_string(path).map_err(|_| MatchError::OpeningsUnreadable)?;
+    let mut openings = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // EPD has four fields where FEN has six, followed by operations
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let game = Game::from_fen(&words[..words.len().min(6)].join(" "))
+            .or_else(|_| Game::from_fen(&words[..words.len().min(4)].join(" ")))
+            .map_err(|_| MatchError::InvalidOpening(number + 1))?;
+        openings.push(game);
+    }
+    if openings.is_empty() {
+        return Err(MatchError::NoOpenings);
+    }
+    Ok(openings)
+}
+
+/// The built in openings
+pub fn default_openings() -> Vec<Game> {
+    DEFAULT_OPENINGS
+        .iter()
+        .map(|line| {
+            let mut game = Game::default();
+            for word in line.split_whitespace() {
+                let mv: Move = word.parse().expect("default openings are valid moves");
+                game.play(mv).expect("default openings are legal");
+            }
+            game
+        })
+        .collect()
+}
+
+/// Plays the match, printing each result with the running score, Elo and SPRT state, and
+/// writing the games to the PGN file as they finish. Stops early once the SPRT decides,
+/// after the games already underway.
+pub fn run_match(config: &MatchConfig) -> Result<MatchScore, MatchError> {
+    let concurrency = config.concurrency.clamp(1, config.games.max(1));
+    let mut players = Vec::new();
+    for _ in 0..concurrency {
+        players.push([config.engines[0].engine()?, config.engines[1].engine()?]);
+    }
+    let mut pgn_file = match &config.pgn {
+        Some(path) => Some(File::create(path).map_err(|_| MatchError::PgnUnwritable)?),
+        None => None,
+    };
+
+    let names = [&config.engines[0].name, &config.engines[1].name];
+    println!("{} vs {}: {} games, {} at a time", names[0], names[1], config.games, concurrency);
+    if let Some(sprt) = &config.sprt {
+        let (lower, upper) = sprt.bounds();
+        println!("SPRT {sprt}, bounds {lower:.2} {upper:.2}");
+    }
+
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let mut score = MatchScore::default();
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        for mut engines in players {
+            let (next, stop, sender) = (&next, &stop, sender.clone());
+            scope.spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= config.games {
+                        break;
+                    }
+                    if sender.send(play_game(config, &mut engines, index)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        for record in receiver {
+            let first_color = if record.first_white { PieceColor::White } else { PieceColor::Black };
+            match record.winner {
+                Some(winner) if winner == first_color => score.wins += 1,
+                Some(_) => score.losses += 1,
+                None => score.draws += 1,
+            }
+
+            let (white, black) = if record.first_white { (names[0], names[1]) } else { (names[1], names[0]) };
+            println!(
+                "Game {} of {}: {white} vs {black} {} ({})",
+                record.index + 1,
+                config.games,
+                result_token(record.winner),
+                record.reason,
+            );
+            let (elo, margin) = score.elo();
+            print!("Score {score} ({:.1}%)  Elo {elo:.1} +/- {margin:.1}", score.score() * 100.0);
+            if let Some(sprt) = &config.sprt {
+                print!("  LLR {:.2}", sprt.llr(&score));
+            }
+            println!();
+
+            if let Some(file) = &mut pgn_file {
+                if writeln!(file, "{}", record.pgn).is_err() {
+                    eprintln!("{}", MatchError::PgnUnwritable);
+                    pgn_file = None;
+                }
+            }
+            if let Some(result) = config.sprt.and_then(|sprt| sprt.result(&score)) {
+                if !stop.swap(true, Ordering::Relaxed) {
+                    println!("SPRT: {result}, finishing the games underway");
+                }
+            }
+        }
+    });
+
+    let (elo, margin) = score.elo();
+    println!("Final score of {} vs {}: {score} in {} games", names[0], names[1], score.games());
+    println!("Elo difference: {elo:.1} +/- {margin:.1}");
+    if let Some(sprt) = &config.sprt {
+        let result = sprt.result(&score).map_or("inconclusive".to_owned(), |result| result.to_string());
+        println!("SPRT: LLR {:.2}, {result}", sprt.llr(&score));
+    }
+    Ok(score)
+}
+
+/// Plays game `index`: the opening is `index / 2`, and the first engine has White in
+/// even games. Ends on the board, on time, by repetition, by the move limit or once both
+/// engines agree on the outcome.
+fn play_game(config: &MatchConfig, engines: &mut [Engine; 2], index: usize) -> GameRecord {
+    let opening = config.openings[index / 2 % config.openings.len()];
+    let first_white = index.is_multiple_of(2);
+    let names = [&config.engines[0].name, &config.engines[1].name];
+
+    let mut pgn = Pgn::new(&opening);
+    pgn.set_tag("Event", &format!("{} vs {}", names[0], names[1]));
+    pgn.set_tag("Site", "rust_chess match");
+    pgn.set_tag("Round", &(index + 1).to_string());
+    pgn.set_tag("White", names[if first_white { 0 } else { 1 }]);
+    pgn.set_tag("Black", names[if first_white { 1 } else { 0 }]);
+    if let Some(time) = config.time {
+        pgn.set_tag("TimeControl", &time.to_string());
+    }
+    for engine in engines.iter() {
+        engine.tt.clear();
+    }
+
+    let mut game = opening;
+    let mut clock = config.time.map(Clock::new);
+    let mut repetitions: HashMap<u64, u8> = HashMap::new();
+    // Each move's score from White's side, for adjudication
+    let mut scores: Vec<i32> = Vec::new();
+    let (winner, reason, termination) = loop {
+        let status = game.status();
+        if status.is_over() {
+            break (status.winner(), status.to_string(), "normal");
+        }
+        let seen = repetitions.entry(game.polyglot_key()).or_default();
+        *seen += 1;
+        if *seen >= 3 {
+            break (None, "Threefold repetition".to_owned(), "normal");
+        }
+        if scores.len() >= MAX_PLIES {
+            break (None, "Move limit".to_owned(), "adjudication");
+        }
+
+        let side = usize::from((game.turn == PieceColor::White) != first_white);
+        let depth = config.engines[side].depth;
+        let limits = match &mut clock {
+            Some(clock) => {
+                clock.start(game.turn);
+                SearchLimits { depth: depth.unwrap_or(MAX_DEPTH), time: Some(clock.limits(game.turn)), nodes: None }
+            },
+            None => SearchLimits::depth(engines[side].depth),
+        };
+        // Book moves are played without a search, and scored as level for adjudication
+        let mut reached = 0;
+        let book = engines[side].book_move(&game);
+        let (best, score) = match book {
+            Some(mv) => (Some(mv), 0),
+            None => engines[side].search_with(&game, limits, |info| reached = info.depth),
+        };
+        let Some(mv) = best else {
+            break (None, "No move found".to_owned(), "unterminated");
+        };
+        if let Some(clock) = &mut clock {
+            clock.stop();
+            if clock.is_flagged(game.turn) {
+                break (Some(game.turn.swap()), format!("{} ran out of time", game.turn), "time forfeit");
+            }
+        }
+
+        let comment = match book {
+            Some(_) => "book".to_owned(),
+            None => format!("{:+.2}/{reached}", score as f64 / 100.0),
+        };
+        pgn.push(&game, mv, Some(comment));
+        scores.push(if game.turn == PieceColor::White { score } else { -score });
+        game.play_unchecked(mv).expect("searched moves are legal");
+
+        if let Some(recent) = scores.len().checked_sub(RESIGN_PLIES).map(|start| &scores[start..]) {
+            if recent.iter().all(|&score| score >= RESIGN_SCORE) {
+                break (Some(PieceColor::White), "Adjudicated win".to_owned(), "adjudication");
+            }
+            if recent.iter().all(|&score| score <= -RESIGN_SCORE) {
+                break (Some(PieceColor::Black), "Adjudicated win".to_owned(), "adjudication");
+            }
+        }
+        if scores.len() >= DRAW_AFTER_PLIES && scores[scores.len() - DRAW_PLIES..].iter().all(|score| score.abs() <= DRAW_SCORE) {
+            break (None, "Adjudicated draw".to_owned(), "adjudication");
+        }
+    };
+
+    pgn.set_result(winner);
+    pgn.set_tag("Termination", termination);
+    GameRecord { index, pgn, winner, reason, first_white }
+}
+
+fn elo_from_score(score: f64) -> f64 {
+    400.0 * (score / (1.0 - score)).log10()
+}
+
+fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_override_the_defaults_they_name() {
+        let defaults = EngineConfig { syzygy: Some("syzygy".to_owned()), book: Some(PathBuf::from("book.bin")), ..EngineConfig::default() };
+        let config = defaults.clone().with_settings("name=new, depth=6, book=other.bin").unwrap();
+        assert_eq!(config.name, "new");
+        assert_eq!(config.depth, Some(6));
+        assert_eq!(config.book, Some(PathBuf::from("other.bin")));
+        assert_eq!(config.syzygy, defaults.syzygy);
+        assert_eq!("syzygy=a:b".parse::<EngineConfig>().unwrap().syzygy.as_deref(), Some("a:b"));
+        assert!(matches!(defaults.with_settings("depth=0"), Err(MatchError::InvalidEngine(_))));
+    }
+}
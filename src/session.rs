@@ -0,0 +1,508 @@
+// Imports
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::analysis::*;
+use crate::clock::*;
+use crate::engine::*;
+use crate::errors::*;
+use crate::game::*;
+use crate::moves::*;
+use crate::pgn::*;
+use crate::piece::*;
+use crate::render::*;
+use crate::save::*;
+use crate::tui::*;
+use crate::variant::*;
+
+/// The engine accepts a draw only when its score, from its own side, is at most this
+const DRAW_ACCEPT_SCORE: i32 = -10;
+
+/// What `help` shows
+pub const COMMAND_HELP: &str = "Commands: undo, redo, resign, draw, flip, fen, pgn, save <file>, load <file>, \
+    hint, legal <square>, eval, help and quit. Moves are written as e4, Nf3, e2e4 or N@f3.";
+
+// Custom Command Type Data
+
+/// Something typed during a game
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Takes back the last move, and the engine's reply before it when playing the engine
+    Undo,
+    /// Plays a taken back move again
+    Redo,
+    Resign,
+    /// Offers a draw, or accepts the opponent's offer
+    Draw,
+    /// Turns the board around
+    Flip,
+    Fen,
+    Pgn,
+    Save(String),
+    Load(String),
+    Hint,
+    /// Lists the legal moves of the piece on a square
+    Legal(Coordinates),
+    Eval,
+    Help,
+    Quit,
+    /// Anything else, tried as a move in SAN or coordinates
+    Move(String),
+}
+
+// Custom Session Type Data
+
+/// A game being played: the position with its history, PGN and clock, and how it is
+/// shown. Both the line prompts and the full screen interface play through one.
+#[derive(Debug, Clone)]
+pub struct Session<V: Variant = Standard> {
+    pub game: Game<V>,
+    pub pgn: Pgn,
+    pub clock: Option<Clock>,
+    pub engine_color: Option<PieceColor>,
+    pub options: RenderOptions,
+    /// Each move played with the position and clock before it, for taking moves back.
+    /// Moves replayed from a save have no clock, and taking them back leaves it alone.
+    pub history: Vec<(Game<V>, Move, Option<Clock>)>,
+    /// Moves taken back with the clock after them, the latest last, which `redo` plays again
+    undone: Vec<(Move, Option<Clock>)>,
+    /// The side whose draw offer stands
+    pub draw_offer: Option<PieceColor>,
+    /// How the game ended, once it has
+    pub ending: Option<String>,
+    /// File the game is saved to after every move, and every command that changes it
+    pub autosave: Option<PathBuf>,
+}
+
+// Custom Command Type Traits
+
+impl FromStr for Command {
+    type Err = CommandError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, argument) = match s.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, Some(argument.trim())),
+            None => (s, None),
+        };
+        let path = |command| argument.map(str::to_owned).ok_or(CommandError::MissingArgument(command, "a file name"));
+        match name.to_ascii_lowercase().as_str() {
+            "undo" | "takeback" => Ok(Command::Undo),
+            "redo" => Ok(Command::Redo),
+            "resign" => Ok(Command::Resign),
+            "draw" => Ok(Command::Draw),
+            "flip" => Ok(Command::Flip),
+            "fen" => Ok(Command::Fen),
+            "pgn" => Ok(Command::Pgn),
+            "save" => Ok(Command::Save(path("save")?)),
+            "load" => Ok(Command::Load(path("load")?)),
+            "hint" => Ok(Command::Hint),
+            "legal" => {
+                let square = argument.ok_or(CommandError::MissingArgument("legal", "a square"))?;
+                let coords = chess_notation_to_array_notation(square).map_err(|_| CommandError::InvalidSquare(square.to_owned()))?;
+                Ok(Command::Legal(coords))
+            },
+            "eval" => Ok(Command::Eval),
+            "help" | "?" => Ok(Command::Help),
+            "quit" | "exit" => Ok(Command::Quit),
+            _ => Ok(Command::Move(s.to_owned())),
+        }
+    }
+}
+
+// Impl Session
+
+impl<V: Variant> Session<V> {
+    /// A game from `game` where the engine plays `engine_color` if given, with the
+    /// players and time control in its PGN tags
+    pub fn new(game: Game<V>, engine_color: Option<PieceColor>, clock: Option<Clock>, options: RenderOptions) -> Session<V> {
+        let mut pgn = Pgn::new(&game);
+        for (tag, color) in [("White", PieceColor::White), ("Black", PieceColor::Black)] {
+            pgn.set_tag(tag, if engine_color == Some(color) { "rust_chess" } else { "Player" });
+        }
+        if let Some(clock) = &clock {
+            pgn.set_tag("TimeControl", &clock.control.to_string());
+        }
+        Session {
+            game,
+            pgn,
+            clock,
+            engine_color,
+            options,
+            history: Vec::new(),
+            undone: Vec::new(),
+            draw_offer: None,
+            ending: None,
+            autosave: None,
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.ending.is_some()
+    }
+
+    /// The position the game started from
+    pub fn start(&self) -> Game<V> {
+        self.history.first().map_or(self.game, |&(start, ..)| start)
+    }
+
+    /// The last move as the player sees it, castling shown as the king's move
+    pub fn last_move(&self) -> Option<Move> {
+        self.history.last().map(|(before, mv, _)| before.uci_move(*mv))
+    }
+
+    /// Plays `mv` for the side to move, which drops any moves taken back
+    pub fn play(&mut self, mv: Move) -> Result<(), GameError> {
+        self.advance(mv)?;
+        self.undone.clear();
+        self.autosave();
+        Ok(())
+    }
+
+    /// Ends the game for a reason the board doesn't show, `winner` taking it or a draw
+    pub fn finish(&mut self, winner: Option<PieceColor>, ending: String) {
+        self.pgn.set_result(winner);
+        self.ending = Some(ending);
+    }
+
+    /// Ends the game if a side ran out of time or the position is over
+    pub fn check_end(&mut self) {
+        if self.is_over() {
+            return;
+        }
+        let turn = self.game.turn;
+        if let Some(flagged) = [turn, turn.swap()].into_iter().find(|&color| self.clock.is_some_and(|clock| clock.is_flagged(color))) {
+            self.finish(Some(flagged.swap()), format!("{flagged} ran out of time"));
+            return;
+        }
+        let status = self.game.status();
+        if status.is_over() {
+            self.finish(status.winner(), status.to_string());
+        }
+    }
+
+    /// Plays the game on the terminal until it ends or the player quits. `engine` answers
+    /// commands such as `hint` and plays `engine_color` if set, thinking within its time
+    /// on the clock when there is one.
+    pub fn game_loop(&mut self, engine: &mut Engine) {
+        // On a terminal the game is played full screen, the prompts below serve piped input
+        if io::stdin().is_terminal() && io::stdout().is_terminal() {
+            if let Ok(terminal) = Terminal::open() {
+                self.tui_loop(terminal, engine);
+                return;
+            }
+        }
+
+        self.check_end();
+        while !self.is_over() {
+            let turn = self.game.turn;
+            println!("{}", self.game.render(&self.options, self.last_move()));
+            if let Some(clock) = &self.clock {
+                println!("{clock}");
+            }
+
+            if self.engine_color == Some(turn) {
+                let limits = match &mut self.clock {
+                    Some(clock) => {
+                        clock.start(turn);
+                        SearchLimits::time(clock.limits(turn))
+                    },
+                    None => SearchLimits::depth(engine.depth),
+                };
+                let Some(mv) = engine.best_move_within(&self.game, limits) else {
+                    break;
+                };
+                let notation = self.game.uci_move(mv);
+                if let Err(error) = self.play(mv) {
+                    println!("{error}");
+                    break;
+                }
+                println!("Engine plays {notation}");
+                continue;
+            }
+            if let Some(clock) = &mut self.clock {
+                clock.start(turn);
+            }
+
+            match V::POCKETS {
+                true => println!("{turn} to move: a move (e4, N@f3), a start square, or a command (help lists them)"),
+                false => println!("{turn} to move: a move (e4, Nf3), a start square, or a command (help lists them)"),
+            }
+            let Some(input) = read_input() else {
+                break;
+            };
+
+            // A lone square holding one of the mover's pieces starts a move, asking where it
+            // goes next. Otherwise `e4` is a pawn move.
+            let start = chess_notation_to_array_notation(&input).ok();
+            if let Some(start) = start.filter(|&start| self.game.board.get(start).is_some_and(|piece| piece.color == turn)) {
+                println!("Input end square {turn}");
+                let Some(end) = read_input().and_then(|end| chess_notation_to_array_notation(&end).ok()) else {
+                    println!("That is not a square");
+                    continue;
+                };
+                let mut mv = Move::new(start, end);
+                if self.game.is_promotion(mv) {
+                    mv.promotion = Some(promotion_from_input(V::promotion_pieces()));
+                }
+                if let Err(error) = self.play(mv) {
+                    println!("{error}");
+                }
+                continue;
+            }
+
+            match input.parse().and_then(|command| self.execute(command, engine)) {
+                Ok(feedback) if feedback.is_empty() => {},
+                Ok(feedback) => println!("{feedback}"),
+                Err(error) => println!("{error}"),
+            }
+        }
+
+        if let Some(ending) = &self.ending {
+            println!("{}", self.game.render(&self.options, self.last_move()));
+            println!("{ending}");
+        }
+    }
+
+    /// Runs `command`, returning what to tell the player (nothing for a move)
+    pub fn execute(&mut self, command: Command, engine: &mut Engine) -> Result<String, CommandError> {
+        let turn = self.game.turn;
+        match command {
+            Command::Undo => {
+                let mut taken = vec![self.take_back().ok_or(CommandError::NothingToUndo)?];
+                // Against the engine the player gets back to their own move
+                if self.engine_color == Some(self.game.turn) {
+                    taken.extend(self.take_back());
+                }
+                taken.reverse();
+                self.autosave();
+                Ok(format!("Took back {}", taken.join(" ")))
+            },
+            Command::Redo => {
+                let mut played = vec![self.replay().ok_or(CommandError::NothingToRedo)?];
+                if self.engine_color == Some(self.game.turn) {
+                    played.extend(self.replay());
+                }
+                self.autosave();
+                Ok(format!("Played {} again", played.join(" ")))
+            },
+            Command::Resign => {
+                let loser = self.engine_color.map_or(turn, |color| color.swap());
+                self.finish(Some(loser.swap()), format!("{loser} resigns, {} wins", loser.swap()));
+                self.autosave();
+                Ok(String::new())
+            },
+            Command::Draw => match self.engine_color {
+                Some(engine_color) => {
+                    let (_, score) = engine.search_with(&self.game, SearchLimits::depth(engine.depth), |_| {});
+                    let score = if turn == engine_color { score } else { -score };
+                    if score > DRAW_ACCEPT_SCORE {
+                        return Ok("The engine declines the draw".to_owned());
+                    }
+                    self.finish(None, "The engine accepts the draw".to_owned());
+                    self.autosave();
+                    Ok(String::new())
+                },
+                None if self.draw_offer == Some(turn.swap()) => {
+                    self.finish(None, format!("{turn} accepts the draw"));
+                    self.autosave();
+                    Ok(String::new())
+                },
+                None => {
+                    self.draw_offer = Some(turn);
+                    self.autosave();
+                    Ok(format!("{turn} offers a draw, {} can accept by typing draw on their move", turn.swap()))
+                },
+            },
+            Command::Flip => {
+                let (orientation, side) = match self.options.is_flipped(turn) {
+                    true => (Orientation::White, PieceColor::White),
+                    false => (Orientation::Black, PieceColor::Black),
+                };
+                self.options.orientation = orientation;
+                Ok(format!("Board shown from {side}'s side"))
+            },
+            Command::Fen => Ok(self.game.to_fen()),
+            Command::Pgn => Ok(self.pgn.to_string()),
+            // Games are saved to resume later, or as PGN for other programs
+            Command::Save(path) => {
+                let text = match path.to_ascii_lowercase().ends_with(".pgn") {
+                    true => self.pgn.to_string(),
+                    false => self.to_save(),
+                };
+                write_atomically(&path, &text).map_err(|_| CommandError::Unwritable(path.clone()))?;
+                Ok(format!("Saved the game to {path}"))
+            },
+            Command::Load(path) => {
+                self.load(&path)?;
+                self.autosave();
+                Ok(format!("Loaded {path}, {} moves in", self.history.len()))
+            },
+            Command::Hint => {
+                let Some(mv) = engine.best_move(&self.game) else {
+                    return Ok("No moves left to suggest".to_owned());
+                };
+                let mut hint = format!("Hint: {}", san(&self.game, mv));
+                if let Some(wdl) = engine.tablebase.as_ref().and_then(|tablebase| tablebase.probe_wdl(&self.game)) {
+                    hint.push_str(&format!(", tablebase: {wdl} for {turn}"));
+                }
+                Ok(hint)
+            },
+            Command::Legal(square) => {
+                let name = array_notation_to_chess_notation(square);
+                if self.game.board.get(square).is_none_or(|piece| piece.color != turn) {
+                    return Err(CommandError::NoPiece(name));
+                }
+                let moves: Vec<String> = self
+                    .game
+                    .legal_moves()
+                    .into_iter()
+                    .filter(|mv| mv.drop.is_none() && mv.start == square)
+                    .map(|mv| san(&self.game, mv))
+                    .collect();
+                match moves.is_empty() {
+                    true => Ok(format!("The piece on {name} has no legal moves")),
+                    false => Ok(format!("Legal moves from {name}: {}", moves.join(", "))),
+                }
+            },
+            Command::Eval => {
+                let (best, score) = engine.search_with(&self.game, SearchLimits::depth(engine.depth), |_| {});
+                let score = if turn == PieceColor::White { score } else { -score };
+                let best = best.map_or(String::new(), |mv| format!(", best {}", san(&self.game, mv)));
+                Ok(format!("Eval {} for White at depth {}{best}", score_text(score), engine.depth))
+            },
+            Command::Help => Ok(COMMAND_HELP.to_owned()),
+            Command::Quit => {
+                self.ending = Some("Game left unfinished".to_owned());
+                Ok(String::new())
+            },
+            Command::Move(text) => {
+                let mv = parse_san(&self.game, &text).ok_or_else(|| CommandError::Unknown(text.clone()))?;
+                self.play(mv).map_err(|_| CommandError::Unknown(text))?;
+                Ok(String::new())
+            },
+        }
+    }
+
+    /// Plays `mv`, stopping its side's clock, recording it and ending the game if it is over
+    fn advance(&mut self, mv: Move) -> Result<(), GameError> {
+        let before = self.game;
+        let clock = self.clock.map(|clock| clock.before_running());
+        self.game.play(mv)?;
+        if let Some(clock) = &mut self.clock {
+            clock.stop();
+        }
+        self.pgn.push(&before, mv, None);
+        self.history.push((before, mv, clock));
+        // Playing on declines the opponent's draw offer
+        if self.draw_offer == Some(self.game.turn) {
+            self.draw_offer = None;
+        }
+        self.check_end();
+        Ok(())
+    }
+
+    /// Takes back the last move and the time spent on it, returning it in SAN
+    fn take_back(&mut self) -> Option<String> {
+        let (before, mv, clock) = self.history.pop()?;
+        self.undone.push((mv, self.clock.map(|clock| clock.before_running())));
+        if clock.is_some() {
+            self.clock = clock;
+        }
+        self.game = before;
+        self.pgn.moves.pop();
+        self.draw_offer = None;
+        Some(san(&before, mv))
+    }
+
+    /// Plays the last move taken back again with the clock as it was after it, returning
+    /// it in SAN
+    fn replay(&mut self) -> Option<String> {
+        let (mv, clock) = self.undone.pop()?;
+        let text = san(&self.game, mv);
+        self.advance(mv).ok()?;
+        if clock.is_some() {
+            self.clock = clock;
+        }
+        Some(text)
+    }
+
+    /// Replaces the game with a saved one, or with the one in a PGN or FEN file keeping
+    /// the players
+    fn load(&mut self, path: &str) -> Result<(), CommandError> {
+        let text = fs::read_to_string(path).map_err(|_| CommandError::Unreadable(path.to_owned()))?;
+        if is_save(&text) {
+            let loaded = Session::from_save(&text, self.options).map_err(CommandError::Save)?;
+            *self = Session { autosave: self.autosave.take(), ..loaded };
+            return Ok(());
+        }
+        let (start, moves) = match Game::<V>::from_fen(text.trim()) {
+            Ok(game) => (game, Vec::new()),
+            Err(_) => {
+                let pgn: Pgn = text.parse().map_err(CommandError::Pgn)?;
+                let variant = pgn.tag("Variant").unwrap_or(Standard::NAME);
+                let chess960 = V::NAME == Standard::NAME && variant.eq_ignore_ascii_case("chess960");
+                if !variant.eq_ignore_ascii_case(V::NAME) && !chess960 {
+                    return Err(CommandError::Pgn(PgnError::UnsupportedVariant(variant.to_owned())));
+                }
+                pgn.replay::<V>().map_err(CommandError::Pgn)?
+            },
+        };
+
+        let mut pgn = Pgn::new(&start);
+        for (name, value) in &self.pgn.tags {
+            if matches!(name.as_str(), "White" | "Black" | "TimeControl") {
+                pgn.set_tag(name, value);
+            }
+        }
+        self.pgn = pgn;
+        self.game = start;
+        self.history.clear();
+        self.undone.clear();
+        self.draw_offer = None;
+        for mv in moves {
+            self.advance(mv).map_err(|_| CommandError::Pgn(PgnError::IllegalMove(mv.to_string())))?;
+        }
+        Ok(())
+    }
+}
+
+// Functions
+
+/// A trimmed line from stdin, `None` once input ends
+fn read_input() -> Option<String> {
+    let mut input = String::new();
+    match io::stdin().read_line(&mut input) {
+        Ok(read) if read > 0 => Some(input.trim().to_owned()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn undo_and_redo_bring_back_the_clock() {
+        let clock = Clock::new("5+0".parse().unwrap());
+        let mut session = Session::<Standard>::new(Game::default(), None, Some(clock), RenderOptions::default());
+        let mut engine = Engine::default();
+        session.clock.as_mut().unwrap().start(PieceColor::White);
+        thread::sleep(Duration::from_millis(20));
+        session.play(parse_san(&session.game, "e4").unwrap()).unwrap();
+        let after = session.clock.unwrap();
+        assert!(after.remaining(PieceColor::White) < Duration::from_secs(300));
+
+        session.clock.as_mut().unwrap().start(PieceColor::Black);
+        thread::sleep(Duration::from_millis(20));
+        session.execute(Command::Undo, &mut engine).unwrap();
+        assert_eq!(session.clock, Some(clock));
+
+        session.execute(Command::Redo, &mut engine).unwrap();
+        assert_eq!(session.clock, Some(after));
+    }
+}
@@ -0,0 +1,161 @@
+// Imports
+use crate::engine::*;
+use crate::game::*;
+use crate::moves::*;
+use crate::rng::*;
+use crate::variant::*;
+
+/// Full strength, with no limits
+pub const MAX_SKILL_LEVEL: u8 = 20;
+/// Rough playing strength of level 0 and of the strongest limited level, for `UCI_Elo`
+pub const MIN_ELO: u32 = 800;
+pub const MAX_ELO: u32 = MIN_ELO + ELO_PER_LEVEL * (MAX_SKILL_LEVEL as u32 - 1);
+const ELO_PER_LEVEL: u32 = 70;
+/// Nodes level 0 may search for a whole move, doubling every other level
+const BASE_NODES: u64 = 100;
+/// Centipawns a move may fall short of the best and still be chosen, per level below full
+const INACCURACY_PER_LEVEL: i32 = 15;
+/// The chance of ignoring the scores for any move not walking into a forced mate is the
+/// square of the levels below full, in percent, divided by this: a half at level 0
+const BLUNDER_DIVISOR: u64 = 8;
+
+// Custom Skill Type Data
+
+/// How well the engine plays, from level 0 for beginners up to full strength at
+/// `MAX_SKILL_LEVEL`. Lower levels search shallower with fewer nodes, pick among moves
+/// that score nearly as well as the best at random, and now and then play a move without
+/// regard for its score.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Skill {
+    level: u8,
+}
+
+// Custom Skill Type Traits
+
+impl Default for Skill {
+    fn default() -> Self {
+        Skill { level: MAX_SKILL_LEVEL }
+    }
+}
+
+// Impl Skill
+
+impl Skill {
+    pub fn new(level: u8) -> Skill {
+        Skill { level: level.min(MAX_SKILL_LEVEL) }
+    }
+
+    /// The limited level that plays closest to `elo`
+    pub fn from_elo(elo: u32) -> Skill {
+        let level = (elo.clamp(MIN_ELO, MAX_ELO) - MIN_ELO + ELO_PER_LEVEL / 2) / ELO_PER_LEVEL;
+        Skill::new(level as u8)
+    }
+
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    /// Approximate Elo of a limited level
+    pub fn elo(&self) -> u32 {
+        MIN_ELO + ELO_PER_LEVEL * self.level as u32
+    }
+
+    pub fn is_limited(&self) -> bool {
+        self.level < MAX_SKILL_LEVEL
+    }
+
+    /// `limits` narrowed to this level's depth and nodes
+    pub fn limit(&self, limits: SearchLimits) -> SearchLimits {
+        if !self.is_limited() {
+            return limits;
+        }
+        let nodes = BASE_NODES << (self.level / 2);
+        SearchLimits {
+            depth: limits.depth.min(1 + self.level / 2),
+            nodes: Some(limits.nodes.map_or(nodes, |limit| limit.min(nodes))),
+            ..limits
+        }
+    }
+
+    /// Picks from root moves scored for the side to move
+    pub fn pick(&self, scored: &[(Move, i32)], rng: &mut Rng) -> Option<Move> {
+        let best = scored.iter().map(|&(_, score)| score).max()?;
+        let below_full = (MAX_SKILL_LEVEL - self.level) as i32;
+
+        if rng.below(100 * BLUNDER_DIVISOR) < (below_full * below_full) as u64 {
+            let unforced: Vec<Move> = scored
+                .iter()
+                .filter(|&&(_, score)| mate_in(score).is_none_or(|moves| moves >= 0))
+                .map(|&(mv, _)| mv)
+                .collect();
+            if !unforced.is_empty() {
+                return Some(unforced[rng.below(unforced.len() as u64) as usize]);
+            }
+        }
+
+        // Each move close enough to the best gets a random bonus of up to the tolerance
+        let tolerance = below_full * INACCURACY_PER_LEVEL;
+        scored
+            .iter()
+            .filter(|&&(_, score)| score >= best - tolerance)
+            .map(|&(mv, score)| (mv, score + rng.below(tolerance as u64 + 1) as i32))
+            .max_by_key(|&(_, score)| score)
+            .map(|(mv, _)| mv)
+    }
+}
+
+// Impl Engine
+
+impl Engine {
+    /// The move to play at the engine's skill level: one search within the level's depth
+    /// and nodes scores every root move as its own line, and `Skill::pick` chooses among
+    /// them. Like any search it keeps to the clock and ends on the stop flag.
+    pub fn skilled_move<V: Variant>(&mut self, game: &Game<V>, limits: SearchLimits) -> Option<Move> {
+        let limits = self.skill.limit(limits);
+        let multi_pv = std::mem::replace(&mut self.multi_pv, usize::MAX);
+        let mut scored = Vec::new();
+        self.search_with(game, limits, |info| {
+            scored = info.lines.iter().filter_map(|line| Some((*line.pv.first()?, line.score))).collect();
+        });
+        self.multi_pv = multi_pv;
+        let skill = self.skill;
+        skill.pick(&scored, &mut self.rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::clock::*;
+    use crate::pgn::*;
+
+    #[test]
+    fn the_lowest_level_misses_what_full_strength_finds() {
+        // Taking the queen is the only good move
+        let game = Game::<Standard>::from_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1").unwrap();
+        let capture = parse_san(&game, "Rxd5").unwrap();
+        let picks = |level| {
+            let mut engine = Engine { skill: Skill::new(level), rng: Rng::new(7), ..Engine::default() };
+            (0..10).map(|_| engine.best_move(&game).unwrap()).collect::<Vec<Move>>()
+        };
+        assert!(picks(MAX_SKILL_LEVEL).iter().all(|&mv| mv == capture));
+        let weakest = picks(0);
+        assert!(weakest.iter().any(|&mv| mv != capture), "{weakest:?}");
+        assert_eq!(weakest, picks(0));
+    }
+
+    #[test]
+    fn limited_searches_keep_to_the_clock() {
+        let game = Game::<Standard>::default();
+        let mut engine = Engine { skill: Skill::new(MAX_SKILL_LEVEL - 1), ..Engine::default() };
+        let limits = SearchLimits::time(TimeLimits::fixed(Duration::from_millis(20)));
+        let start = Instant::now();
+        assert!(engine.best_move_within(&game, limits).is_some());
+        // Its nodes alone would take far longer
+        assert!(start.elapsed() < Duration::from_secs(2), "{:?}", start.elapsed());
+        // The search's own multi-PV setting is left as it was
+        assert_eq!(engine.multi_pv, 1);
+    }
+}
@@ -0,0 +1,169 @@
+// Imports
+use std::fmt::{Debug, Display};
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not};
+
+use crate::piece::*;
+
+// Custom SquareSet Type Data
+
+/// A set of board squares stored as a 64 bit mask.
+/// Bit `row * 8 + col` is set when `Coordinates(row, col)` is in the set,
+/// so bit 0 is A8 and bit 63 is H1, matching the layout of `Board::board`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SquareSet(pub u64);
+
+pub struct SquareSetIter(u64);
+
+// Custom SquareSet Type Traits
+
+impl Display for SquareSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in 0..8u8 {
+            for col in 0..8u8 {
+                let mark = if self.contains(Coordinates(row, col)) { '1' } else { '.' };
+                write!(f, "{mark}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl BitOr for SquareSet {
+    type Output = SquareSet;
+
+    fn bitor(self, rhs: SquareSet) -> SquareSet {
+        SquareSet(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for SquareSet {
+    fn bitor_assign(&mut self, rhs: SquareSet) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for SquareSet {
+    type Output = SquareSet;
+
+    fn bitand(self, rhs: SquareSet) -> SquareSet {
+        SquareSet(self.0 & rhs.0)
+    }
+}
+
+impl BitAndAssign for SquareSet {
+    fn bitand_assign(&mut self, rhs: SquareSet) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl Not for SquareSet {
+    type Output = SquareSet;
+
+    fn not(self) -> SquareSet {
+        SquareSet(!self.0)
+    }
+}
+
+impl Iterator for SquareSetIter {
+    type Item = Coordinates;
+
+    fn next(&mut self) -> Option<Coordinates> {
+        if self.0 == 0 {
+            return None;
+        }
+        let index = self.0.trailing_zeros() as u8;
+        self.0 &= self.0 - 1;
+        Some(Coordinates(index / 8, index % 8))
+    }
+}
+
+impl IntoIterator for SquareSet {
+    type Item = Coordinates;
+    type IntoIter = SquareSetIter;
+
+    fn into_iter(self) -> SquareSetIter {
+        SquareSetIter(self.0)
+    }
+}
+
+impl FromIterator<Coordinates> for SquareSet {
+    fn from_iter<T: IntoIterator<Item = Coordinates>>(iter: T) -> Self {
+        let mut set = SquareSet::default();
+        for coords in iter {
+            set.insert(coords);
+        }
+        set
+    }
+}
+
+// Impl SquareSet
+
+impl SquareSet {
+    pub fn from_square(coords: Coordinates) -> SquareSet {
+        SquareSet(1 << square_index(coords))
+    }
+
+    pub fn contains(&self, coords: Coordinates) -> bool {
+        self.0 & (1 << square_index(coords)) != 0
+    }
+
+    pub fn insert(&mut self, coords: Coordinates) {
+        self.0 |= 1 << square_index(coords);
+    }
+
+    pub fn remove(&mut self, coords: Coordinates) {
+        self.0 &= !(1 << square_index(coords));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn len(&self) -> u32 {
+        self.0.count_ones()
+    }
+
+    pub fn iter(&self) -> SquareSetIter {
+        SquareSetIter(self.0)
+    }
+}
+
+// Functions
+
+pub fn square_index(coords: Coordinates) -> u8 {
+    coords.0 * 8 + coords.1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn squares_map_to_bits_from_a8_to_h1() {
+        assert_eq!(SquareSet::from_square(Coordinates(0, 0)), SquareSet(1));
+        assert_eq!(SquareSet::from_square(Coordinates(7, 7)), SquareSet(1 << 63));
+        assert_eq!(SquareSet::from_square(Coordinates(1, 2)), SquareSet(1 << 10));
+    }
+
+    #[test]
+    fn sets_combine_and_iterate_in_order() {
+        let mut set: SquareSet = [Coordinates(7, 7), Coordinates(0, 0), Coordinates(3, 4)].into_iter().collect();
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(Coordinates(3, 4)) && !set.contains(Coordinates(4, 3)));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![Coordinates(0, 0), Coordinates(3, 4), Coordinates(7, 7)]);
+
+        let corners = SquareSet::from_square(Coordinates(0, 0)) | SquareSet::from_square(Coordinates(7, 7));
+        assert_eq!(set & corners, corners);
+        assert_eq!((set & !corners).iter().collect::<Vec<_>>(), vec![Coordinates(3, 4)]);
+
+        set.remove(Coordinates(3, 4));
+        set.remove(Coordinates(3, 4));
+        assert_eq!(set, corners);
+        set &= SquareSet::default();
+        assert!(set.is_empty());
+        set |= corners;
+        assert_eq!(set.to_string().lines().next(), Some("1......."));
+    }
+}
@@ -0,0 +1,1285 @@
+// Imports
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Display};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::errors::*;
+use crate::game::*;
+use crate::moves::*;
+use crate::piece::*;
+use crate::variant::*;
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+const MAX_PIECES: usize = 7;
+
+// Per table and per block flags
+const SPLIT: u8 = 1;
+const HAS_PAWNS: u8 = 2;
+const STM: u8 = 1;
+const MAPPED: u8 = 2;
+const WIN_PLIES: u8 = 4;
+const LOSS_PLIES: u8 = 8;
+const WIDE: u8 = 16;
+const SINGLE_VALUE: u8 = 128;
+
+/// Tells whether a piece type is of one kind
+type PieceKindTest = fn(PieceType) -> bool;
+
+/// Piece letters in the order Syzygy file names use them
+const NAME_ORDER: [(char, PieceKindTest); 6] = [
+    ('K', |piece_type| matches!(piece_type, PieceType::King(_))),
+    ('Q', |piece_type| piece_type == PieceType::Queen),
+    ('R', |piece_type| matches!(piece_type, PieceType::Rook(_))),
+    ('B', |piece_type| piece_type == PieceType::Bishop),
+    ('N', |piece_type| piece_type == PieceType::Knight),
+    ('P', |piece_type| matches!(piece_type, PieceType::Pawn(_))),
+];
+
+// Custom Wdl Type Data
+
+/// Win/draw/loss for the side to move with perfect play. Cursed wins and blessed losses
+/// are wins and losses that the fifty move rule turns into draws.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2,
+}
+
+// Custom Wdl Type Traits
+
+impl Display for Wdl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Wdl::Loss => write!(f, "Lost with perfect play"),
+            Wdl::BlessedLoss => write!(f, "Lost, but drawn by the fifty move rule"),
+            Wdl::Draw => write!(f, "Drawn with perfect play"),
+            Wdl::CursedWin => write!(f, "Won, but drawn by the fifty move rule"),
+            Wdl::Win => write!(f, "Won with perfect play"),
+        }
+    }
+}
+
+// Impl Wdl
+
+impl Wdl {
+    fn from_value(value: i32) -> Wdl {
+        match value {
+            ..=-2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+
+    fn negate(self) -> Wdl {
+        Wdl::from_value(-(self as i32))
+    }
+
+    /// The side that wins under the fifty move rule, given the side to move
+    pub fn winner(self, turn: PieceColor) -> Option<PieceColor> {
+        match self {
+            Wdl::Win => Some(turn),
+            Wdl::Loss => Some(turn.swap()),
+            _ => None,
+        }
+    }
+
+    /// DTZ of a position whose best move resets the fifty move counter
+    fn dtz_before_zeroing(self) -> i32 {
+        match self {
+            Wdl::Win => 1,
+            Wdl::CursedWin => 101,
+            Wdl::BlessedLoss => -101,
+            Wdl::Loss => -1,
+            Wdl::Draw => 0,
+        }
+    }
+}
+
+// Custom Tablebase Type Data
+
+/// Syzygy WDL (`.rtbw`) and DTZ (`.rtbz`) tables found in one or more directories.
+/// Files are read the first time a position with their material is probed.
+pub struct Tablebase {
+    pub directories: Vec<PathBuf>,
+    /// Table names available on disk (e.g. "KQvK"), without the extension
+    available: HashSet<String>,
+    max_pieces: usize,
+    wdl_tables: Mutex<HashMap<String, Option<Arc<Table>>>>,
+    dtz_tables: Mutex<HashMap<String, Option<Arc<Table>>>>,
+}
+
+/// Outcome of reading a single table
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Probe {
+    Ok,
+    /// The best move resets the fifty move counter, so DTZ must not be read
+    ZeroingBestMove,
+    /// A DTZ table only stores the other side to move
+    ChangeStm,
+}
+
+// Custom Tablebase Type Traits
+
+impl Debug for Tablebase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tablebase")
+            .field("directories", &self.directories)
+            .field("tables", &self.available.len())
+            .field("max_pieces", &self.max_pieces)
+            .finish()
+    }
+}
+
+// Impl Tablebase
+
+impl Tablebase {
+    /// Finds the tables in a list of directories separated like `PATH` (`:` or `;` on Windows)
+    pub fn open(paths: &str) -> Result<Tablebase, TablebaseError> {
+        let directories: Vec<PathBuf> = std::env::split_paths(paths).filter(|path| !path.as_os_str().is_empty()).collect();
+        let mut available = HashSet::new();
+        for directory in &directories {
+            let entries = fs::read_dir(directory).map_err(|_| TablebaseError::Unreadable)?;
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let is_table = path.extension().is_some_and(|extension| extension == "rtbw" || extension == "rtbz");
+                if let (true, Some(stem)) = (is_table, path.file_stem().and_then(|stem| stem.to_str())) {
+                    available.insert(stem.to_owned());
+                }
+            }
+        }
+        if available.is_empty() {
+            return Err(TablebaseError::NoTables);
+        }
+
+        let max_pieces = available.iter().map(|name| name.len() - 1).max().unwrap_or(0).min(MAX_PIECES);
+        Ok(Tablebase {
+            directories,
+            available,
+            max_pieces,
+            wdl_tables: Mutex::new(HashMap::new()),
+            dtz_tables: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// The most pieces (kings included) any available table covers
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// Whether the position is standard chess with few enough pieces and no castling rights
+    pub fn can_probe<V: Variant>(&self, game: &Game<V>) -> bool {
+        V::NAME == Standard::NAME &&
+        game.board.board.iter().flatten().flatten().count() <= self.max_pieces &&
+        [PieceColor::White, PieceColor::Black].iter().all(|&color| game.castling_sides(color) == (false, false))
+    }
+
+    /// Win/draw/loss for the side to move, ignoring how many moves the fifty move counter has left
+    pub fn probe_wdl<V: Variant>(&self, game: &Game<V>) -> Option<Wdl> {
+        if !self.can_probe(game) {
+            return None;
+        }
+        self.search(game, false).map(|(wdl, _)| wdl)
+    }
+
+    /// Distance to zeroing in plies: the number of plies until the winning side captures
+    /// or moves a pawn, positive when the side to move wins and negative when it loses.
+    /// Cursed wins and blessed losses are pushed beyond 100.
+    pub fn probe_dtz<V: Variant>(&self, game: &Game<V>) -> Option<i32> {
+        if !self.can_probe(game) {
+            return None;
+        }
+        self.dtz(game)
+    }
+
+    /// The move that keeps the best result the quickest, with the result after it
+    /// for the side to move now. Without DTZ tables the moves are only ranked by
+    /// WDL, which keeps the result but may not make progress towards it.
+    pub fn best_move<V: Variant>(&self, game: &Game<V>) -> Option<(Move, Wdl)> {
+        if !self.can_probe(game) {
+            return None;
+        }
+        self.best_move_by_dtz(game).or_else(|| self.best_move_by_wdl(game))
+    }
+
+    fn best_move_by_dtz<V: Variant>(&self, game: &Game<V>) -> Option<(Move, Wdl)> {
+        let clock = game.halfmove_clock as i32;
+        let mut best: Option<(Move, Wdl, i32)> = None;
+        for mv in game.legal_moves() {
+            let mut after = *game;
+            after.play_unchecked(mv).ok()?;
+
+            // DTZ for the move counted from the current position
+            let mut dtz = if after.halfmove_clock == 0 {
+                self.search(&after, false)?.0.negate().dtz_before_zeroing()
+            } else {
+                let dtz = -self.dtz(&after)?;
+                dtz + dtz.signum()
+            };
+            let mates = after.legal_moves().is_empty() && after.is_king_in_check(after.turn);
+            if mates {
+                dtz = 1;
+            }
+
+            // Wins inside the fifty moves first, then wins the rule spoils, draws, losses the
+            // rule saves and real losses. Ties go to the quickest win or the slowest loss.
+            let wdl = match dtz {
+                1.. if dtz + clock <= 100 => Wdl::Win,
+                1.. => Wdl::CursedWin,
+                0 => Wdl::Draw,
+                _ if clock - dtz > 100 => Wdl::BlessedLoss,
+                _ => Wdl::Loss,
+            };
+            let rank = (wdl, -dtz);
+            if best.is_none_or(|(_, best_wdl, best_dtz)| rank > (best_wdl, -best_dtz)) {
+                best = Some((mv, wdl, dtz));
+            }
+        }
+        best.map(|(mv, wdl, _)| (mv, wdl))
+    }
+
+    /// Keeps the best WDL result, preferring mates and then moves that reset the fifty move counter
+    fn best_move_by_wdl<V: Variant>(&self, game: &Game<V>) -> Option<(Move, Wdl)> {
+        let mut best: Option<(Move, (Wdl, bool, bool))> = None;
+        for mv in game.legal_moves() {
+            let mut after = *game;
+            after.play_unchecked(mv).ok()?;
+            let mates = after.legal_moves().is_empty() && after.is_king_in_check(after.turn);
+            let wdl = if mates { Wdl::Win } else { self.search(&after, false)?.0.negate() };
+            let rank = (wdl, mates, after.halfmove_clock == 0);
+            if best.is_none_or(|(_, best_rank)| rank > best_rank) {
+                best = Some((mv, rank));
+            }
+        }
+        best.map(|(mv, (wdl, _, _))| (mv, wdl))
+    }
+
+    /// Looks for captures (and pawn moves when `zeroing` is set) that settle the
+    /// result before reading the table, as tables skip positions where they matter
+    fn search<V: Variant>(&self, game: &Game<V>, zeroing: bool) -> Option<(Wdl, Probe)> {
+        let moves = game.legal_moves();
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+        for &mv in &moves {
+            let is_pawn_move = matches!(game.board.get(mv.start).map(|piece| piece.piece_type), Some(PieceType::Pawn(_)));
+            if !(game.is_capture(mv) || zeroing && is_pawn_move) {
+                continue;
+            }
+            searched += 1;
+
+            let mut after = *game;
+            after.play_unchecked(mv).ok()?;
+            let value = self.search(&after, false)?.0.negate();
+            if value > best {
+                best = value;
+                if value == Wdl::Win {
+                    return Some((value, Probe::ZeroingBestMove));
+                }
+            }
+        }
+
+        // With every move searched the table is not needed, and it could even be wrong
+        // (tables know nothing of en passant)
+        let no_more_moves = searched > 0 && searched == moves.len();
+        let value = if no_more_moves {
+            best
+        } else if moves.is_empty() {
+            return Some((if game.is_king_in_check(game.turn) { Wdl::Loss } else { Wdl::Draw }, Probe::Ok));
+        } else {
+            Wdl::from_value(self.probe_table(game, TableKind::Wdl, Wdl::Draw)?.0)
+        };
+
+        if best >= value {
+            let probe = if best > Wdl::Draw || no_more_moves { Probe::ZeroingBestMove } else { Probe::Ok };
+            return Some((best, probe));
+        }
+        Some((value, Probe::Ok))
+    }
+
+    fn dtz<V: Variant>(&self, game: &Game<V>) -> Option<i32> {
+        let (wdl, probe) = self.search(game, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if probe == Probe::ZeroingBestMove {
+            return Some(wdl.dtz_before_zeroing());
+        }
+
+        let (dtz, probe) = self.probe_table(game, TableKind::Dtz, wdl)?;
+        if probe != Probe::ChangeStm {
+            let cursed = matches!(wdl, Wdl::BlessedLoss | Wdl::CursedWin);
+            return Some((dtz + if cursed { 100 } else { 0 }) * (wdl as i32).signum());
+        }
+
+        // The table only has the other side to move, so look one ply ahead
+        let mut min_dtz = i32::MAX;
+        for mv in game.legal_moves() {
+            let is_pawn_move = matches!(game.board.get(mv.start).map(|piece| piece.piece_type), Some(PieceType::Pawn(_)));
+            let zeroing = game.is_capture(mv) || is_pawn_move;
+            let mut after = *game;
+            after.play_unchecked(mv).ok()?;
+
+            let mut dtz = if zeroing {
+                -self.search(&after, false)?.0.dtz_before_zeroing()
+            } else {
+                -self.dtz(&after)?
+            };
+            if dtz == 1 && after.is_king_in_check(after.turn) && after.legal_moves().is_empty() {
+                min_dtz = 1;
+            }
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+            if dtz < min_dtz && dtz.signum() == (wdl as i32).signum() {
+                min_dtz = dtz;
+            }
+        }
+        Some(if min_dtz == i32::MAX { -1 } else { min_dtz })
+    }
+
+    /// Reads the stored value for the position: a WDL value in -2..=2 or a DTZ in plies
+    fn probe_table<V: Variant>(&self, game: &Game<V>, kind: TableKind, wdl: Wdl) -> Option<(i32, Probe)> {
+        let white = material_name(game, PieceColor::White);
+        let black = material_name(game, PieceColor::Black);
+        if white == "K" && black == "K" {
+            return Some((0, Probe::Ok));
+        }
+
+        // Tables are stored with the stronger side as White, e.g. KRvK but not KvKR
+        let (name, black_stronger) = if self.available.contains(&format!("{white}v{black}")) {
+            (format!("{white}v{black}"), false)
+        } else {
+            (format!("{black}v{white}"), true)
+        };
+        let table = self.table(&name, kind)?;
+
+        let mut pieces = Vec::new();
+        for piece in game.board.board.iter().flatten().flatten() {
+            let square = (7 - piece.coordinates.0) * 8 + piece.coordinates.1;
+            let role = match piece.piece_type {
+                PieceType::Pawn(_) => 1,
+                PieceType::Knight => 2,
+                PieceType::Bishop => 3,
+                PieceType::Rook(_) => 4,
+                PieceType::Queen => 5,
+                PieceType::King(_) => 6,
+            };
+            let color = if piece.color == PieceColor::White { 0 } else { 8 };
+            pieces.push((square, role | color));
+        }
+        pieces.sort_unstable();
+
+        // Symmetric tables only store White to move, so Black to move is flipped too
+        let flip = black_stronger || (table.symmetric && game.turn == PieceColor::Black);
+        let stm = (flip as usize) ^ (game.turn == PieceColor::Black) as usize;
+        table.probe(&pieces, flip, stm, wdl)
+    }
+
+    fn table(&self, name: &str, kind: TableKind) -> Option<Arc<Table>> {
+        let (tables, extension, magic) = match kind {
+            TableKind::Wdl => (&self.wdl_tables, "rtbw", WDL_MAGIC),
+            TableKind::Dtz => (&self.dtz_tables, "rtbz", DTZ_MAGIC),
+        };
+        let mut tables = tables.lock().ok()?;
+        tables
+            .entry(name.to_owned())
+            .or_insert_with(|| {
+                let bytes = self
+                    .directories
+                    .iter()
+                    .find_map(|directory| fs::read(directory.join(format!("{name}.{extension}"))).ok())?;
+                if bytes.get(0..4)? != magic {
+                    return None;
+                }
+                Table::new(name, kind, bytes).map(Arc::new)
+            })
+            .clone()
+    }
+}
+
+// Custom Table Type Data
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+/// One decoded table file. Offsets point into `bytes`, which holds the whole file.
+struct Table {
+    kind: TableKind,
+    bytes: Vec<u8>,
+    piece_count: usize,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    /// Pawns of the leading color, then of the other color
+    pawn_count: [usize; 2],
+    symmetric: bool,
+    /// Compressed data, indexed by side to move then by the leading pawn's file
+    pairs: Vec<[Pairs; 4]>,
+    /// Start of the DTZ value maps
+    map: usize,
+}
+
+#[derive(Debug, Default, Clone)]
+struct Pairs {
+    flags: u8,
+    block_size: usize,
+    span: u64,
+    blocks: usize,
+    block_length_size: usize,
+    min_sym_len: u8,
+    lowest_sym: usize,
+    btree: usize,
+    block_length: usize,
+    sparse_index: usize,
+    sparse_index_size: usize,
+    data: usize,
+    base64: Vec<u64>,
+    symlen: Vec<u8>,
+    pieces: [u8; MAX_PIECES],
+    group_idx: [u64; MAX_PIECES + 1],
+    group_len: [usize; MAX_PIECES + 1],
+    map_idx: [u16; 4],
+}
+
+// Impl Table
+
+impl Table {
+    /// Reads the table layout following the Syzygy file format
+    fn new(name: &str, kind: TableKind, bytes: Vec<u8>) -> Option<Table> {
+        let (white, black) = name.split_once('v')?;
+        let count = |side: &str, letter: char| side.chars().filter(|&c| c == letter).count();
+        let (white_pawns, black_pawns) = (count(white, 'P'), count(black, 'P'));
+        let has_unique_pieces = [white, black]
+            .iter()
+            .any(|side| "QRBNP".chars().any(|letter| count(side, letter) == 1));
+        // The side with fewer (but some) pawns leads, which compresses better
+        let white_leads = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+
+        let mut table = Table {
+            kind,
+            bytes,
+            piece_count: white.len() + black.len(),
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces,
+            pawn_count: if white_leads { [white_pawns, black_pawns] } else { [black_pawns, white_pawns] },
+            symmetric: white == black,
+            pairs: Vec::new(),
+            map: 0,
+        };
+        if table.piece_count > MAX_PIECES || table.piece_count < 3 {
+            return None;
+        }
+        table.read_layout()?;
+        Some(table)
+    }
+
+    fn read_layout(&mut self) -> Option<()> {
+        let flags = *self.bytes.get(4)?;
+        if (flags & HAS_PAWNS != 0) != self.has_pawns || (flags & SPLIT != 0) == self.symmetric {
+            return None;
+        }
+
+        let sides = if self.kind == TableKind::Wdl && !self.symmetric { 2 } else { 1 };
+        let files = if self.has_pawns { 4 } else { 1 };
+        let both_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        self.pairs = vec![Default::default(); sides];
+
+        let mut at = 5;
+        for file in 0..files {
+            let first = *self.bytes.get(at)?;
+            let second = if both_pawns { *self.bytes.get(at + 1)? } else { 0xff };
+            let order = [[first & 0xf, second & 0xf], [first >> 4, second >> 4]];
+            at += 1 + both_pawns as usize;
+
+            for k in 0..self.piece_count {
+                let byte = *self.bytes.get(at)?;
+                for side in 0..sides {
+                    self.pairs[side][file].pieces[k] = if side == 0 { byte & 0xf } else { byte >> 4 };
+                }
+                at += 1;
+            }
+            for (side, &order) in order.iter().enumerate().take(sides) {
+                self.set_groups(side, file, order);
+            }
+        }
+        at += at & 1;
+
+        for file in 0..files {
+            for side in 0..sides {
+                at = self.set_sizes(side, file, at)?;
+            }
+        }
+        if self.kind == TableKind::Dtz {
+            at = self.set_dtz_map(at, files)?;
+        }
+        for file in 0..files {
+            for side in 0..sides {
+                let pairs = &mut self.pairs[side][file];
+                pairs.sparse_index = at;
+                at += pairs.sparse_index_size * 6;
+            }
+        }
+        for file in 0..files {
+            for side in 0..sides {
+                let pairs = &mut self.pairs[side][file];
+                pairs.block_length = at;
+                at += pairs.block_length_size * 2;
+            }
+        }
+        // Single valued tables have no blocks, and the file may end before the alignment
+        let mut end = at;
+        for file in 0..files {
+            for side in 0..sides {
+                at = (at + 0x3f) & !0x3f;
+                let pairs = &mut self.pairs[side][file];
+                pairs.data = at;
+                at += pairs.blocks * pairs.block_size;
+                if pairs.blocks > 0 {
+                    end = at;
+                }
+            }
+        }
+        (end <= self.bytes.len()).then_some(())
+    }
+
+    /// Splits the pieces into groups of identical pieces and works out each group's
+    /// multiplier in the position index
+    fn set_groups(&mut self, side: usize, file: usize, order: [u8; 2]) {
+        let maps = maps();
+        let (has_pawns, has_unique_pieces, piece_count) = (self.has_pawns, self.has_unique_pieces, self.piece_count);
+        let both_pawns = has_pawns && self.pawn_count[1] > 0;
+        let pairs = &mut self.pairs[side][file];
+
+        let mut n = 0;
+        let mut first_len: i32 = if has_pawns { 0 } else if has_unique_pieces { 3 } else { 2 };
+        pairs.group_len[0] = 1;
+        for i in 1..piece_count {
+            first_len -= 1;
+            if first_len > 0 || pairs.pieces[i] == pairs.pieces[i - 1] {
+                pairs.group_len[n] += 1;
+            } else {
+                n += 1;
+                pairs.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        pairs.group_len[n] = 0;
+
+        let mut next = if both_pawns { 2 } else { 1 };
+        let mut free_squares = 64 - pairs.group_len[0] - if both_pawns { pairs.group_len[1] } else { 0 };
+        let mut idx: u64 = 1;
+        let mut k = 0;
+        while next < n || k == order[0] as usize || k == order[1] as usize {
+            if k == order[0] as usize {
+                pairs.group_idx[0] = idx;
+                idx *= if has_pawns {
+                    maps.lead_pawns_size[pairs.group_len[0]][file]
+                } else if has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] as usize {
+                pairs.group_idx[1] = idx;
+                idx *= maps.binomial[pairs.group_len[1]][48 - pairs.group_len[0]];
+            } else {
+                pairs.group_idx[next] = idx;
+                idx *= maps.binomial[pairs.group_len[next]][free_squares];
+                free_squares -= pairs.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        pairs.group_idx[n] = idx;
+    }
+
+    /// Reads the block sizes and the canonical Huffman code of one compressed table
+    fn set_sizes(&mut self, side: usize, file: usize, mut at: usize) -> Option<usize> {
+        let bytes = &self.bytes;
+        let pairs = &mut self.pairs[side][file];
+        pairs.flags = *bytes.get(at)?;
+        at += 1;
+        if pairs.flags & SINGLE_VALUE != 0 {
+            // The one value every position has
+            pairs.min_sym_len = *bytes.get(at)?;
+            return Some(at + 1);
+        }
+
+        let end = pairs.group_len.iter().position(|&len| len == 0).unwrap_or(MAX_PIECES);
+        let table_size = pairs.group_idx[end];
+        pairs.block_size = 1 << *bytes.get(at)?;
+        pairs.span = 1 << *bytes.get(at + 1)?;
+        pairs.sparse_index_size = table_size.div_ceil(pairs.span) as usize;
+        let padding = *bytes.get(at + 2)? as usize;
+        pairs.blocks = read_u32_le(bytes, at + 3)? as usize;
+        pairs.block_length_size = pairs.blocks + padding;
+        let max_sym_len = *bytes.get(at + 7)?;
+        pairs.min_sym_len = *bytes.get(at + 8)?;
+        at += 9;
+        if max_sym_len < pairs.min_sym_len {
+            return None;
+        }
+
+        // Lowest symbol of each code length, longest codes having the lowest values
+        pairs.lowest_sym = at;
+        let lengths = (max_sym_len - pairs.min_sym_len + 1) as usize;
+        pairs.base64 = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            let lowest = read_u16_le(bytes, at + 2 * i)? as u64;
+            let next_lowest = read_u16_le(bytes, at + 2 * (i + 1))? as u64;
+            pairs.base64[i] = pairs.base64[i + 1].wrapping_add(lowest).wrapping_sub(next_lowest) / 2;
+        }
+        for (i, base) in pairs.base64.iter_mut().enumerate() {
+            *base = base.checked_shl(64 - i as u32 - pairs.min_sym_len as u32).unwrap_or(0);
+        }
+        at += lengths * 2;
+
+        // Each symbol expands into a pair of symbols, down to single values
+        let symbols = read_u16_le(bytes, at)? as usize;
+        at += 2;
+        pairs.btree = at;
+        if at + symbols * 3 > bytes.len() {
+            return None;
+        }
+        pairs.symlen = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for symbol in 0..symbols {
+            if !visited[symbol] {
+                let len = set_symlen(bytes, pairs, symbol, &mut visited)?;
+                pairs.symlen[symbol] = len;
+            }
+        }
+        Some(at + symbols * 3 + (symbols & 1))
+    }
+
+    fn set_dtz_map(&mut self, mut at: usize, files: usize) -> Option<usize> {
+        self.map = at;
+        for file in 0..files {
+            let flags = self.pairs[0][file].flags;
+            if flags & MAPPED == 0 {
+                continue;
+            }
+            if flags & WIDE != 0 {
+                at += at & 1;
+                for i in 0..4 {
+                    self.pairs[0][file].map_idx[i] = ((at - self.map) / 2 + 1) as u16;
+                    at += 2 * read_u16_le(&self.bytes, at)? as usize + 2;
+                }
+            } else {
+                for i in 0..4 {
+                    self.pairs[0][file].map_idx[i] = (at - self.map + 1) as u16;
+                    at += *self.bytes.get(at)? as usize + 1;
+                }
+            }
+        }
+        Some(at + (at & 1))
+    }
+
+    /// Reads the value stored for the pieces (square a1 = 0, piece code).
+    /// `flip` swaps colors and mirrors ranks so that the table's stronger side is White.
+    fn probe(&self, pieces: &[(u8, u8)], flip: bool, stm: usize, wdl: Wdl) -> Option<(i32, Probe)> {
+        let (side, file, idx) = self.index(pieces, flip, stm)?;
+        let pairs = &self.pairs[side][file];
+        let both_sides_stored = self.symmetric && !self.has_pawns;
+        if self.kind == TableKind::Dtz && (pairs.flags & STM) as usize != stm && !both_sides_stored {
+            return Some((0, Probe::ChangeStm));
+        }
+
+        let value = self.decompress(pairs, idx)?;
+        Some((self.map_score(file, value, wdl)?, Probe::Ok))
+    }
+
+    /// Maps the pieces to the sub table (side to move, leading pawn file) they are
+    /// stored in and their index within it
+    fn index(&self, pieces: &[(u8, u8)], flip: bool, stm: usize) -> Option<(usize, usize, u64)> {
+        let maps = maps();
+        let (flip_color, flip_squares) = if flip { (8, 56) } else { (0, 0) };
+        let mut squares = Vec::with_capacity(pieces.len());
+        let mut codes = Vec::with_capacity(pieces.len());
+        let mut lead_pawns = 0;
+        let mut file = 0;
+
+        if self.has_pawns {
+            // Every sub table lists the leading pawns first, in the reference color
+            let lead_code = self.pairs[0][0].pieces[0] ^ flip_color;
+            for &(square, code) in pieces.iter().filter(|&&(_, code)| code == lead_code) {
+                squares.push(square ^ flip_squares);
+                codes.push(code ^ flip_color);
+            }
+            lead_pawns = squares.len();
+            let lead = (0..lead_pawns).max_by_key(|&i| (maps.map_pawns[squares[i] as usize], usize::MAX - i))?;
+            squares.swap(0, lead);
+            file = (squares[0] & 7) as usize;
+            file = file.min(7 - file);
+        }
+
+        let side = stm % self.pairs.len();
+        let pairs = &self.pairs[side][file];
+
+        for &(square, code) in pieces {
+            if self.has_pawns && code == self.pairs[0][0].pieces[0] ^ flip_color {
+                continue;
+            }
+            squares.push(square ^ flip_squares);
+            codes.push(code ^ flip_color);
+        }
+        let size = squares.len();
+        if size != self.piece_count {
+            return None;
+        }
+
+        // Order the pieces the way the table lists them
+        for i in lead_pawns..size.saturating_sub(1) {
+            if let Some(j) = (i + 1..size).find(|&j| pairs.pieces[i] == codes[j]) {
+                codes.swap(i, j);
+                squares.swap(i, j);
+            }
+        }
+
+        // The lead piece goes on the queenside, and for pawnless tables into a1-d1-d4
+        if squares[0] & 7 > 3 {
+            squares.iter_mut().for_each(|square| *square ^= 7);
+        }
+
+        let mut idx: u64;
+        if self.has_pawns {
+            idx = maps.lead_pawn_idx[lead_pawns][squares[0] as usize];
+            squares[1..lead_pawns].sort_by_key(|&square| maps.map_pawns[square as usize]);
+            for (i, &square) in squares.iter().enumerate().take(lead_pawns).skip(1) {
+                idx += maps.binomial[i][maps.map_pawns[square as usize]];
+            }
+        } else {
+            if squares[0] >> 3 > 3 {
+                squares.iter_mut().for_each(|square| *square ^= 56);
+            }
+            for i in 0..pairs.group_len[0] {
+                match off_diagonal(squares[i]) {
+                    0 => continue,
+                    1.. => squares[i..].iter_mut().for_each(|square| *square = ((*square >> 3) | (*square << 3)) & 63),
+                    _ => {},
+                }
+                break;
+            }
+
+            if self.has_unique_pieces {
+                let adjust1 = (squares[1] > squares[0]) as u64;
+                let adjust2 = (squares[2] > squares[0]) as u64 + (squares[2] > squares[1]) as u64;
+                let (s0, s1, s2) = (squares[0] as u64, squares[1] as u64, squares[2] as u64);
+                idx = if off_diagonal(squares[0]) != 0 {
+                    (maps.map_a1d1d4[s0 as usize] * 63 + (s1 - adjust1)) * 62 + s2 - adjust2
+                } else if off_diagonal(squares[1]) != 0 {
+                    (6 * 63 + (s0 >> 3) * 28 + maps.map_b1h1h7[s1 as usize]) * 62 + s2 - adjust2
+                } else if off_diagonal(squares[2]) != 0 {
+                    6 * 63 * 62 + 4 * 28 * 62 + (s0 >> 3) * 7 * 28 + ((s1 >> 3) - adjust1) * 28 + maps.map_b1h1h7[s2 as usize]
+                } else {
+                    6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + (s0 >> 3) * 7 * 6 + ((s1 >> 3) - adjust1) * 6 + ((s2 >> 3) - adjust2)
+                };
+            } else {
+                idx = maps.map_kk[maps.map_a1d1d4[squares[0] as usize] as usize][squares[1] as usize];
+            }
+        }
+
+        // The remaining groups, each placed on the squares the earlier groups left free
+        idx *= pairs.group_idx[0];
+        let mut start = pairs.group_len[0];
+        let mut remaining_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = 1;
+        while pairs.group_len[next] != 0 {
+            let len = pairs.group_len[next];
+            squares[start..start + len].sort_unstable();
+            let mut n = 0;
+            for i in 0..len {
+                let square = squares[start + i];
+                let adjust = squares[..start].iter().filter(|&&earlier| square > earlier).count();
+                n += maps.binomial[i + 1][square as usize - adjust - 8 * remaining_pawns as usize];
+            }
+            remaining_pawns = false;
+            idx += n * pairs.group_idx[next];
+            start += len;
+            next += 1;
+        }
+        Some((side, file, idx))
+    }
+
+    /// Finds the value stored for index `idx`: locate the block with the sparse index,
+    /// walk its Huffman coded symbols, then expand the symbol down to a single value
+    fn decompress(&self, pairs: &Pairs, idx: u64) -> Option<i32> {
+        if pairs.flags & SINGLE_VALUE != 0 {
+            return Some(pairs.min_sym_len as i32);
+        }
+        let bytes = &self.bytes;
+
+        let k = (idx / pairs.span) as usize;
+        if k >= pairs.sparse_index_size {
+            return None;
+        }
+        let entry = pairs.sparse_index + 6 * k;
+        let mut block = read_u32_le(bytes, entry)? as usize;
+        let mut offset = read_u16_le(bytes, entry + 4)? as i64;
+        offset += (idx % pairs.span) as i64 - (pairs.span / 2) as i64;
+
+        let block_length = |block: usize| read_u16_le(bytes, pairs.block_length + 2 * block).map(|len| len as i64);
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += block_length(block)? + 1;
+        }
+        while offset > block_length(block)? {
+            offset -= block_length(block)? + 1;
+            block += 1;
+        }
+
+        let mut ptr = pairs.data + block * pairs.block_size;
+        let mut buf64 = read_u64_be(bytes, ptr)?;
+        ptr += 8;
+        let mut buf64_size = 64;
+        let min_sym_len = pairs.min_sym_len as usize;
+        let mut symbol;
+        loop {
+            let mut len = 0;
+            while buf64 < pairs.base64[len] {
+                len += 1;
+                if len >= pairs.base64.len() {
+                    return None;
+                }
+            }
+            // Symbols of one length are consecutive, counting up from the lowest
+            let code = (buf64 - pairs.base64[len]).checked_shr((64 - len - min_sym_len) as u32).unwrap_or(0);
+            symbol = (code as u16).wrapping_add(read_u16_le(bytes, pairs.lowest_sym + 2 * len)?) as usize;
+            let expands = *pairs.symlen.get(symbol)? as i64 + 1;
+            if offset < expands {
+                break;
+            }
+            offset -= expands;
+            len += min_sym_len;
+            buf64 <<= len;
+            buf64_size -= len;
+            if buf64_size <= 32 {
+                buf64_size += 32;
+                buf64 |= (read_u32_be(bytes, ptr)? as u64) << (64 - buf64_size);
+                ptr += 4;
+            }
+        }
+
+        while pairs.symlen[symbol] != 0 {
+            let (left, right) = read_pair(bytes, pairs.btree, symbol)?;
+            let left_expands = pairs.symlen[left] as i64 + 1;
+            if offset < left_expands {
+                symbol = left;
+            } else {
+                offset -= left_expands;
+                symbol = right;
+            }
+        }
+        Some(read_pair(bytes, pairs.btree, symbol)?.0 as i32)
+    }
+
+    /// Turns a stored value into a WDL value or a DTZ in plies
+    fn map_score(&self, file: usize, value: i32, wdl: Wdl) -> Option<i32> {
+        if self.kind == TableKind::Wdl {
+            return Some(value - 2);
+        }
+
+        let pairs = &self.pairs[0][file];
+        let mut value = value;
+        if pairs.flags & MAPPED != 0 {
+            let map_index = match wdl {
+                Wdl::Win | Wdl::Draw => 0,
+                Wdl::Loss => 1,
+                Wdl::CursedWin => 2,
+                Wdl::BlessedLoss => 3,
+            };
+            let index = pairs.map_idx[map_index] as usize + value as usize;
+            value = if pairs.flags & WIDE != 0 {
+                read_u16_le(&self.bytes, self.map + 2 * index)? as i32
+            } else {
+                *self.bytes.get(self.map + index)? as i32
+            };
+        }
+
+        // Values are stored in moves unless the table says plies
+        let in_moves = match wdl {
+            Wdl::Win => pairs.flags & WIN_PLIES == 0,
+            Wdl::Loss => pairs.flags & LOSS_PLIES == 0,
+            Wdl::CursedWin | Wdl::BlessedLoss => true,
+            Wdl::Draw => false,
+        };
+        if in_moves {
+            value *= 2;
+        }
+        Some(value + 1)
+    }
+}
+
+// Custom Maps Type Data
+
+/// Square numbering tables the index encoding is built from, shared by every table
+struct Maps {
+    /// Squares below the a1-h8 diagonal to 0..28
+    map_b1h1h7: [u64; 64],
+    /// Squares of the a1-d1-d4 triangle to 0..10, the diagonal last
+    map_a1d1d4: [u64; 64],
+    /// The 462 legal king pairs with the first king in a1-d1-d4
+    map_kk: [[u64; 64]; 10],
+    /// Squares a2-h7 to 0..48, highest for the pawn that leads
+    map_pawns: [usize; 64],
+    /// `binomial[k][n]` ways to choose k squares out of n
+    binomial: [[u64; 64]; MAX_PIECES],
+    lead_pawn_idx: [[u64; 64]; MAX_PIECES],
+    lead_pawns_size: [[u64; 4]; MAX_PIECES],
+}
+
+// Functions
+
+fn maps() -> &'static Maps {
+    static MAPS: OnceLock<Maps> = OnceLock::new();
+    MAPS.get_or_init(|| {
+        let mut maps = Maps {
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+            map_pawns: [0; 64],
+            binomial: [[0; 64]; MAX_PIECES],
+            lead_pawn_idx: [[0; 64]; MAX_PIECES],
+            lead_pawns_size: [[0; 4]; MAX_PIECES],
+        };
+
+        let mut code = 0;
+        for square in 0..64u8 {
+            if off_diagonal(square) < 0 {
+                maps.map_b1h1h7[square as usize] = code;
+                code += 1;
+            }
+        }
+
+        let mut diagonal = Vec::new();
+        code = 0;
+        for square in 0..=27u8 {
+            if square & 7 > 3 {
+                continue;
+            }
+            if off_diagonal(square) < 0 {
+                maps.map_a1d1d4[square as usize] = code;
+                code += 1;
+            } else if off_diagonal(square) == 0 {
+                diagonal.push(square);
+            }
+        }
+        for square in diagonal {
+            maps.map_a1d1d4[square as usize] = code;
+            code += 1;
+        }
+
+        let mut both_on_diagonal = Vec::new();
+        code = 0;
+        for idx in 0..10 {
+            for first in 0..=27u8 {
+                // b1 is the only square of the triangle numbered 0
+                if first & 7 > 3 || maps.map_a1d1d4[first as usize] != idx || (idx == 0 && first != 1) {
+                    continue;
+                }
+                for second in 0..64u8 {
+                    let rank_gap = (first >> 3).abs_diff(second >> 3);
+                    let file_gap = (first & 7).abs_diff(second & 7);
+                    if rank_gap <= 1 && file_gap <= 1 {
+                        continue;
+                    }
+                    if off_diagonal(first) == 0 && off_diagonal(second) > 0 {
+                        continue;
+                    }
+                    if off_diagonal(first) == 0 && off_diagonal(second) == 0 {
+                        both_on_diagonal.push((idx, second));
+                    } else {
+                        maps.map_kk[idx as usize][second as usize] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, second) in both_on_diagonal {
+            maps.map_kk[idx as usize][second as usize] = code;
+            code += 1;
+        }
+
+        maps.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..MAX_PIECES.min(n + 1) {
+                let with = if k > 0 { maps.binomial[k - 1][n - 1] } else { 0 };
+                let without = if k < n { maps.binomial[k][n - 1] } else { 0 };
+                maps.binomial[k][n] = with + without;
+            }
+        }
+
+        let mut available = 47;
+        for lead_pawns in 1..MAX_PIECES - 1 {
+            for file in 0..4u8 {
+                let mut idx = 0;
+                for rank in 1..7u8 {
+                    let square = (rank * 8 + file) as usize;
+                    if lead_pawns == 1 {
+                        maps.map_pawns[square] = available;
+                        maps.map_pawns[square ^ 7] = available - 1;
+                        available = available.saturating_sub(2);
+                    }
+                    maps.lead_pawn_idx[lead_pawns][square] = idx;
+                    idx += maps.binomial[lead_pawns - 1][maps.map_pawns[square]];
+                }
+                maps.lead_pawns_size[lead_pawns][file as usize] = idx;
+            }
+        }
+        maps
+    })
+}
+
+/// Rank minus file: zero on the a1-h8 diagonal, negative below it
+fn off_diagonal(square: u8) -> i32 {
+    (square >> 3) as i32 - (square & 7) as i32
+}
+
+fn set_symlen(bytes: &[u8], pairs: &mut Pairs, symbol: usize, visited: &mut [bool]) -> Option<u8> {
+    visited[symbol] = true;
+    let (left, right) = read_pair(bytes, pairs.btree, symbol)?;
+    if right == 0xfff {
+        return Some(0);
+    }
+    if left >= visited.len() || right >= visited.len() {
+        return None;
+    }
+    if !visited[left] {
+        pairs.symlen[left] = set_symlen(bytes, pairs, left, visited)?;
+    }
+    if !visited[right] {
+        pairs.symlen[right] = set_symlen(bytes, pairs, right, visited)?;
+    }
+    Some((pairs.symlen[left] as u32 + pairs.symlen[right] as u32 + 1) as u8)
+}
+
+/// The two 12 bit symbols a symbol expands into
+fn read_pair(bytes: &[u8], btree: usize, symbol: usize) -> Option<(usize, usize)> {
+    let pair = bytes.get(btree + 3 * symbol..btree + 3 * symbol + 3)?;
+    let left = (((pair[1] & 0xf) as usize) << 8) | pair[0] as usize;
+    let right = ((pair[2] as usize) << 4) | (pair[1] >> 4) as usize;
+    Some((left, right))
+}
+
+fn read_u16_le(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u32_le(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn read_u32_be(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn read_u64_be(bytes: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(bytes.get(at..at + 8)?.try_into().ok()?))
+}
+
+/// Material of one side the way table names write it, e.g. "KRP"
+fn material_name<V: Variant>(game: &Game<V>, color: PieceColor) -> String {
+    let mut name = String::new();
+    for (letter, is_kind) in NAME_ORDER {
+        let count = game.pieces(color).filter(|piece| is_kind(piece.piece_type)).count();
+        name.extend(std::iter::repeat_n(letter, count));
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/syzygy");
+
+    fn position(fen: &str) -> Game {
+        Game::from_fen(fen).unwrap()
+    }
+
+    #[test]
+    fn probes_known_positions() {
+        let tablebase = Tablebase::open(TABLES).unwrap();
+        let cases = [
+            // Mate in one, the mated side, stalemate and a hanging queen
+            ("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1", Wdl::Win, 1),
+            ("k6Q/8/1K6/8/8/8/8/8 b - - 0 1", Wdl::Loss, -1),
+            ("k7/8/1Q6/8/8/8/8/K7 b - - 0 1", Wdl::Draw, 0),
+            ("8/8/8/8/8/8/1Q6/k6K b - - 0 1", Wdl::Draw, 0),
+            // The longest wins: mate in 10 with the queen, mate in 16 with the rook
+            ("8/8/4k3/8/8/8/1Q6/K7 w - - 0 1", Wdl::Win, 19),
+            ("8/8/8/8/8/2k5/1R6/K7 w - - 0 1", Wdl::Win, 31),
+            ("8/8/8/8/8/2k5/1R6/K7 b - - 0 1", Wdl::Loss, -32),
+            // The same with colors swapped, read from the KRvK table flipped
+            ("k7/1r6/2K5/8/8/8/8/8 b - - 0 1", Wdl::Win, 31),
+            // King and pawn: the king in front wins either way, the opposition decides
+            // one square back, and the rook pawn is a draw
+            ("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1", Wdl::Win, 3),
+            ("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1", Wdl::Loss, -4),
+            ("8/4k3/8/4K3/4P3/8/8/8 w - - 0 1", Wdl::Draw, 0),
+            ("8/4k3/8/4K3/4P3/8/8/8 b - - 0 1", Wdl::Loss, -4),
+            ("k7/8/8/8/8/8/P7/7K w - - 0 1", Wdl::Draw, 0),
+            ("7k/8/8/8/8/8/7P/K7 w - - 0 1", Wdl::Draw, 0),
+            // Promoting resets the count, so the DTZ is one
+            ("8/4P3/8/8/8/8/k7/4K3 w - - 0 1", Wdl::Win, 1),
+            ("8/8/8/k7/8/8/K4P2/8 w - - 0 1", Wdl::Win, 19),
+            // Queen against rook: the longest win, a rook mate, a skewer and a stalemate trap
+            ("8/8/8/8/Q7/5k2/8/K3r3 w - - 0 1", Wdl::Win, 61),
+            ("8/8/8/8/Q7/5k2/4r3/K7 b - - 0 1", Wdl::Loss, -62),
+            ("8/8/8/8/7Q/4r3/8/K1k5 b - - 0 1", Wdl::Win, 1),
+            ("8/8/8/r7/8/7Q/8/K1k5 w - - 0 1", Wdl::Loss, -2),
+            ("8/8/8/8/8/8/Q7/K4r1k w - - 0 1", Wdl::Draw, 0),
+        ];
+        for (fen, wdl, dtz) in cases {
+            let game = position(fen);
+            assert_eq!(tablebase.probe_wdl(&game), Some(wdl), "{fen}");
+            assert_eq!(tablebase.probe_dtz(&game), Some(dtz), "{fen}");
+        }
+    }
+
+    #[test]
+    fn best_move_keeps_the_win() {
+        let tablebase = Tablebase::open(TABLES).unwrap();
+        for fen in ["8/8/8/8/8/2k5/1R6/K7 w - - 0 1", "8/8/8/k7/8/8/K4P2/8 w - - 0 1"] {
+            let mut game = position(fen);
+            let mut plies = 0;
+            while !game.legal_moves().is_empty() {
+                let winner = game.turn == PieceColor::White;
+                let (mv, wdl) = tablebase.best_move(&game).unwrap();
+                assert_eq!(wdl, if winner { Wdl::Win } else { Wdl::Loss }, "{fen} after {plies} plies");
+                let dtz = tablebase.probe_dtz(&game).unwrap();
+                game.play(mv).unwrap();
+                plies += 1;
+
+                // Each move brings the next zeroing move one ply closer
+                if game.halfmove_clock > 0 && !game.legal_moves().is_empty() {
+                    assert_eq!(tablebase.probe_dtz(&game).unwrap(), -(dtz - dtz.signum()), "{fen} after {plies} plies");
+                }
+                assert!(plies < 100, "{fen} is not mated after 100 plies");
+            }
+            assert!(game.is_king_in_check(PieceColor::Black));
+        }
+    }
+
+    /// Whether the side to move mates within `moves` moves, found by trying everything
+    fn mates_within(game: &Game, moves: u32) -> bool {
+        game.legal_moves().into_iter().any(|mv| {
+            let mut after = *game;
+            after.play(mv).unwrap();
+            let replies = after.legal_moves();
+            if replies.is_empty() {
+                return after.is_king_in_check(after.turn);
+            }
+            moves > 1
+                && replies.into_iter().all(|reply| {
+                    let mut next = after;
+                    next.play(reply).unwrap();
+                    mates_within(&next, moves - 1)
+                })
+        })
+    }
+
+    #[test]
+    fn agrees_with_a_mate_search() {
+        // The tables are generated rather than copied from the official set, so check
+        // them against a plain search. Without pawns or anything to capture the DTZ
+        // is the distance to mate.
+        let tablebase = Tablebase::open(TABLES).unwrap();
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let (mut mates, mut rook_endings) = (0, 0);
+        for round in 0..4000 {
+            let pieces = if round % 100 == 0 { "KkQr" } else { ["KkQ", "KkR"][round % 2] };
+            let mut board = ['1'; 64];
+            for piece in pieces.chars() {
+                loop {
+                    seed ^= seed << 13;
+                    seed ^= seed >> 7;
+                    seed ^= seed << 17;
+                    let square = (seed % 64) as usize;
+                    if board[square] == '1' {
+                        board[square] = piece;
+                        break;
+                    }
+                }
+            }
+            let rows: Vec<String> = board.chunks(8).map(|row| row.iter().collect()).collect();
+            let turn = if seed & 1 == 0 { "w" } else { "b" };
+            let Ok(game) = Game::from_fen(&format!("{} {turn} - - 0 1", rows.join("/"))) else { continue };
+            if game.is_king_in_check(game.turn.swap()) {
+                continue;
+            }
+
+            if pieces.len() == 3 {
+                let dtz = tablebase.probe_dtz(&game).unwrap();
+                let fen = game.to_fen();
+                match dtz {
+                    0 | 4.. | ..=-4 => continue,
+                    1 => assert!(mates_within(&game, 1), "{fen}"),
+                    3 => assert!(mates_within(&game, 2) && !mates_within(&game, 1), "{fen}"),
+                    -2 | -1 => {
+                        for mv in game.legal_moves() {
+                            let mut after = game;
+                            after.play(mv).unwrap();
+                            assert!(mates_within(&after, 1), "{fen} {mv}");
+                        }
+                    },
+                    _ => panic!("{fen} has DTZ {dtz}"),
+                }
+                mates += 1;
+            } else {
+                // Captures also reset the count, so a mate only bounds the DTZ
+                let (wdl, dtz) = (tablebase.probe_wdl(&game).unwrap(), tablebase.probe_dtz(&game).unwrap());
+                if mates_within(&game, 2) {
+                    assert!(wdl == Wdl::Win && (1..=3).contains(&dtz), "{}", game.to_fen());
+                } else if wdl == Wdl::Win {
+                    assert!(dtz != 1 || game.legal_moves().into_iter().any(|mv| game.is_capture(mv)), "{}", game.to_fen());
+                }
+                rook_endings += 1;
+            }
+        }
+        assert!(mates > 50 && rook_endings > 20, "{mates} mates and {rook_endings} rook endings");
+    }
+
+    #[test]
+    fn missing_tables_are_errors() {
+        assert!(matches!(Tablebase::open("/no/such/directory"), Err(TablebaseError::Unreadable)));
+        let empty = concat!(env!("CARGO_MANIFEST_DIR"), "/books");
+        assert!(matches!(Tablebase::open(empty), Err(TablebaseError::NoTables)));
+
+        // A directory with the KQvK WDL table alone probes WDL only, and nothing else
+        let directory = TempDirectory(std::env::temp_dir().join(format!("rust_chess_syzygy_{}", std::process::id())));
+        let directory = &directory.0;
+        fs::create_dir_all(directory).unwrap();
+        fs::copy(format!("{TABLES}/KQvK.rtbw"), directory.join("KQvK.rtbw")).unwrap();
+        let tablebase = Tablebase::open(directory.to_str().unwrap()).unwrap();
+        let queen = position("8/8/4k3/8/8/8/1Q6/K7 w - - 0 1");
+        assert_eq!(tablebase.probe_wdl(&queen), Some(Wdl::Win));
+        assert_eq!(tablebase.probe_dtz(&queen), None);
+        assert_eq!(tablebase.probe_wdl(&position("8/8/8/8/8/2k5/1R6/K7 w - - 0 1")), None);
+        assert_eq!(tablebase.max_pieces(), 3);
+        assert_eq!(tablebase.probe_wdl(&position("8/8/8/8/8/2k5/1R6/KR6 w - - 0 1")), None);
+
+        // A file that is not a table is skipped like a missing one
+        fs::write(directory.join("KRvK.rtbw"), b"not a table").unwrap();
+        let tablebase = Tablebase::open(directory.to_str().unwrap()).unwrap();
+        assert_eq!(tablebase.probe_wdl(&position("8/8/8/8/8/2k5/1R6/K7 w - - 0 1")), None);
+    }
+
+    /// Removes the directory when dropped, so a failing test doesn't leave it behind
+    struct TempDirectory(PathBuf);
+
+    impl Drop for TempDirectory {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+}
@@ -0,0 +1,302 @@
+// Imports
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use crate::moves::*;
+use crate::piece::*;
+
+/// Transposition table size when none is configured, in megabytes
+pub const DEFAULT_HASH_MB: usize = 16;
+
+/// Set in every stored entry, so an all zero slot reads as empty
+const OCCUPIED: u64 = 1 << 63;
+
+// Custom Bound Type Data
+
+/// How a stored score relates to the true score of the position
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Bound {
+    Exact,
+    /// The search failed high: the true score is at least this
+    Lower,
+    /// The search failed low: the true score is at most this
+    Upper,
+}
+
+// Custom TtEntry Type Data
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TtEntry {
+    pub key: u64,
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub depth: u8,
+    pub bound: Bound,
+    /// Search generation the entry was written in
+    pub age: u8,
+}
+
+// Custom TranspositionTable Type Data
+
+/// Fixed size hash table of search results, shared by every search thread without locks.
+/// Each slot is two atomic words: the entry packed into one, and the key xor'ed with it in
+/// the other, so an entry torn by two threads writing at once fails the key check and is
+/// simply ignored.
+/// A slot is overwritten by deeper searches of other positions, or by anything once
+/// the entry is left over from an earlier search.
+pub struct TranspositionTable {
+    slots: Vec<[AtomicU64; 2]>,
+    age: AtomicU8,
+}
+
+// Custom TranspositionTable Type Traits
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new(DEFAULT_HASH_MB)
+    }
+}
+
+impl Debug for TranspositionTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TranspositionTable")
+            .field("slots", &self.slots.len())
+            .field("age", &self.age.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+// Impl TranspositionTable
+
+impl TranspositionTable {
+    pub fn new(megabytes: usize) -> TranspositionTable {
+        let slots = (megabytes * 1024 * 1024 / size_of::<[AtomicU64; 2]>()).max(1);
+        TranspositionTable {
+            slots: (0..slots).map(|_| [AtomicU64::new(0), AtomicU64::new(0)]).collect(),
+            age: AtomicU8::new(0),
+        }
+    }
+
+    /// Empties the table. Must not run while a search is using it.
+    pub fn clear(&self) {
+        for [check, data] in &self.slots {
+            check.store(0, Ordering::Relaxed);
+            data.store(0, Ordering::Relaxed);
+        }
+        self.age.store(0, Ordering::Relaxed);
+    }
+
+    /// Marks the start of a new search, so older entries give way to new ones
+    pub fn new_search(&self) {
+        self.age.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn probe(&self, key: u64) -> Option<TtEntry> {
+        let [check, data] = &self.slots[self.slot(key)];
+        let data = data.load(Ordering::Relaxed);
+        if data & OCCUPIED == 0 || check.load(Ordering::Relaxed) ^ data != key {
+            return None;
+        }
+        Some(unpack_entry(key, data))
+    }
+
+    pub fn store(&self, key: u64, best_move: Option<Move>, score: i32, depth: u8, bound: Bound) {
+        let age = self.age.load(Ordering::Relaxed);
+        let old = self.probe(key);
+        if old.is_none() {
+            // A different position may sit in the slot, only an old or shallower one gives way
+            let data = self.slots[self.slot(key)][1].load(Ordering::Relaxed);
+            if data & OCCUPIED != 0 {
+                let other = unpack_entry(0, data);
+                if other.age == age && depth < other.depth {
+                    return;
+                }
+            }
+        }
+
+        // Keep the old move when a shallower search of the same position found none
+        let best_move = best_move.or_else(|| old.and_then(|old| old.best_move));
+        let data = pack_entry(TtEntry { key, best_move, score, depth, bound, age });
+        let [check, slot_data] = &self.slots[self.slot(key)];
+        slot_data.store(data, Ordering::Relaxed);
+        check.store(key ^ data, Ordering::Relaxed);
+    }
+
+    /// How full the table is with entries from the current search, in permille as UCI reports it
+    pub fn hashfull(&self) -> usize {
+        let age = self.age.load(Ordering::Relaxed);
+        let sample = self.slots.len().min(1000);
+        let used = self.slots[..sample]
+            .iter()
+            .map(|[_, data]| data.load(Ordering::Relaxed))
+            .filter(|&data| data & OCCUPIED != 0 && unpack_entry(0, data).age == age)
+            .count();
+        used * 1000 / sample
+    }
+
+    fn slot(&self, key: u64) -> usize {
+        // Multiply-shift maps the key onto the table without needing a power of two size
+        ((key as u128 * self.slots.len() as u128) >> 64) as usize
+    }
+}
+
+// Functions
+
+/// Packs an entry into one word: move (20 bits), score (16), depth (8), bound (2), age (8)
+fn pack_entry(entry: TtEntry) -> u64 {
+    let bound = match entry.bound {
+        Bound::Exact => 0,
+        Bound::Lower => 1,
+        Bound::Upper => 2,
+    };
+    OCCUPIED |
+    pack_move(entry.best_move) |
+    ((entry.score as i16 as u16 as u64) << 20) |
+    ((entry.depth as u64) << 36) |
+    (bound << 44) |
+    ((entry.age as u64) << 46)
+}
+
+fn unpack_entry(key: u64, data: u64) -> TtEntry {
+    TtEntry {
+        key,
+        best_move: unpack_move(data & 0xf_ffff),
+        score: (data >> 20) as u16 as i16 as i32,
+        depth: (data >> 36) as u8,
+        bound: match (data >> 44) & 3 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        },
+        age: (data >> 46) as u8,
+    }
+}
+
+/// Start square (6 bits), end square (6), promotion (3), dropped piece (3) and a presence bit
+fn pack_move(mv: Option<Move>) -> u64 {
+    let Some(mv) = mv else {
+        return 0;
+    };
+    let square = |coords: Coordinates| (coords.0 * 8 + coords.1) as u64;
+    1 |
+    (square(mv.start) << 1) |
+    (square(mv.end) << 7) |
+    (piece_code(mv.promotion) << 13) |
+    (piece_code(mv.drop) << 16)
+}
+
+fn unpack_move(bits: u64) -> Option<Move> {
+    if bits & 1 == 0 {
+        return None;
+    }
+    let square = |shift: u64| {
+        let index = ((bits >> shift) & 63) as u8;
+        Coordinates(index / 8, index % 8)
+    };
+    Some(Move {
+        start: square(1),
+        end: square(7),
+        promotion: piece_from_code((bits >> 13) & 7),
+        drop: piece_from_code((bits >> 16) & 7),
+    })
+}
+
+fn piece_code(piece_type: Option<PieceType>) -> u64 {
+    match piece_type {
+        None => 0,
+        Some(PieceType::Pawn(_)) => 1,
+        Some(PieceType::Knight) => 2,
+        Some(PieceType::Bishop) => 3,
+        Some(PieceType::Rook(_)) => 4,
+        Some(PieceType::Queen) => 5,
+        Some(PieceType::King(_)) => 6,
+    }
+}
+
+/// Piece types as moves carry them: pawns for drops, moved rooks and kings for promotions
+fn piece_from_code(code: u64) -> Option<PieceType> {
+    match code {
+        1 => Some(PieceType::Pawn(PawnData { has_moved: true })),
+        2 => Some(PieceType::Knight),
+        3 => Some(PieceType::Bishop),
+        4 => Some(PieceType::Rook(true)),
+        5 => Some(PieceType::Queen),
+        6 => Some(PieceType::King(true)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: u64 = 0x1234_5678_9abc_def0;
+    const B: u64 = 0x0fed_cba9_8765_4321;
+
+    fn e2e4() -> Option<Move> {
+        Some(Move::new(Coordinates(6, 4), Coordinates(4, 4)))
+    }
+
+    #[test]
+    fn entries_come_back_as_stored() {
+        let table = TranspositionTable::new(1);
+        let promotion = Some(Move { promotion: Some(PieceType::Knight), ..Move::new(Coordinates(1, 0), Coordinates(0, 0)) });
+        table.store(A, promotion, -31_000, 12, Bound::Upper);
+        assert_eq!(
+            table.probe(A),
+            Some(TtEntry { key: A, best_move: promotion, score: -31_000, depth: 12, bound: Bound::Upper, age: 0 })
+        );
+        assert_eq!(table.probe(B), None);
+    }
+
+    #[test]
+    fn deeper_and_newer_entries_win_the_slot() {
+        // A single slot, so every key lands on the same one
+        let table = TranspositionTable::new(0);
+        table.store(A, e2e4(), 10, 5, Bound::Exact);
+
+        // A shallower search of another position leaves it alone, a deeper one replaces it
+        table.store(B, None, 20, 4, Bound::Lower);
+        assert_eq!(table.probe(A).map(|entry| entry.depth), Some(5));
+        assert_eq!(table.probe(B), None);
+        table.store(B, None, 20, 6, Bound::Lower);
+        assert_eq!(table.probe(A), None);
+        assert_eq!(table.probe(B).map(|entry| entry.score), Some(20));
+
+        // Once the entry is from an earlier search, anything replaces it
+        table.new_search();
+        table.store(A, None, 30, 1, Bound::Upper);
+        assert_eq!(table.probe(A).map(|entry| (entry.depth, entry.age)), Some((1, 1)));
+
+        // The same position is always rewritten, keeping its move if the new search found none
+        table.store(A, e2e4(), 30, 3, Bound::Exact);
+        table.store(A, None, 40, 1, Bound::Lower);
+        assert_eq!(table.probe(A).map(|entry| (entry.best_move, entry.score, entry.depth)), Some((e2e4(), 40, 1)));
+    }
+
+    #[test]
+    fn clearing_empties_every_slot() {
+        let table = TranspositionTable::new(0);
+        table.new_search();
+        table.store(A, e2e4(), 10, 5, Bound::Exact);
+        assert_eq!(table.hashfull(), 1000);
+        table.clear();
+        assert_eq!(table.probe(A), None);
+        assert_eq!(table.hashfull(), 0);
+        table.store(A, e2e4(), 10, 5, Bound::Exact);
+        assert_eq!(table.probe(A).map(|entry| entry.age), Some(0));
+    }
+
+    #[test]
+    fn the_key_check_rejects_other_and_torn_entries() {
+        let table = TranspositionTable::new(0);
+        table.store(A, e2e4(), 10, 5, Bound::Exact);
+        assert_eq!(table.probe(B), None);
+        assert_eq!(table.probe(A ^ 1), None);
+
+        // A slot whose two words were written by different stores fails the check
+        let data = table.slots[0][1].load(Ordering::Relaxed);
+        table.slots[0][1].store(data ^ (1 << 20), Ordering::Relaxed);
+        assert_eq!(table.probe(A), None);
+    }
+}
@@ -0,0 +1,591 @@
+// Imports
+use std::io::{self, Read, Write};
+use std::process::{self, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::analysis::*;
+use crate::annotate::*;
+use crate::errors::*;
+use crate::engine::*;
+use crate::game::*;
+use crate::moves::*;
+use crate::pgn::*;
+use crate::render::*;
+use crate::piece::*;
+use crate::session::*;
+use crate::variant::*;
+
+/// Depth of the search behind the evaluation bar, run after every move
+const EVAL_DEPTH: u8 = 5;
+/// Screen row and column (from 1) of the board's top left corner, and the side panel's column
+const BOARD_TOP: u16 = 2;
+const BOARD_LEFT: u16 = 5;
+const PANEL_LEFT: u16 = 44;
+/// Rows of the move list, each holding a full move
+const MOVE_ROWS: usize = 12;
+/// Rows of the evaluation bar, beside the board's ranks
+const BAR_ROWS: usize = 16;
+/// Columns a message is cut to, so it stays on its line
+const MESSAGE_WIDTH: usize = 100;
+/// Longest a read waits for a key, so running clocks keep being redrawn
+const READ_TIMEOUT: Duration = Duration::from_millis(200);
+
+// Custom Terminal Type Data
+
+/// The terminal switched to raw input, the alternate screen and mouse reporting, which
+/// puts it back as it was when dropped. Reads time out after `READ_TIMEOUT`.
+pub struct Terminal {
+    saved: String,
+}
+
+// Custom Key Type Data
+
+/// A key press or mouse click read from the terminal
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Escape,
+    Backspace,
+    /// Ctrl-C or Ctrl-D
+    Interrupt,
+    Char(char),
+    /// A left click, holding the screen row and column counted from 1
+    Click(u16, u16),
+}
+
+// Custom Screen Type Data
+
+/// What the full screen game shows besides the position itself
+struct Screen {
+    cursor: Coordinates,
+    selected: Option<Coordinates>,
+    /// A promotion waiting for the player to pick the piece
+    promotion: Option<Move>,
+    command: String,
+    message: String,
+    /// The evaluation bar's score, from White's view
+    eval: Option<i32>,
+}
+
+/// What a key press asks the game to do
+enum Action {
+    Nothing,
+    Play(Move),
+    Quit,
+}
+
+// Custom Terminal Type Traits
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        let _ = write!(stdout, "\x1b[?1006l\x1b[?1000l\x1b[?25h\x1b[?1049l");
+        let _ = stdout.flush();
+        let _ = stty(&[self.saved.trim()]);
+    }
+}
+
+// Impl Terminal
+
+impl Terminal {
+    pub fn open() -> io::Result<Terminal> {
+        let saved = stty(&["-g"])?;
+        let tenths = (READ_TIMEOUT.as_millis() / 100).to_string();
+        stty(&["raw", "-echo", "min", "0", "time", &tenths])?;
+        let terminal = Terminal { saved };
+
+        let mut stdout = io::stdout();
+        write!(stdout, "\x1b[?1049h\x1b[?25l\x1b[?1000h\x1b[?1006h\x1b[2J")?;
+        stdout.flush()?;
+        Ok(terminal)
+    }
+
+    /// Replaces the screen with `frame`, which positions its own lines
+    pub fn draw(&self, frame: &str) {
+        let mut stdout = io::stdout().lock();
+        let _ = write!(stdout, "{frame}\x1b[J");
+        let _ = stdout.flush();
+    }
+
+    /// Keys pressed since the last read, waiting `READ_TIMEOUT` for one at most. Input
+    /// that has ended reads as `Key::Interrupt`, like Ctrl-D.
+    pub fn read_keys(&self) -> Vec<Key> {
+        let mut buffer = [0; 64];
+        let start = Instant::now();
+        match io::stdin().lock().read(&mut buffer) {
+            // A read that times out comes back empty after the whole wait, one at the end
+            // of the input at once
+            Ok(0) if start.elapsed() < READ_TIMEOUT / 2 => vec![Key::Interrupt],
+            Ok(read) => parse_keys(&buffer[..read]),
+            Err(_) => vec![Key::Interrupt],
+        }
+    }
+}
+
+// Impl Screen
+
+impl Screen {
+    fn new(turn: PieceColor) -> Screen {
+        // The cursor starts on the king's pawn of the side to move
+        let row = if turn == PieceColor::White { 6 } else { 1 };
+        Screen {
+            cursor: Coordinates(row, 4),
+            selected: None,
+            promotion: None,
+            command: String::new(),
+            message: String::new(),
+            eval: None,
+        }
+    }
+
+    /// Clears the selection after the position changed and scores the new one
+    fn played<V: Variant>(&mut self, game: &Game<V>, engine: &mut Engine) {
+        self.selected = None;
+        self.promotion = None;
+        self.evaluate(game, engine);
+    }
+
+    fn evaluate<V: Variant>(&mut self, game: &Game<V>, engine: &mut Engine) {
+        let status = game.status();
+        let score = match status.winner() {
+            Some(winner) if winner == game.turn => MATE_SCORE,
+            Some(_) => -MATE_SCORE,
+            None if status.is_over() => 0,
+            None => engine.search_with(game, SearchLimits::depth(EVAL_DEPTH), |_| {}).1,
+        };
+        self.eval = Some(if game.turn == PieceColor::White { score } else { -score });
+    }
+
+    /// Legal moves of the piece on `from`, ignoring drops
+    fn moves_from<V: Variant>(&self, game: &Game<V>, from: Coordinates) -> Vec<Move> {
+        game.legal_moves().into_iter().filter(|mv| mv.drop.is_none() && mv.start == from).collect()
+    }
+
+    /// Whether `mv` lands on `square`, counting castling by the king's two square move too
+    fn lands_on<V: Variant>(game: &Game<V>, mv: Move, square: Coordinates) -> bool {
+        mv.end == square || game.uci_move(mv).end == square
+    }
+
+    fn handle<V: Variant>(&mut self, session: &mut Session<V>, key: Key, engine: &mut Engine) -> Action {
+        let game = &session.game;
+        if let Some(promotion) = self.promotion {
+            return match key {
+                Key::Char(letter) => match piece_type_from_letter(letter).filter(|piece| V::promotion_pieces().contains(piece)) {
+                    Some(piece_type) => Action::Play(Move { promotion: Some(piece_type), ..promotion }),
+                    None => Action::Nothing,
+                },
+                Key::Escape => {
+                    self.promotion = None;
+                    self.message.clear();
+                    Action::Nothing
+                },
+                Key::Interrupt => Action::Quit,
+                _ => Action::Nothing,
+            };
+        }
+
+        // Arrows move the cursor as drawn, which runs backwards when Black is at the bottom
+        let Coordinates(row, col) = self.cursor;
+        let forward = if session.options.is_flipped(game.turn) { 1 } else { -1 };
+        let step = |index: u8, by: i8| index.saturating_add_signed(by).min(7);
+        match key {
+            Key::Up => self.cursor = Coordinates(step(row, forward), col),
+            Key::Down => self.cursor = Coordinates(step(row, -forward), col),
+            Key::Left => self.cursor = Coordinates(row, step(col, forward)),
+            Key::Right => self.cursor = Coordinates(row, step(col, -forward)),
+            Key::Enter | Key::Char(' ') if self.command.is_empty() => return self.pick(game),
+            Key::Enter => {
+                let command = std::mem::take(&mut self.command);
+                return self.run(session, command.trim(), engine);
+            },
+            Key::Escape => {
+                self.selected = None;
+                self.command.clear();
+                self.message.clear();
+            },
+            Key::Backspace => {
+                self.command.pop();
+            },
+            Key::Interrupt => return Action::Quit,
+            Key::Char(letter) => self.command.push(letter),
+            Key::Click(row, col) => {
+                if let Some(square) = square_at(row, col, &session.options.facing(game.turn)) {
+                    self.cursor = square;
+                    return self.pick(game);
+                }
+            },
+        }
+        Action::Nothing
+    }
+
+    /// Selects the piece under the cursor, or moves the selected piece there
+    fn pick<V: Variant>(&mut self, game: &Game<V>) -> Action {
+        let square = self.cursor;
+        if let Some(from) = self.selected {
+            let moves: Vec<Move> = self.moves_from(game, from).into_iter().filter(|&mv| Screen::lands_on(game, mv, square)).collect();
+            match moves.as_slice() {
+                [] => {},
+                [mv] => return Action::Play(*mv),
+                [mv, ..] => {
+                    let letters: String = V::promotion_pieces().iter().map(|&piece_type| piece_type_letter(piece_type)).collect();
+                    self.promotion = Some(*mv);
+                    self.message = format!("Promote to which piece? ({letters})");
+                    return Action::Nothing;
+                },
+            }
+        }
+
+        let own = game.board.get(square).is_some_and(|piece| piece.color == game.turn);
+        self.selected = match own && self.selected != Some(square) && !self.moves_from(game, square).is_empty() {
+            true => Some(square),
+            false => None,
+        };
+        Action::Nothing
+    }
+
+    /// Runs a typed move in SAN or coordinates, or a command, see `Session::execute`
+    fn run<V: Variant>(&mut self, session: &mut Session<V>, command: &str, engine: &mut Engine) -> Action {
+        if command.is_empty() {
+            return Action::Nothing;
+        }
+        let game = session.game;
+        let moves = session.history.len();
+        match command.parse() {
+            Ok(Command::Quit) => return Action::Quit,
+            // The hint is shown on the board as well, by selecting the piece and its square
+            Ok(Command::Hint) => {
+                self.message = match engine.best_move(&game) {
+                    Some(mv) => {
+                        self.selected = Some(mv.start).filter(|_| mv.drop.is_none());
+                        self.cursor = game.uci_move(mv).end;
+                        format!("Hint: {}", san(&game, mv))
+                    },
+                    None => "No moves left to suggest".to_owned(),
+                };
+            },
+            // The tags don't fit on the message line, so only the moves are shown
+            Ok(Command::Pgn) => {
+                let start = session.start();
+                let moves: Vec<Move> = session.history.iter().map(|&(_, mv, _)| mv).collect();
+                self.message = cut(&movetext(&start, &moves), MESSAGE_WIDTH);
+            },
+            Ok(Command::Move(text)) => match parse_san(&game, &text) {
+                Some(mv) => return Action::Play(mv),
+                None => self.message = CommandError::Unknown(text).to_string(),
+            },
+            Ok(command) => {
+                let feedback = session.execute(command, engine).unwrap_or_else(|error| error.to_string());
+                let feedback: Vec<&str> = feedback.split_whitespace().collect();
+                self.message = cut(&feedback.join(" "), MESSAGE_WIDTH);
+            },
+            Err(error) => self.message = error.to_string(),
+        }
+        if session.history.len() != moves || session.game.to_fen() != game.to_fen() {
+            self.played(&session.game, engine);
+        }
+        Action::Nothing
+    }
+
+    /// The whole screen, each line placed with cursor positioning
+    fn render<V: Variant>(&self, session: &Session<V>) -> String {
+        let (game, clock, pgn) = (&session.game, &session.clock, &session.pgn);
+        let mut frame = String::from("\x1b[H");
+        let mut line = |row: u16, col: u16, text: &str| frame.push_str(&format!("\x1b[{row};{col}H{text}\x1b[K"));
+
+        let variant = pgn.tag("Variant").unwrap_or(Standard::NAME);
+        line(1, 1, &format!("\x1b[1mrust_chess\x1b[0m  {variant}"));
+        let options = session.options.facing(game.turn);
+        let top = options.top_lines();
+        for (index, text) in game.board.render(&options, &self.highlights(session)).lines().enumerate() {
+            let bar = index.checked_sub(top).map_or("  ", |line| self.bar_cell(line, options.is_flipped(game.turn)));
+            line(BOARD_TOP + index as u16, 1, &format!(" {bar} {text}"));
+        }
+        let eval = self.eval.map_or(String::new(), score_text);
+        let below = BOARD_TOP + top as u16 + 18;
+        line(below, 1, &format!(" {eval}"));
+
+        // Each player sits on their own side of the board, with the pieces they have taken
+        let player = |color: PieceColor| {
+            let name = pgn.tag(&color.to_string()).unwrap_or("?");
+            let time = clock.map_or(String::new(), |clock| clock_text(clock.remaining(color)));
+            let text = format!(" {color}: {name:<20} {time:>7} ");
+            match color == game.turn {
+                true => format!("\x1b[7m{text}\x1b[0m"),
+                false => text,
+            }
+        };
+        // Captured pieces go back into play from the pockets in variants that have them,
+        // so those show the pocket instead
+        let captured = |color: PieceColor| {
+            if V::POCKETS {
+                let letters: Vec<String> = game
+                    .pocket(color)
+                    .pieces()
+                    .map(|piece_type| Piece { piece_type, color, coordinates: Coordinates(0, 0) }.symbol(options.pieces).to_string())
+                    .collect();
+                return format!(" Pocket: {}", letters.join(" "));
+            }
+            let letters: Vec<String> = session
+                .history
+                .iter()
+                .filter(|(before, ..)| before.turn == color)
+                .filter_map(|(before, mv, _)| before.captured_piece(*mv))
+                .map(|piece| Piece { coordinates: Coordinates(0, 0), ..piece }.symbol(options.pieces).to_string())
+                .collect();
+            format!(" Taken: {}", letters.join(" "))
+        };
+        let (top_color, bottom_color) = match options.is_flipped(game.turn) {
+            true => (PieceColor::White, PieceColor::Black),
+            false => (PieceColor::Black, PieceColor::White),
+        };
+        let mut panel = vec![player(top_color), captured(top_color), String::new(), " Moves".to_owned()];
+        let mut moves = move_rows(pgn);
+        moves.drain(..moves.len().saturating_sub(MOVE_ROWS));
+        moves.resize(MOVE_ROWS, String::new());
+        panel.extend(moves);
+        panel.push(captured(bottom_color));
+        panel.push(player(bottom_color));
+        for (index, text) in panel.iter().enumerate() {
+            frame.push_str(&format!("\x1b[{};{PANEL_LEFT}H{text}", BOARD_TOP + (top + index) as u16));
+        }
+
+        let mut line = |row: u16, text: &str| frame.push_str(&format!("\x1b[{row};1H{text}\x1b[K"));
+        line(below + 1, &format!(" {}", self.message));
+        line(below + 2, &format!(" > {}\x1b[7m \x1b[0m", self.command));
+        line(below + 3, " Arrows or mouse to move pieces, Enter to pick, type a move or a command (help lists them)");
+        frame
+    }
+
+    /// The cursor, the selected piece and its destinations, the last move, a king in check
+    /// and the pieces of the side to move left hanging
+    fn highlights<V: Variant>(&self, session: &Session<V>) -> Vec<(Coordinates, Highlight)> {
+        let game = &session.game;
+        let mut highlights = vec![(self.cursor, Highlight::Cursor)];
+        if let Some(from) = self.selected {
+            highlights.push((from, Highlight::Selected));
+            for mv in self.moves_from(game, from) {
+                highlights.extend([(mv.end, Highlight::Target), (game.uci_move(mv).end, Highlight::Target)]);
+            }
+        }
+        if let Some(mv) = session.last_move() {
+            highlights.extend([(mv.start, Highlight::LastMove), (mv.end, Highlight::LastMove)]);
+        }
+        if let Some(king) = game.king(game.turn).filter(|_| game.is_king_in_check(game.turn)) {
+            highlights.push((king, Highlight::Check));
+        }
+        highlights.extend(game.hanging_pieces(game.turn).into_iter().map(|square| (square, Highlight::Hanging)));
+        highlights
+    }
+
+    /// One row of the evaluation bar beside a line of the board, White's share (solid)
+    /// in proportion to its chance of winning and drawn from its side of the board
+    fn bar_cell(&self, line: usize, flipped: bool) -> &'static str {
+        let Some(eval) = self.eval else {
+            return "  ";
+        };
+        if !(1..=BAR_ROWS).contains(&line) {
+            return "  ";
+        }
+        let white_rows = (win_percent(eval) / 100.0 * BAR_ROWS as f64).round() as usize;
+        let from_white = if flipped { line - 1 } else { BAR_ROWS - line };
+        match from_white < white_rows {
+            true => "██",
+            false => "░░",
+        }
+    }
+}
+
+// Impl Session for the full screen interface
+
+impl<V: Variant> Session<V> {
+    /// Plays the game full screen on `terminal` until it ends or the player quits, see
+    /// `game_loop`. Pieces are moved with the arrow keys or the mouse, and moves or
+    /// commands can be typed at the prompt.
+    pub fn tui_loop(&mut self, terminal: Terminal, engine: &mut Engine) {
+        let mut screen = Screen::new(self.game.turn);
+        screen.evaluate(&self.game, engine);
+        loop {
+            self.check_end();
+            if let Some(ending) = &self.ending {
+                screen.message = format!("{ending}. Press any key to leave");
+                terminal.draw(&screen.render(self));
+                while terminal.read_keys().is_empty() {}
+                return;
+            }
+
+            let turn = self.game.turn;
+            if let Some(clock) = &mut self.clock {
+                clock.start(turn);
+            }
+            if self.engine_color == Some(turn) {
+                screen.message = "Engine is thinking...".to_owned();
+                terminal.draw(&screen.render(self));
+                let limits = match &self.clock {
+                    Some(clock) => SearchLimits::time(clock.limits(turn)),
+                    None => SearchLimits::depth(engine.depth),
+                };
+                let Some(mv) = engine.best_move_within(&self.game, limits) else {
+                    return;
+                };
+                if self.play(mv).is_err() {
+                    return;
+                }
+                screen.played(&self.game, engine);
+                screen.message = format!("Engine played {}", self.pgn.moves.last().map_or("", |pgn_move| &pgn_move.san));
+                continue;
+            }
+
+            terminal.draw(&screen.render(self));
+            for key in terminal.read_keys() {
+                match screen.handle(self, key, engine) {
+                    Action::Nothing => {},
+                    Action::Quit => return,
+                    Action::Play(mv) => {
+                        screen.message = match self.play(mv) {
+                            Ok(()) => {
+                                screen.played(&self.game, engine);
+                                String::new()
+                            },
+                            Err(error) => error.to_string(),
+                        };
+                        break;
+                    },
+                }
+                // A command may have ended the game or changed whose move it is
+                if self.is_over() || self.game.turn != turn {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+// Functions
+
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = process::Command::new("stty").args(args).stdin(Stdio::inherit()).output()?;
+    match output.status.success() {
+        true => Ok(String::from_utf8_lossy(&output.stdout).into_owned()),
+        false => Err(io::Error::other("stty could not set up the terminal")),
+    }
+}
+
+/// Keys in a chunk of terminal input: printable characters, control keys, arrow key
+/// escape sequences and SGR mouse reports (`ESC [ < button ; col ; row M`)
+fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        let byte = bytes[index];
+        index += 1;
+        match byte {
+            0x1b if matches!(bytes.get(index), Some(b'[' | b'O')) => {
+                // Parameters run up to a final byte from '@' to '~'
+                let Some(end) = bytes[index + 1..].iter().position(|byte| (0x40..=0x7e).contains(byte)) else {
+                    break;
+                };
+                let params = &bytes[index + 1..index + 1 + end];
+                let last = bytes[index + 1 + end];
+                index += end + 2;
+                match (params, last) {
+                    ([], b'A') => keys.push(Key::Up),
+                    ([], b'B') => keys.push(Key::Down),
+                    ([], b'C') => keys.push(Key::Right),
+                    ([], b'D') => keys.push(Key::Left),
+                    ([b'<', report @ ..], b'M') => {
+                        let numbers: Vec<u16> = String::from_utf8_lossy(report).split(';').filter_map(|number| number.parse().ok()).collect();
+                        if let [0, col, row] = *numbers.as_slice() {
+                            keys.push(Key::Click(row, col));
+                        }
+                    },
+                    _ => {},
+                }
+            },
+            0x1b => keys.push(Key::Escape),
+            b'\r' | b'\n' => keys.push(Key::Enter),
+            0x7f | 0x08 => keys.push(Key::Backspace),
+            0x03 | 0x04 => keys.push(Key::Interrupt),
+            byte if byte.is_ascii_graphic() || byte == b' ' => keys.push(Key::Char(byte as char)),
+            _ => {},
+        }
+    }
+    keys
+}
+
+/// The board square drawn at a screen row and column, if any
+fn square_at(row: u16, col: u16, options: &RenderOptions) -> Option<Coordinates> {
+    let line = row.checked_sub(BOARD_TOP + options.top_lines() as u16)?;
+    let x = col.checked_sub(BOARD_LEFT)?.checked_sub(4)?;
+    if !(1..=16).contains(&line) || x >= 32 {
+        return None;
+    }
+    let (row, col) = (((line - 1) / 2) as u8, (x / 4) as u8);
+    match options.orientation == Orientation::Black {
+        true => Some(Coordinates(7 - row, 7 - col)),
+        false => Some(Coordinates(row, col)),
+    }
+}
+
+/// The move list a full move per row, starting with `12... Nf6` when Black moved first
+fn move_rows(pgn: &Pgn) -> Vec<String> {
+    let mut rows: Vec<String> = Vec::new();
+    for (index, pgn_move) in pgn.moves.iter().enumerate() {
+        let follows_white = index > 0 && pgn.moves[index - 1].color == PieceColor::White;
+        match (pgn_move.color, rows.last_mut()) {
+            (PieceColor::Black, Some(row)) if follows_white => row.push_str(&pgn_move.san),
+            (PieceColor::White, _) => rows.push(format!(" {:>3}. {:<8}", pgn_move.number, pgn_move.san)),
+            (PieceColor::Black, _) => rows.push(format!(" {:>3}. {:<8}{}", pgn_move.number, "...", pgn_move.san)),
+        }
+    }
+    rows
+}
+
+fn clock_text(time: std::time::Duration) -> String {
+    format!("{}:{:02}", time.as_secs() / 60, time.as_secs() % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arrow_keys_and_characters_are_read() {
+        assert_eq!(parse_keys(b"\x1b[A\x1b[B\x1b[C\x1b[D"), vec![Key::Up, Key::Down, Key::Right, Key::Left]);
+        assert_eq!(parse_keys(b"\x1bOA"), vec![Key::Up]);
+        assert_eq!(parse_keys(b"e4\r\x7f\x03"), vec![Key::Char('e'), Key::Char('4'), Key::Enter, Key::Backspace, Key::Interrupt]);
+        assert_eq!(parse_keys(b"\x1b"), vec![Key::Escape]);
+    }
+
+    #[test]
+    fn left_clicks_are_read_from_sgr_reports() {
+        assert_eq!(parse_keys(b"\x1b[<0;10;5M"), vec![Key::Click(5, 10)]);
+        // Releases and other buttons are ignored
+        assert_eq!(parse_keys(b"\x1b[<0;10;5m\x1b[<2;10;5M"), vec![]);
+        assert_eq!(parse_keys(b"\x1b[<0;10;5Mq"), vec![Key::Click(5, 10), Key::Char('q')]);
+    }
+
+    #[test]
+    fn clicks_map_to_squares_for_either_orientation() {
+        let white = RenderOptions::default();
+        let black = RenderOptions { orientation: Orientation::Black, ..white };
+        // The top left square and the bottom right one
+        assert_eq!(square_at(3, 9, &white), Some(Coordinates(0, 0)));
+        assert_eq!(square_at(18, 40, &white), Some(Coordinates(7, 7)));
+        assert_eq!(square_at(3, 9, &black), Some(Coordinates(7, 7)));
+        assert_eq!(square_at(18, 40, &black), Some(Coordinates(0, 0)));
+        assert_eq!(square_at(10, 22, &white), Some(Coordinates(3, 3)));
+        assert_eq!(square_at(10, 22, &black), Some(Coordinates(4, 4)));
+        // Labels on the top push the board down a line
+        let labelled = RenderOptions { labels_both_sides: true, ..white };
+        assert_eq!(square_at(4, 9, &labelled), Some(Coordinates(0, 0)));
+        // Outside the board
+        assert_eq!(square_at(2, 9, &white), None);
+        assert_eq!(square_at(19, 9, &white), None);
+        assert_eq!(square_at(3, 8, &white), None);
+        assert_eq!(square_at(3, 41, &white), None);
+    }
+}
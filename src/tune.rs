@@ -0,0 +1,178 @@
+// Imports
+use std::fs;
+use std::path::Path;
+
+use crate::engine::*;
+use crate::errors::*;
+use crate::eval::*;
+use crate::game::*;
+use crate::piece::*;
+
+/// Epochs between searching the positions' quiet leaves again with the new weights
+const RESOLVE_EVERY: usize = 10;
+/// Adam step size, in centipawns
+const LEARNING_RATE: f64 = 1.0;
+
+// Custom TuningPosition Type Data
+
+/// A position from a played game and how that game ended: 1 White won, 0.5 draw, 0 Black won
+#[derive(Debug, Copy, Clone)]
+pub struct TuningPosition {
+    pub game: Game,
+    pub result: f64,
+}
+
+// Functions
+
+/// Reads one position per line: a FEN followed by the game's result, as `1-0`, `0-1` or
+/// `1/2-1/2` (optionally quoted, as in EPD `c9` fields) or as `[1.0]`, `[0.5]` or `[0.0]`.
+/// Empty lines and lines starting with `#` are skipped.
+pub fn load_positions<P: AsRef<Path>>(path: P) -> Result<Vec<TuningPosition>, TuneError> {
+    let text = fs::read_to_string(path).map_err(|_| TuneError::Unreadable)?;
+    let mut positions = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        positions.push(parse_position(line).ok_or(TuneError::InvalidLine(number + 1))?);
+    }
+    if positions.is_empty() {
+        return Err(TuneError::NoPositions);
+    }
+    Ok(positions)
+}
+
+fn parse_position(line: &str) -> Option<TuningPosition> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (last, fen) = words.split_last()?;
+    let result = match last.trim_matches(|c| matches!(c, '"' | ';' | '[' | ']')) {
+        "1-0" | "1.0" | "1" => 1.0,
+        "0-1" | "0.0" | "0" => 0.0,
+        "1/2-1/2" | "0.5" => 0.5,
+        _ => return None,
+    };
+    // Up to the six FEN fields, leaving out an EPD opcode such as `c9` before the result
+    let fen: Vec<&str> = fen.iter().take(6).take_while(|word| !word.starts_with("c9")).copied().collect();
+    let game = Game::from_fen(&fen.join(" ")).ok()?;
+    Some(TuningPosition { game, result })
+}
+
+/// Fits `params` to the results of `positions` by gradient descent on the squared
+/// difference between each result and the win probability its quiescence score predicts
+/// (Texel's method). `report` gets the error after each epoch.
+pub fn tune(params: &EvalParams, positions: &[TuningPosition], epochs: usize, mut report: impl FnMut(usize, f64)) -> EvalParams {
+    let mut weights: Vec<f64> = params.to_vec().into_iter().map(f64::from).collect();
+    let mut leaves = quiet_leaves(params, positions);
+    let scaling = fit_scaling(params, &leaves, positions);
+
+    // Adam keeps running averages of each weight's gradient and squared gradient
+    let (mut mean, mut variance) = (vec![0.0; PARAM_COUNT], vec![0.0; PARAM_COUNT]);
+    let (beta1, beta2) = (0.9f64, 0.999f64);
+    for epoch in 1..=epochs {
+        let rounded = round(&weights);
+        if epoch % RESOLVE_EVERY == 0 {
+            leaves = quiet_leaves(&rounded, positions);
+        }
+
+        let mut gradient = vec![0.0; PARAM_COUNT];
+        for (leaf, position) in leaves.iter().zip(positions) {
+            let score: f64 = features(leaf).map(|(index, count)| weights[index] * count as f64).sum();
+            let predicted = win_probability(scaling, score);
+            let slope = (predicted - position.result) * predicted * (1.0 - predicted);
+            for (index, count) in features(leaf) {
+                gradient[index] += slope * count as f64;
+            }
+        }
+
+        for index in 0..PARAM_COUNT {
+            let step = gradient[index] / positions.len() as f64;
+            mean[index] = beta1 * mean[index] + (1.0 - beta1) * step;
+            variance[index] = beta2 * variance[index] + (1.0 - beta2) * step * step;
+            let mean_hat = mean[index] / (1.0 - beta1.powi(epoch as i32));
+            let variance_hat = variance[index] / (1.0 - beta2.powi(epoch as i32));
+            weights[index] -= LEARNING_RATE * mean_hat / (variance_hat.sqrt() + 1e-8);
+        }
+        report(epoch, tuning_error(&round(&weights), &leaves, positions, scaling));
+    }
+    round(&weights)
+}
+
+/// Mean squared difference between the results and the win probabilities predicted from
+/// the evaluation of each position's quiet leaf
+fn tuning_error(params: &EvalParams, leaves: &[Game], positions: &[TuningPosition], scaling: f64) -> f64 {
+    let total: f64 = leaves
+        .iter()
+        .zip(positions)
+        .map(|(leaf, position)| (position.result - win_probability(scaling, white_score(leaf, params) as f64)).powi(2))
+        .sum();
+    total / positions.len() as f64
+}
+
+/// The scaling from centipawns to win probability that fits the current weights best,
+/// found by narrowing down a range of candidates
+fn fit_scaling(params: &EvalParams, leaves: &[Game], positions: &[TuningPosition]) -> f64 {
+    let (mut low, mut high) = (0.05, 4.0);
+    for _ in 0..40 {
+        let third = (high - low) / 3.0;
+        if tuning_error(params, leaves, positions, low + third) < tuning_error(params, leaves, positions, high - third) {
+            high -= third;
+        } else {
+            low += third;
+        }
+    }
+    (low + high) / 2.0
+}
+
+fn win_probability(scaling: f64, score: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-scaling * score / 400.0))
+}
+
+fn round(weights: &[f64]) -> EvalParams {
+    let weights: Vec<i32> = weights.iter().map(|weight| weight.round() as i32).collect();
+    EvalParams::from_slice(&weights)
+}
+
+fn white_score(game: &Game, params: &EvalParams) -> i32 {
+    match game.turn {
+        PieceColor::White => evaluate(game, params),
+        PieceColor::Black => -evaluate(game, params),
+    }
+}
+
+/// The quiet position each position's quiescence search ends its best line in
+fn quiet_leaves(params: &EvalParams, positions: &[TuningPosition]) -> Vec<Game> {
+    positions.iter().map(|position| quiet_leaf(&position.game, params)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_adam_step_lowers_the_error() {
+        let positions: Vec<TuningPosition> = [
+            "4k3/8/8/8/8/8/8/3NK3 w - - 0 1 1/2-1/2",
+            "3nk3/8/8/8/8/8/8/4K3 b - - 0 1 1/2-1/2",
+            "4k3/8/8/8/8/8/8/3QK3 w - - 0 1 1-0",
+            "3qk3/8/8/8/8/8/8/4K3 w - - 0 1 0-1",
+            "4k3/pp6/8/8/8/8/PPP5/4K3 w - - 0 1 1-0",
+            // A capture first, so the leaf is not the position itself
+            "4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1 1-0",
+        ]
+        .iter()
+        .map(|line| parse_position(line).unwrap())
+        .collect();
+
+        let params = EvalParams::default();
+        let leaves = quiet_leaves(&params, &positions);
+        assert_ne!(leaves[5].board.get(Coordinates(3, 3)).unwrap().color, PieceColor::Black);
+        let scaling = fit_scaling(&params, &leaves, &positions);
+        let before = tuning_error(&params, &leaves, &positions, scaling);
+
+        let mut after = None;
+        let tuned = tune(&params, &positions, 1, |_, error| after = Some(error));
+        assert!(after.unwrap() < before, "{} is not below {before}", after.unwrap());
+        assert_ne!(tuned.to_vec(), params.to_vec());
+    }
+}
@@ -0,0 +1,400 @@
+// Imports
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::book::*;
+use crate::clock::*;
+use crate::engine::*;
+use crate::errors::*;
+use crate::eval::*;
+use crate::fen::*;
+use crate::game::*;
+use crate::moves::*;
+use crate::nnue::*;
+use crate::piece::*;
+use crate::skill::*;
+use crate::tablebase::*;
+use crate::tt::*;
+use crate::variant::*;
+
+/// Most search threads the `Threads` option allows
+const MAX_THREADS: usize = 256;
+/// Most lines the `MultiPV` option allows
+pub const MAX_MULTI_PV: usize = 256;
+
+// Custom UciSettings Type Data
+
+/// Options that are remembered apart from the engine, because they combine
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct UciSettings {
+    chess960: bool,
+    skill_level: u8,
+    /// `UCI_LimitStrength`: play at `elo` rather than `skill_level`
+    limit_strength: bool,
+    elo: u32,
+}
+
+// Custom UciSettings Type Traits
+
+impl Default for UciSettings {
+    fn default() -> Self {
+        UciSettings {
+            chess960: false,
+            skill_level: MAX_SKILL_LEVEL,
+            limit_strength: false,
+            elo: MAX_ELO,
+        }
+    }
+}
+
+// Impl UciSettings
+
+impl UciSettings {
+    fn skill(&self) -> Skill {
+        match self.limit_strength {
+            true => Skill::from_elo(self.elo),
+            false => Skill::new(self.skill_level),
+        }
+    }
+}
+
+// Custom Search Type Data
+
+/// A `go` running on its own thread, which hands the engine back when it ends
+struct Search {
+    /// Ends with the engine and the move it played
+    handle: JoinHandle<(Engine, Option<Move>)>,
+    /// The engine's stop flag
+    stop: Arc<AtomicBool>,
+    /// Tells a `go infinite` that finished searching that it may print its move
+    stopped: Sender<()>,
+}
+
+// Impl Search
+
+impl Search {
+    /// Searches a copy of the engine on another thread, lending it the opening book
+    fn start(engine: &mut Engine, game: Game, line: &str) -> Search {
+        let mut worker = Engine { book: engine.book.take(), ..engine.clone() };
+        let (stopped, stop_received) = mpsc::channel();
+        let line = line.to_owned();
+        Search {
+            handle: thread::spawn(move || {
+                let best = go(&mut worker, &game, &line, stop_received);
+                (worker, best)
+            }),
+            stop: Arc::clone(&engine.stop),
+            stopped,
+        }
+    }
+
+    /// Stops the search, waits for its `bestmove` and takes the engine back, returning
+    /// the move
+    fn finish(self, engine: &mut Engine) -> Option<Move> {
+        let _ = self.stopped.send(());
+        // The search clears the flag as it starts, so keep setting it until the thread ends
+        while !self.handle.is_finished() {
+            self.stop.store(true, Ordering::Relaxed);
+            thread::sleep(Duration::from_millis(1));
+        }
+        let (worker, best) = self.handle.join().ok()?;
+        *engine = worker;
+        best
+    }
+}
+
+// Functions
+
+/// Talks the Universal Chess Interface on stdin/stdout, so the engine can be used from
+/// chess GUIs. Standard chess and Chess960 only. `go` searches on its own thread, so
+/// `stop` and `isready` are answered during a search; any other command stops it first.
+pub fn uci_loop(engine: &mut Engine) {
+    let mut game: Game = Game::default();
+    let mut settings = UciSettings::default();
+    let mut search: Option<Search> = None;
+
+    for line in io::stdin().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let mut words = line.split_whitespace();
+        let command = words.next();
+        if command == Some("isready") {
+            println!("readyok");
+            continue;
+        }
+        if let Some(search) = search.take() {
+            search.finish(engine);
+        }
+        match command {
+            Some("uci") => {
+                println!("id name rust_chess");
+                println!("id author the rust_chess authors");
+                println!("option name Hash type spin default {DEFAULT_HASH_MB} min 1 max 65536");
+                println!("option name Threads type spin default 1 min 1 max {MAX_THREADS}");
+                println!("option name Clear Hash type button");
+                println!("option name BookFile type string default <empty>");
+                println!("option name SyzygyPath type string default <empty>");
+                println!("option name EvalFile type string default <empty>");
+                println!("option name UseNNUE type check default false");
+                println!("option name ParamsFile type string default <empty>");
+                println!("option name MultiPV type spin default 1 min 1 max {MAX_MULTI_PV}");
+                println!("option name Skill Level type spin default {MAX_SKILL_LEVEL} min 0 max {MAX_SKILL_LEVEL}");
+                println!("option name UCI_LimitStrength type check default false");
+                println!("option name UCI_Elo type spin default {MAX_ELO} min {MIN_ELO} max {MAX_ELO}");
+                println!("option name UCI_Chess960 type check default false");
+                println!("uciok");
+            },
+            Some("setoption") => set_option(engine, &mut settings, &line),
+            Some("ucinewgame") => {
+                engine.tt.clear();
+                game = Game::default();
+            },
+            Some("position") => match parse_position(&line) {
+                Ok(mut position) => {
+                    position.chess960 |= settings.chess960;
+                    game = position;
+                },
+                Err(error) => println!("info string {error}"),
+            },
+            Some("go") => search = Some(Search::start(engine, game, &line)),
+            Some("quit") => break,
+            _ => {},
+        }
+    }
+    if let Some(search) = search {
+        search.finish(engine);
+    }
+}
+
+/// Handles `setoption name <name> [value <value>]`
+fn set_option(engine: &mut Engine, settings: &mut UciSettings, line: &str) {
+    let rest = line.trim_start_matches("setoption").trim();
+    let rest = rest.strip_prefix("name").unwrap_or(rest).trim();
+    let (name, value) = match rest.split_once(" value ") {
+        Some((name, value)) => (name.trim(), value.trim()),
+        None => (rest, ""),
+    };
+
+    match name.to_ascii_lowercase().as_str() {
+        "hash" => match value.parse::<usize>() {
+            Ok(megabytes) => engine.tt = Arc::new(TranspositionTable::new(megabytes.max(1))),
+            Err(_) => println!("info string Hash must be a number of megabytes"),
+        },
+        "threads" => match value.parse::<usize>() {
+            Ok(threads) => engine.threads = threads.clamp(1, MAX_THREADS),
+            Err(_) => println!("info string Threads must be a number"),
+        },
+        "clear hash" => engine.tt.clear(),
+        "bookfile" => {
+            engine.book = None;
+            if !value.is_empty() && value != "<empty>" {
+                match Book::open(value) {
+                    Ok(book) => engine.book = Some(book),
+                    Err(error) => println!("info string {error}"),
+                }
+            }
+        },
+        "syzygypath" => {
+            engine.tablebase = None;
+            if !value.is_empty() && value != "<empty>" {
+                match Tablebase::open(value) {
+                    Ok(tablebase) => engine.tablebase = Some(Arc::new(tablebase)),
+                    Err(error) => println!("info string {error}"),
+                }
+            }
+        },
+        "evalfile" => {
+            engine.network = None;
+            if !value.is_empty() && value != "<empty>" {
+                match Network::open(value) {
+                    Ok(network) => engine.network = Some(Arc::new(network)),
+                    Err(error) => println!("info string {error}"),
+                }
+            }
+        },
+        "usennue" => {
+            engine.use_network = value == "true";
+            if engine.use_network && engine.network.is_none() {
+                println!("info string UseNNUE needs an EvalFile, material counting is used until then");
+            }
+        },
+        "paramsfile" => {
+            engine.params = Arc::new(EvalParams::default());
+            if !value.is_empty() && value != "<empty>" {
+                match EvalParams::open(value) {
+                    Ok(params) => engine.params = Arc::new(params),
+                    Err(error) => println!("info string {error}"),
+                }
+            }
+        },
+        "multipv" => match value.parse::<usize>() {
+            Ok(lines) => engine.multi_pv = lines.clamp(1, MAX_MULTI_PV),
+            Err(_) => println!("info string MultiPV must be a number"),
+        },
+        "skill level" => match value.parse::<u8>() {
+            Ok(level) => settings.skill_level = level.min(MAX_SKILL_LEVEL),
+            Err(_) => println!("info string Skill Level must be a number from 0 to {MAX_SKILL_LEVEL}"),
+        },
+        "uci_limitstrength" => settings.limit_strength = value == "true",
+        "uci_elo" => match value.parse::<u32>() {
+            Ok(elo) => settings.elo = elo.clamp(MIN_ELO, MAX_ELO),
+            Err(_) => println!("info string UCI_Elo must be a number"),
+        },
+        "uci_chess960" => settings.chess960 = value == "true",
+        _ => println!("info string Unknown option {name}"),
+    }
+    engine.skill = settings.skill();
+}
+
+/// Reads `position startpos|fen <fen> [moves <move>...]`
+pub fn parse_position(line: &str) -> Result<Game, FenError> {
+    let rest = line.trim_start_matches("position").trim();
+    let (setup, moves) = match rest.split_once("moves") {
+        Some((setup, moves)) => (setup.trim(), moves),
+        None => (rest, ""),
+    };
+
+    let mut game: Game = match setup.strip_prefix("fen") {
+        Some(fen) => Game::from_fen(fen.trim())?,
+        None => Game::from_fen(STARTING_FEN)?,
+    };
+    for word in moves.split_whitespace() {
+        let played = word.parse::<Move>().ok().and_then(|mv| game.play(mv).ok());
+        if played.is_none() {
+            println!("info string Illegal move {word}");
+            break;
+        }
+    }
+    Ok(game)
+}
+
+/// Handles `go [depth <plies>] [nodes <n>] [movetime <ms>] [wtime <ms> btime <ms> [winc <ms>] [binc <ms>] [movestogo <n>]] [infinite]`,
+/// printing progress as `info` lines and then `bestmove`. `infinite` searches until
+/// `stop` arrives on `stop_received` and only then prints `bestmove`. Returns the move.
+fn go(engine: &mut Engine, game: &Game, line: &str, stop_received: Receiver<()>) -> Option<Move> {
+    let mut words = line.split_whitespace().skip(1);
+    let mut limits = SearchLimits::depth(engine.depth);
+    let mut depth = None;
+    let mut nodes = None;
+    let mut move_time = None;
+    let (mut time, mut increment) = ([None; 2], [Duration::ZERO; 2]);
+    let mut moves_to_go = None;
+    let mut infinite = false;
+    while let Some(word) = words.next() {
+        let value = words.clone().next().and_then(|value| value.parse::<u64>().ok());
+        let millis = value.map(Duration::from_millis);
+        match word {
+            "depth" => depth = value.map(|value| value.clamp(1, MAX_DEPTH as u64) as u8),
+            "nodes" => nodes = value,
+            "movetime" => move_time = millis,
+            "wtime" => time[color_index(PieceColor::White)] = millis,
+            "btime" => time[color_index(PieceColor::Black)] = millis,
+            "winc" => increment[color_index(PieceColor::White)] = millis.unwrap_or_default(),
+            "binc" => increment[color_index(PieceColor::Black)] = millis.unwrap_or_default(),
+            "movestogo" => moves_to_go = value.map(|value| value as u32),
+            "infinite" => {
+                infinite = true;
+                continue;
+            },
+            _ => continue,
+        }
+        words.next();
+    }
+
+    let turn = color_index(game.turn);
+    if let Some(remaining) = time[turn] {
+        limits = SearchLimits::time(TimeLimits::new(remaining, increment[turn], moves_to_go));
+    }
+    if let Some(move_time) = move_time {
+        limits = SearchLimits::time(TimeLimits::fixed(move_time));
+    }
+    if let Some(depth) = depth {
+        limits.depth = depth;
+    }
+    if infinite {
+        limits = SearchLimits::depth(MAX_DEPTH);
+    }
+    limits.nodes = nodes;
+
+    // An infinite search is analysis, which wants every line searched and reported until
+    // `stop`, so it never answers from the book or tablebase
+    let book_or_tablebase = match infinite {
+        true => None,
+        false => engine.book_move(game).or_else(|| {
+            let tablebase = engine.tablebase.as_ref().filter(|_| !engine.skill.is_limited());
+            tablebase.and_then(|tablebase| tablebase.best_move(game)).map(|(mv, _)| mv)
+        }),
+    };
+    let best = match book_or_tablebase {
+        Some(mv) => Some(mv),
+        None if engine.skill.is_limited() => engine.skilled_move(game, limits),
+        None => {
+            let (best, _) = engine.search_with(game, limits, |info| {
+                for line in info_lines(game, info) {
+                    println!("{line}");
+                }
+            });
+            println!("info hashfull {}", engine.tt.hashfull());
+            best
+        },
+    };
+
+    if infinite {
+        let _ = stop_received.recv();
+    }
+    match best {
+        Some(mv) => println!("bestmove {}", game.uci_move(mv)),
+        None => println!("bestmove 0000"),
+    }
+    best
+}
+
+/// Formats search progress as UCI `info` lines, one per line of a multi-PV search
+pub fn info_lines(game: &Game, info: &SearchInfo) -> Vec<String> {
+    let millis = info.elapsed.as_millis().max(1);
+    let nps = info.nodes as u128 * 1000 / millis;
+    let mut lines = Vec::new();
+    for (index, line) in info.lines.iter().enumerate() {
+        let score = match mate_in(line.score) {
+            Some(moves) => format!("mate {moves}"),
+            None => format!("cp {}", line.score),
+        };
+
+        let mut pv = String::new();
+        let mut position = *game;
+        for &mv in &line.pv {
+            pv.push(' ');
+            pv.push_str(&position.uci_move(mv).to_string());
+            if position.play_unchecked(mv).is_err() {
+                break;
+            }
+        }
+        lines.push(format!(
+            "info depth {} multipv {} score {score} nodes {} nps {nps} time {millis} pv{pv}",
+            info.depth,
+            index + 1,
+            info.nodes,
+        ));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stop_straight_after_go_still_plays_a_legal_move() {
+        let game = Game::default();
+        for line in ["go infinite", "go depth 30", "go wtime 60000 btime 60000"] {
+            let mut engine = Engine { threads: 2, ..Engine::default() };
+            let best = Search::start(&mut engine, game, line).finish(&mut engine);
+            assert!(best.is_some_and(|mv| game.legal_moves().contains(&mv)), "{line} played {best:?}");
+        }
+    }
+}
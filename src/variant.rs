@@ -0,0 +1,467 @@
+// Imports
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use crate::board::*;
+use crate::game::*;
+use crate::moves::*;
+use crate::piece::*;
+
+const STANDARD_PROMOTIONS: [PieceType; 4] = [PieceType::Queen, PieceType::Rook(true), PieceType::Bishop, PieceType::Knight];
+
+const HORDE_FEN: &str = "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1";
+const RACING_KINGS_FEN: &str = "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1";
+
+// Variant Trait
+
+/// Rules hooks for a chess variant. `Game` is generic over its variant, and any state a
+/// variant needs (such as the Three-Check counters) lives in the implementing type,
+/// stored as `Game::variant`. Every hook defaults to the rules of standard chess.
+pub trait Variant: Debug + Default + Copy + Clone + PartialEq + Hash + Send + Sync {
+    /// Name used in menus and in PGN `Variant` tags
+    const NAME: &'static str;
+
+    /// Captures go to pockets and pieces can be dropped back in
+    const POCKETS: bool = false;
+
+    /// Whether the engine may assume passing is never better than the best move, which
+    /// null move pruning relies on. Not so where zugzwang is common.
+    const NULL_MOVE_PRUNING: bool = true;
+
+    /// Kings may castle. Where they may not, neither the setup nor a FEN grants the rights.
+    const CASTLING: bool = true;
+
+    fn starting_board() -> Board {
+        Board::default()
+    }
+
+    /// Pieces a pawn may promote to
+    fn promotion_pieces() -> &'static [PieceType] {
+        &STANDARD_PROMOTIONS
+    }
+
+    /// Adds or removes candidate moves before legality is checked
+    fn adjust_pseudo_legal(_game: &Game<Self>, _moves: &mut Vec<Move>) {}
+
+    /// Decides if `mv` from `before` is legal, given the position `after` it was played.
+    /// By default the mover's king may not be left in check.
+    fn is_legal(before: &Game<Self>, _mv: Move, after: &Game<Self>) -> bool {
+        !after.is_king_in_check(before.turn)
+    }
+
+    /// Whether a king of `color` standing on `square` would be attacked.
+    /// Used for the squares a castling king crosses.
+    fn king_square_attacked(game: &Game<Self>, color: PieceColor, square: Coordinates) -> bool {
+        game.is_coord_attacked_by_team(color.swap(), square)
+    }
+
+    /// Narrows the list of moves, e.g. to force captures. The search narrows pseudo-legal
+    /// moves before checking them, so this must not depend on which are legal.
+    fn filter_legal(_game: &Game<Self>, moves: Vec<Move>) -> Vec<Move> {
+        moves
+    }
+
+    /// Runs after a move is made on the board but before the turn passes on
+    fn after_move(_game: &mut Game<Self>, _mv: Move, _captured: Option<Piece>) {}
+
+    /// Variant specific results, checked before checkmate and the draw rules
+    fn outcome(_game: &Game<Self>) -> Option<GameStatus> {
+        None
+    }
+
+    /// Result when the side to move has no legal moves
+    fn no_moves_status(game: &Game<Self>) -> GameStatus {
+        if game.is_king_in_check(game.turn) {
+            GameStatus::Checkmate(game.turn.swap())
+        } else {
+            GameStatus::Stalemate
+        }
+    }
+
+    fn insufficient_material(game: &Game<Self>) -> bool {
+        game.standard_insufficient_material()
+    }
+}
+
+// Variants
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Standard;
+
+impl Variant for Standard {
+    const NAME: &'static str = "Standard";
+}
+
+/// Bringing your king to one of the four centre squares wins
+#[derive(Debug, Default, Copy, Clone, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KingOfTheHill;
+
+impl Variant for KingOfTheHill {
+    const NAME: &'static str = "King of the Hill";
+
+    fn outcome(game: &Game<Self>) -> Option<GameStatus> {
+        game.kings()
+            .find(|king| (3..=4).contains(&king.coordinates.0) && (3..=4).contains(&king.coordinates.1))
+            .map(|king| GameStatus::VariantWin(king.color))
+    }
+
+    fn insufficient_material(_game: &Game<Self>) -> bool {
+        false
+    }
+}
+
+/// Giving check three times wins. `checks` counts the checks given by White and Black.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ThreeCheck {
+    pub checks: [u8; 2],
+}
+
+impl ThreeCheck {
+    pub fn checks_given(&self, color: PieceColor) -> u8 {
+        self.checks[color_index(color)]
+    }
+}
+
+impl Variant for ThreeCheck {
+    const NAME: &'static str = "Three-check";
+
+    fn after_move(game: &mut Game<Self>, _mv: Move, _captured: Option<Piece>) {
+        if game.is_king_in_check(game.turn.swap()) {
+            game.variant.checks[color_index(game.turn)] += 1;
+        }
+    }
+
+    fn outcome(game: &Game<Self>) -> Option<GameStatus> {
+        [PieceColor::White, PieceColor::Black]
+            .into_iter()
+            .find(|&color| game.variant.checks_given(color) >= 3)
+            .map(GameStatus::VariantWin)
+    }
+}
+
+/// White has 36 pawns and no king; Black wins by capturing all of them
+#[derive(Debug, Default, Copy, Clone, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Horde;
+
+impl Variant for Horde {
+    const NAME: &'static str = "Horde";
+
+    fn starting_board() -> Board {
+        Game::<Standard>::from_fen(HORDE_FEN).unwrap().board
+    }
+
+    fn after_move(game: &mut Game<Self>, mv: Move, _captured: Option<Piece>) {
+        // A double move from the first rank cannot be taken en passant
+        if mv.start.0 == 7 {
+            game.en_passant = None;
+        }
+        // A White pawn that stepped up from the first rank may still move two squares
+        if mv.end.0 == 6 {
+            if let Some(piece) = game.at(mv.end) {
+                if piece.color == PieceColor::White && matches!(piece.piece_type, PieceType::Pawn(_)) {
+                    piece.piece_type = PieceType::Pawn(PawnData::default());
+                }
+            }
+        }
+    }
+
+    fn outcome(game: &Game<Self>) -> Option<GameStatus> {
+        if game.pieces(PieceColor::White).next().is_none() {
+            return Some(GameStatus::VariantWin(PieceColor::Black));
+        }
+        None
+    }
+
+    fn insufficient_material(_game: &Game<Self>) -> bool {
+        false
+    }
+}
+
+/// Losing chess: captures are compulsory, the king is an ordinary piece,
+/// and losing all your pieces (or being stalemated) wins
+#[derive(Debug, Default, Copy, Clone, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Antichess;
+
+impl Variant for Antichess {
+    const NAME: &'static str = "Antichess";
+    const NULL_MOVE_PRUNING: bool = false;
+    const CASTLING: bool = false;
+
+    fn starting_board() -> Board {
+        let mut board = Board::default();
+        board.clear_castling_rights();
+        board
+    }
+
+    fn promotion_pieces() -> &'static [PieceType] {
+        &[PieceType::Queen, PieceType::Rook(true), PieceType::Bishop, PieceType::Knight, PieceType::King(true)]
+    }
+
+    fn is_legal(_before: &Game<Self>, _mv: Move, _after: &Game<Self>) -> bool {
+        true
+    }
+
+    fn filter_legal(game: &Game<Self>, moves: Vec<Move>) -> Vec<Move> {
+        if moves.iter().any(|&mv| game.is_capture(mv)) {
+            moves.into_iter().filter(|&mv| game.is_capture(mv)).collect()
+        } else {
+            moves
+        }
+    }
+
+    fn outcome(game: &Game<Self>) -> Option<GameStatus> {
+        if game.pieces(game.turn).next().is_none() {
+            return Some(GameStatus::VariantWin(game.turn));
+        }
+        None
+    }
+
+    fn no_moves_status(game: &Game<Self>) -> GameStatus {
+        GameStatus::VariantWin(game.turn)
+    }
+
+    fn insufficient_material(_game: &Game<Self>) -> bool {
+        false
+    }
+}
+
+/// Every capture explodes, removing the capturer and all non-pawn pieces next to the
+/// capture square. Exploding the enemy king wins.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Atomic;
+
+impl Variant for Atomic {
+    const NAME: &'static str = "Atomic";
+
+    fn adjust_pseudo_legal(game: &Game<Self>, moves: &mut Vec<Move>) {
+        // Kings cannot capture, they would explode themselves
+        moves.retain(|&mv| {
+            !matches!(game.board.get(mv.start).map(|piece| piece.piece_type), Some(PieceType::King(_))) ||
+            !game.is_capture(mv)
+        });
+    }
+
+    fn is_legal(before: &Game<Self>, _mv: Move, after: &Game<Self>) -> bool {
+        let color = before.turn;
+        let Some(own_king) = after.king(color) else {
+            return false;
+        };
+        let Some(enemy_king) = after.king(color.swap()) else {
+            return true;
+        };
+        // Touching kings cannot give check, any capture would blow up both
+        let touching = (own_king.0 as i8 - enemy_king.0 as i8).abs() <= 1 &&
+                       (own_king.1 as i8 - enemy_king.1 as i8).abs() <= 1;
+        touching || !after.is_king_in_check(color)
+    }
+
+    fn king_square_attacked(game: &Game<Self>, color: PieceColor, square: Coordinates) -> bool {
+        let touching = game.king(color.swap()).is_some_and(|enemy_king| {
+            (square.0 as i8 - enemy_king.0 as i8).abs() <= 1 && (square.1 as i8 - enemy_king.1 as i8).abs() <= 1
+        });
+        !touching && game.is_coord_attacked_by_team(color.swap(), square)
+    }
+
+    fn after_move(game: &mut Game<Self>, mv: Move, captured: Option<Piece>) {
+        if captured.is_none() {
+            return;
+        }
+        *game.at(mv.end) = None;
+        for row in -1..=1 {
+            for col in -1..=1 {
+                let Some(square) = offset_coords(mv.end, row, col) else {
+                    continue;
+                };
+                if game.board.get(square).is_some_and(|piece| !matches!(piece.piece_type, PieceType::Pawn(_))) {
+                    *game.at(square) = None;
+                }
+            }
+        }
+    }
+
+    fn outcome(game: &Game<Self>) -> Option<GameStatus> {
+        [PieceColor::White, PieceColor::Black]
+            .into_iter()
+            .find(|&color| game.king(color).is_none())
+            .map(|color| GameStatus::VariantWin(color.swap()))
+    }
+
+    fn insufficient_material(_game: &Game<Self>) -> bool {
+        false
+    }
+}
+
+/// No pawns and no checks; the first king to reach the eighth rank wins.
+/// If Black can reach it on the very next move, the game is a draw instead.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RacingKings;
+
+impl Variant for RacingKings {
+    const NAME: &'static str = "Racing Kings";
+
+    fn starting_board() -> Board {
+        Game::<Standard>::from_fen(RACING_KINGS_FEN).unwrap().board
+    }
+
+    fn is_legal(before: &Game<Self>, _mv: Move, after: &Game<Self>) -> bool {
+        !after.is_king_in_check(before.turn) && !after.is_king_in_check(before.turn.swap())
+    }
+
+    fn outcome(game: &Game<Self>) -> Option<GameStatus> {
+        let on_goal = |color| game.king(color).is_some_and(|king| king.0 == 0);
+        match (on_goal(PieceColor::White), on_goal(PieceColor::Black)) {
+            (true, true) => Some(GameStatus::VariantDraw),
+            (false, true) => Some(GameStatus::VariantWin(PieceColor::Black)),
+            (true, false) => {
+                let black_can_follow = game.turn == PieceColor::Black &&
+                    game.legal_moves_ignoring_outcome().iter().any(|mv| {
+                        matches!(game.board.get(mv.start).map(|piece| piece.piece_type), Some(PieceType::King(_))) && mv.end.0 == 0
+                    });
+                if black_can_follow {
+                    None
+                } else {
+                    Some(GameStatus::VariantWin(PieceColor::White))
+                }
+            },
+            (false, false) => None,
+        }
+    }
+
+    fn insufficient_material(_game: &Game<Self>) -> bool {
+        false
+    }
+}
+
+/// Captured pieces join the capturer's pocket and can be dropped back onto the board
+#[derive(Debug, Default, Copy, Clone, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Crazyhouse;
+
+impl Variant for Crazyhouse {
+    const NAME: &'static str = "Crazyhouse";
+    const POCKETS: bool = true;
+
+    fn adjust_pseudo_legal(game: &Game<Self>, moves: &mut Vec<Move>) {
+        moves.extend(game.drop_moves());
+    }
+
+    fn after_move(game: &mut Game<Self>, _mv: Move, captured: Option<Piece>) {
+        if let Some(piece) = captured {
+            let turn = game.turn;
+            game.pocket_mut(turn).add(piece.piece_type);
+        }
+    }
+
+    fn insufficient_material(_game: &Game<Self>) -> bool {
+        false
+    }
+}
+
+/// One board of a Bughouse match. Drops work as in Crazyhouse, but captures are handed to
+/// the partner on the other board by `BughouseMatch` rather than kept.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bughouse;
+
+impl Variant for Bughouse {
+    const NAME: &'static str = "Bughouse";
+    const POCKETS: bool = true;
+
+    fn adjust_pseudo_legal(game: &Game<Self>, moves: &mut Vec<Move>) {
+        moves.extend(game.drop_moves());
+    }
+
+    fn insufficient_material(_game: &Game<Self>) -> bool {
+        false
+    }
+}
+
+// Functions
+
+pub fn color_index(color: PieceColor) -> usize {
+    match color {
+        PieceColor::White => 0,
+        PieceColor::Black => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play<V: Variant>(game: &mut Game<V>, text: &str) {
+        let mv: Move = text.parse().unwrap();
+        assert!(game.legal_moves().contains(&mv), "{text} is not legal");
+        game.play(mv).unwrap();
+    }
+
+    #[test]
+    fn a_king_on_the_hill_wins() {
+        let mut game = Game::<KingOfTheHill>::from_fen("4k3/8/8/8/8/3K4/8/8 w - - 0 1").unwrap();
+        assert_eq!(game.status(), GameStatus::Ongoing);
+        play(&mut game, "d3d4");
+        assert_eq!(game.status(), GameStatus::VariantWin(PieceColor::White));
+    }
+
+    #[test]
+    fn the_third_check_wins() {
+        let mut game = Game::<ThreeCheck>::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        game.variant.checks = [2, 0];
+        play(&mut game, "a1a8");
+        assert_eq!(game.variant.checks_given(PieceColor::White), 3);
+        assert_eq!(game.status(), GameStatus::VariantWin(PieceColor::White));
+    }
+
+    #[test]
+    fn taking_the_last_horde_pawn_wins() {
+        let mut game = Game::<Horde>::from_fen("4k3/8/8/8/8/1p6/P7/8 b - - 0 1").unwrap();
+        assert_eq!(game.status(), GameStatus::Ongoing);
+        play(&mut game, "b3a2");
+        assert_eq!(game.status(), GameStatus::VariantWin(PieceColor::Black));
+    }
+
+    #[test]
+    fn antichess_captures_are_forced_and_kings_never_castle() {
+        let mut game = Game::<Antichess>::default();
+        play(&mut game, "e2e3");
+        play(&mut game, "b7b5");
+        assert_eq!(game.legal_moves(), vec!["f1b5".parse().unwrap()]);
+
+        let game = Game::<Antichess>::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let king = Coordinates(7, 4);
+        assert!(!game.legal_moves().iter().any(|mv| mv.start == king && (mv.end.1 == 0 || mv.end.1 == 7)));
+        assert_eq!(game.to_fen(), "r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1");
+        assert!(Game::<Antichess>::chess960(518).to_fen().contains(" w - - "));
+    }
+
+    #[test]
+    fn atomic_captures_explode_the_pieces_around_them() {
+        let mut game = Game::<Atomic>::from_fen("4k3/8/2p1b3/3p4/4RN2/3B4/8/4K3 w - - 0 1").unwrap();
+        play(&mut game, "f4d5");
+        // The capturer, the captured pawn and the bishop and rook next to them are gone,
+        // the pawn next to them and the bishop two squares away are not
+        assert_eq!(game.to_fen(), "4k3/8/2p5/8/8/3B4/8/4K3 b - - 0 1");
+        assert_eq!(game.status(), GameStatus::Ongoing);
+    }
+
+    #[test]
+    fn the_first_king_on_the_eighth_rank_wins() {
+        let mut game = Game::<RacingKings>::from_fen("8/6K1/8/8/8/8/k7/8 w - - 0 1").unwrap();
+        play(&mut game, "g7g8");
+        assert_eq!(game.status(), GameStatus::VariantWin(PieceColor::White));
+
+        // Black reaching the goal right after draws
+        let mut game = Game::<RacingKings>::from_fen("8/1k4K1/8/8/8/8/8/8 w - - 0 1").unwrap();
+        play(&mut game, "g7g8");
+        assert_eq!(game.status(), GameStatus::Ongoing);
+        play(&mut game, "b7b8");
+        assert_eq!(game.status(), GameStatus::VariantDraw);
+    }
+}
@@ -0,0 +1,1302 @@
+//! Builds the small Syzygy tables in `syzygy/` that the tests probe: KQvK, KRvK, KPvK and
+//! KQvKR by retrograde analysis, and the drawn KBvK and KNvK. They follow the Syzygy file
+//! format but are compressed by this program, so they are not byte for byte the official
+//! files.
+//!
+//! `cargo run --release -- <directory> [--dump]` writes the tables, and with `--dump` text
+//! files of positions with their WDL and DTZ for checking a reader against.
+use std::collections::HashMap;
+use std::io::Write;
+
+const WHITE: usize = 0;
+const BLACK: usize = 1;
+const UNKNOWN: u8 = 0;
+const WIN: u8 = 1;
+const LOSS: u8 = 2;
+const DRAW: u8 = 3;
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+enum Kind {
+    Q,
+    R,
+    P,
+}
+
+fn rank(s: usize) -> i32 {
+    (s >> 3) as i32
+}
+fn file(s: usize) -> i32 {
+    (s & 7) as i32
+}
+fn adj(a: usize, b: usize) -> bool {
+    (rank(a) - rank(b)).abs() <= 1 && (file(a) - file(b)).abs() <= 1
+}
+
+fn king_targets(s: usize) -> Vec<usize> {
+    let mut v = vec![];
+    for dr in -1..=1 {
+        for df in -1..=1 {
+            if dr == 0 && df == 0 {
+                continue;
+            }
+            let (r, f) = (rank(s) + dr, file(s) + df);
+            if (0..8).contains(&r) && (0..8).contains(&f) {
+                v.push((r * 8 + f) as usize);
+            }
+        }
+    }
+    v
+}
+
+fn dirs(kind: Kind) -> Vec<(i32, i32)> {
+    let rook = vec![(1, 0), (-1, 0), (0, 1), (0, -1)];
+    match kind {
+        Kind::R => rook,
+        Kind::Q => {
+            let mut d = rook;
+            d.extend([(1, 1), (1, -1), (-1, 1), (-1, -1)]);
+            d
+        }
+        Kind::P => vec![],
+    }
+}
+
+/// Squares a slider on `from` reaches, stopping on (and including) any blocker
+fn slides(from: usize, kind: Kind, blockers: &[usize]) -> Vec<usize> {
+    let mut v = vec![];
+    for (dr, df) in dirs(kind) {
+        let (mut r, mut f) = (rank(from) + dr, file(from) + df);
+        while (0..8).contains(&r) && (0..8).contains(&f) {
+            let s = (r * 8 + f) as usize;
+            v.push(s);
+            if blockers.contains(&s) {
+                break;
+            }
+            r += dr;
+            f += df;
+        }
+    }
+    v
+}
+
+fn piece_attacks(kind: Kind, x: usize, target: usize, blockers: &[usize]) -> bool {
+    match kind {
+        Kind::P => (file(x) > 0 && x + 7 == target) || (file(x) < 7 && x + 9 == target),
+        _ => slides(x, kind, blockers).contains(&target),
+    }
+}
+
+fn idx3(stm: usize, wk: usize, bk: usize, x: usize) -> usize {
+    ((stm * 64 + wk) * 64 + bk) * 64 + x
+}
+
+fn legal(kind: Kind, stm: usize, wk: usize, bk: usize, x: usize) -> bool {
+    if wk == bk || wk == x || bk == x || adj(wk, bk) {
+        return false;
+    }
+    if kind == Kind::P && (rank(x) == 0 || rank(x) == 7) {
+        return false;
+    }
+    !(stm == WHITE && piece_attacks(kind, x, bk, &[wk]))
+}
+
+#[derive(Clone, Copy)]
+enum Succ {
+    /// Stays in this table, quiet move
+    Quiet(usize),
+    /// Zeroing move into a position whose WDL (for the side then to move) is known
+    Zero(u8),
+}
+
+struct Solved {
+    wdl: Vec<u8>,
+    dtz: Vec<u16>,
+    zeroing_win: Vec<bool>,
+}
+
+/// Moves of the side to move. `lookup(kind, wk, bk, sq)` gives the WDL for black to move
+/// after a promotion or pawn push.
+fn moves(
+    kind: Kind,
+    stm: usize,
+    wk: usize,
+    bk: usize,
+    x: usize,
+    lookup: &dyn Fn(Kind, usize, usize, usize) -> u8,
+) -> Vec<Succ> {
+    let mut v = vec![];
+    if stm == WHITE {
+        for t in king_targets(wk) {
+            if t != x && t != bk && !adj(t, bk) {
+                v.push(Succ::Quiet(idx3(BLACK, t, bk, x)));
+            }
+        }
+        match kind {
+            Kind::P => {
+                let one = x + 8;
+                if one != wk && one != bk {
+                    if rank(one) == 7 {
+                        v.push(Succ::Zero(lookup(Kind::Q, wk, bk, one)));
+                        v.push(Succ::Zero(lookup(Kind::R, wk, bk, one)));
+                        v.push(Succ::Zero(DRAW));
+                        v.push(Succ::Zero(DRAW));
+                    } else {
+                        v.push(Succ::Zero(lookup(Kind::P, wk, bk, one)));
+                        let two = x + 16;
+                        if rank(x) == 1 && two != wk && two != bk {
+                            v.push(Succ::Zero(lookup(Kind::P, wk, bk, two)));
+                        }
+                    }
+                }
+            }
+            _ => {
+                for t in slides(x, kind, &[wk, bk]) {
+                    if t != wk && t != bk {
+                        v.push(Succ::Quiet(idx3(BLACK, wk, bk, t)));
+                    }
+                }
+            }
+        }
+    } else {
+        for t in king_targets(bk) {
+            if t == wk || adj(t, wk) {
+                continue;
+            }
+            if t == x {
+                v.push(Succ::Zero(DRAW));
+                continue;
+            }
+            if piece_attacks(kind, x, t, &[wk]) {
+                continue;
+            }
+            v.push(Succ::Quiet(idx3(WHITE, wk, t, x)));
+        }
+    }
+    v
+}
+
+/// Result for the side to move after the zeroing move, which is the opponent's result
+fn flipped(w: u8) -> u8 {
+    match w {
+        WIN => LOSS,
+        LOSS => WIN,
+        other => other,
+    }
+}
+
+fn solve(kind: Kind, squares: &[usize], prev: &mut Solved, lookup: &dyn Fn(Kind, usize, usize, usize) -> u8) {
+    let mut positions = vec![];
+    for stm in 0..2 {
+        for wk in 0..64 {
+            for bk in 0..64 {
+                for &x in squares {
+                    if legal(kind, stm, wk, bk, x) {
+                        positions.push((stm, wk, bk, x));
+                    }
+                }
+            }
+        }
+    }
+    let succ: HashMap<usize, Vec<Succ>> = positions
+        .iter()
+        .map(|&(s, w, b, x)| (idx3(s, w, b, x), moves(kind, s, w, b, x, lookup)))
+        .collect();
+    let in_check = |w: usize, b: usize, x: usize| piece_attacks(kind, x, b, &[w]);
+
+    // Ply 1: mates, stalemates, winning zeroing moves and mates in one
+    for &(s, w, b, x) in &positions {
+        let i = idx3(s, w, b, x);
+        if s == BLACK && succ[&i].is_empty() {
+            if in_check(w, b, x) {
+                prev.wdl[i] = LOSS;
+                prev.dtz[i] = 1;
+            } else {
+                prev.wdl[i] = DRAW;
+            }
+        }
+    }
+    for &(s, w, b, x) in &positions {
+        let i = idx3(s, w, b, x);
+        if s != WHITE {
+            continue;
+        }
+        let mut win = false;
+        for m in &succ[&i] {
+            match *m {
+                Succ::Zero(r) => {
+                    if flipped(r) == WIN {
+                        win = true;
+                        prev.zeroing_win[i] = true;
+                    }
+                }
+                Succ::Quiet(q) => {
+                    if prev.wdl[q] == LOSS && prev.dtz[q] == 1 {
+                        win = true;
+                    }
+                }
+            }
+        }
+        if win {
+            prev.wdl[i] = WIN;
+            prev.dtz[i] = 1;
+        }
+    }
+
+    let mut n = 2u16;
+    loop {
+        let mut new_w = vec![];
+        let mut new_l = vec![];
+        for &(s, w, b, x) in &positions {
+            let i = idx3(s, w, b, x);
+            if prev.wdl[i] != UNKNOWN {
+                continue;
+            }
+            let moves = &succ[&i];
+            if s == WHITE {
+                if moves
+                    .iter()
+                    .any(|m| matches!(*m, Succ::Quiet(q) if prev.wdl[q] == LOSS && prev.dtz[q] == n - 1))
+                {
+                    new_w.push(i);
+                }
+            } else {
+                let mut max = 0;
+                let mut all = true;
+                for m in moves {
+                    match *m {
+                        Succ::Zero(r) => {
+                            if flipped(r) == LOSS {
+                                max = max.max(1)
+                            } else {
+                                all = false
+                            }
+                        }
+                        Succ::Quiet(q) => {
+                            if prev.wdl[q] == WIN {
+                                max = max.max(1 + prev.dtz[q])
+                            } else {
+                                all = false
+                            }
+                        }
+                    }
+                }
+                if all && max == n {
+                    new_l.push(i);
+                }
+            }
+        }
+        if new_w.is_empty() && new_l.is_empty() {
+            break;
+        }
+        for i in new_w {
+            prev.wdl[i] = WIN;
+            prev.dtz[i] = n;
+        }
+        for i in new_l {
+            prev.wdl[i] = LOSS;
+            prev.dtz[i] = n;
+        }
+        n += 1;
+    }
+    for &(s, w, b, x) in &positions {
+        let i = idx3(s, w, b, x);
+        if prev.wdl[i] == UNKNOWN {
+            prev.wdl[i] = DRAW;
+        }
+    }
+}
+
+fn new_solved() -> Solved {
+    Solved {
+        wdl: vec![UNKNOWN; 2 * 64 * 64 * 64],
+        dtz: vec![0; 2 * 64 * 64 * 64],
+        zeroing_win: vec![false; 2 * 64 * 64 * 64],
+    }
+}
+
+// KQvKR
+
+/// White king, black king, white queen and black rook, with the side to move
+#[derive(Clone, Copy)]
+struct Four {
+    stm: usize,
+    wk: usize,
+    bk: usize,
+    q: usize,
+    r: usize,
+}
+
+const FOUR_SIZE: usize = 2 * 64 * 64 * 64 * 64;
+
+impl Four {
+    fn index(self) -> usize {
+        (((self.stm * 64 + self.wk) * 64 + self.bk) * 64 + self.q) * 64 + self.r
+    }
+
+    fn from_index(i: usize) -> Four {
+        Four {
+            stm: i >> 24,
+            wk: (i >> 18) & 63,
+            bk: (i >> 12) & 63,
+            q: (i >> 6) & 63,
+            r: i & 63,
+        }
+    }
+
+    fn occupied(self) -> [usize; 4] {
+        [self.wk, self.bk, self.q, self.r]
+    }
+
+    /// Whether the side not to move is in check, or the pieces overlap
+    fn legal(self) -> bool {
+        let sq = self.occupied();
+        if (0..4).any(|i| (i + 1..4).any(|j| sq[i] == sq[j])) || adj(self.wk, self.bk) {
+            return false;
+        }
+        if self.stm == WHITE {
+            !line_attack(Kind::Q, self.q, self.bk, &sq)
+        } else {
+            !line_attack(Kind::R, self.r, self.wk, &sq)
+        }
+    }
+}
+
+/// Whether a slider on `from` attacks `target`, the squares in `occupied` blocking it
+fn line_attack(kind: Kind, from: usize, target: usize, occupied: &[usize]) -> bool {
+    let (dr, df) = (rank(target) - rank(from), file(target) - file(from));
+    let straight = dr == 0 || df == 0;
+    let diagonal = dr.abs() == df.abs();
+    if from == target || !(straight || (kind == Kind::Q && diagonal)) {
+        return false;
+    }
+    let (sr, sf) = (dr.signum(), df.signum());
+    let (mut r, mut f) = (rank(from) + sr, file(from) + sf);
+    while (r, f) != (rank(target), file(target)) {
+        if occupied.contains(&((r * 8 + f) as usize)) {
+            return false;
+        }
+        r += sr;
+        f += sf;
+    }
+    true
+}
+
+/// Empty squares a slider on `from` reaches, stopping before any piece in `occupied`.
+/// Sliders move the same way backwards, so this also gives the squares it came from.
+fn slide_quiet(from: usize, kind: Kind, occupied: &[usize]) -> Vec<usize> {
+    slides(from, kind, occupied)
+        .into_iter()
+        .filter(|s| !occupied.contains(s))
+        .collect()
+}
+
+/// Quiet moves and captures of a position. Captures leave KQvK or KRvK, and `capture`
+/// gives the WDL for the side to move after them.
+fn four_moves(p: Four, capture: &dyn Fn(Four) -> u8) -> (Vec<Four>, Vec<u8>) {
+    let mut quiet = vec![];
+    let mut captures = vec![];
+    let occupied = p.occupied();
+    if p.stm == WHITE {
+        for t in king_targets(p.wk) {
+            if t == p.q || adj(t, p.bk) {
+                continue;
+            }
+            let next = Four { stm: BLACK, wk: t, ..p };
+            if t == p.r {
+                captures.push(capture(next));
+            } else if next.legal() {
+                quiet.push(next);
+            }
+        }
+        for t in slides(p.q, Kind::Q, &occupied) {
+            let next = Four { stm: BLACK, q: t, ..p };
+            if t == p.r {
+                captures.push(capture(next));
+            } else if t != p.wk && t != p.bk && next.legal() {
+                quiet.push(next);
+            }
+        }
+    } else {
+        for t in king_targets(p.bk) {
+            if t == p.r || adj(t, p.wk) {
+                continue;
+            }
+            let next = Four { stm: WHITE, bk: t, ..p };
+            if t == p.q {
+                captures.push(capture(next));
+            } else if next.legal() {
+                quiet.push(next);
+            }
+        }
+        for t in slides(p.r, Kind::R, &occupied) {
+            let next = Four { stm: WHITE, r: t, ..p };
+            if t == p.q {
+                captures.push(capture(next));
+            } else if t != p.wk && t != p.bk && next.legal() {
+                quiet.push(next);
+            }
+        }
+    }
+    (quiet, captures)
+}
+
+/// Positions with a quiet move to `p`, by the side that is not to move in it
+fn four_unmoves(p: Four) -> Vec<Four> {
+    let occupied = p.occupied();
+    let mut v = vec![];
+    if p.stm == BLACK {
+        for s in king_targets(p.wk) {
+            if !occupied.contains(&s) && !adj(s, p.bk) {
+                v.push(Four { stm: WHITE, wk: s, ..p });
+            }
+        }
+        for s in slide_quiet(p.q, Kind::Q, &occupied) {
+            v.push(Four { stm: WHITE, q: s, ..p });
+        }
+    } else {
+        for s in king_targets(p.bk) {
+            if !occupied.contains(&s) && !adj(s, p.wk) {
+                v.push(Four { stm: BLACK, bk: s, ..p });
+            }
+        }
+        for s in slide_quiet(p.r, Kind::R, &occupied) {
+            v.push(Four { stm: BLACK, r: s, ..p });
+        }
+    }
+    v.retain(|q| q.legal());
+    v
+}
+
+struct SolvedFour {
+    wdl: Vec<u8>,
+    dtz: Vec<u8>,
+    zeroing: Vec<bool>,
+}
+
+/// Retrograde analysis of KQvKR. Captures zero the count, so the DTZ is the number of
+/// plies to mate or to the capture that keeps the result.
+fn solve_four(queen: &Solved, rook: &Solved) -> SolvedFour {
+    // After capturing the rook black is to move in KQvK, after capturing the queen white
+    // is to move against a black rook, which is KRvK with the colors swapped
+    let capture = |next: Four| -> u8 {
+        if next.stm == BLACK {
+            queen.wdl[idx3(BLACK, next.wk, next.bk, next.q)]
+        } else {
+            rook.wdl[idx3(BLACK, next.bk ^ 56, next.wk ^ 56, next.r ^ 56)]
+        }
+    };
+
+    let mut s = SolvedFour {
+        wdl: vec![UNKNOWN; FOUR_SIZE],
+        dtz: vec![0; FOUR_SIZE],
+        zeroing: vec![false; FOUR_SIZE],
+    };
+    let mut quiet_left = vec![0u8; FOUR_SIZE];
+    let mut capture_draws = vec![false; FOUR_SIZE];
+    let mut frontier: Vec<Vec<u32>> = vec![vec![], vec![]];
+    for i in 0..FOUR_SIZE {
+        let p = Four::from_index(i);
+        if !p.legal() {
+            continue;
+        }
+        let (quiet, captures) = four_moves(p, &capture);
+        // The capture results are for the side to move after it
+        let best = captures
+            .iter()
+            .map(|&r| flipped(r))
+            .fold(None, |best: Option<u8>, r| match (best, r) {
+                (_, WIN) | (Some(WIN), _) => Some(WIN),
+                (_, DRAW) | (Some(DRAW), _) => Some(DRAW),
+                _ => Some(LOSS),
+            });
+        quiet_left[i] = quiet.len() as u8;
+        if best == Some(WIN) {
+            s.wdl[i] = WIN;
+            s.dtz[i] = 1;
+            s.zeroing[i] = true;
+            frontier[1].push(i as u32);
+        } else if quiet.is_empty() && best.is_none() {
+            let in_check = if p.stm == WHITE {
+                line_attack(Kind::R, p.r, p.wk, &p.occupied())
+            } else {
+                line_attack(Kind::Q, p.q, p.bk, &p.occupied())
+            };
+            if in_check {
+                // Mate is reached at once, but probes read it as a loss in one ply
+                s.wdl[i] = LOSS;
+                s.dtz[i] = 1;
+                frontier[0].push(i as u32);
+            } else {
+                s.wdl[i] = DRAW;
+            }
+        } else if quiet.is_empty() {
+            // Only captures, none of them winning
+            s.wdl[i] = if best == Some(DRAW) { DRAW } else { LOSS };
+            s.dtz[i] = 1;
+            s.zeroing[i] = true;
+            if s.wdl[i] == LOSS {
+                frontier[1].push(i as u32);
+            }
+        }
+        capture_draws[i] = best == Some(DRAW);
+    }
+
+    let mut d = 0;
+    while d < frontier.len() {
+        let current = std::mem::take(&mut frontier[d]);
+        if d + 2 > frontier.len() {
+            frontier.push(vec![]);
+        }
+        for &q in &current {
+            let child_wdl = s.wdl[q as usize];
+            for p in four_unmoves(Four::from_index(q as usize)) {
+                let i = p.index();
+                if s.wdl[i] != UNKNOWN {
+                    continue;
+                }
+                if child_wdl == LOSS {
+                    s.wdl[i] = WIN;
+                    s.dtz[i] = d as u8 + 1;
+                    frontier[d + 1].push(i as u32);
+                } else {
+                    quiet_left[i] -= 1;
+                    if quiet_left[i] == 0 && !capture_draws[i] {
+                        s.wdl[i] = LOSS;
+                        s.dtz[i] = d as u8 + 1;
+                        frontier[d + 1].push(i as u32);
+                    }
+                }
+            }
+        }
+        d += 1;
+        if frontier[d..].iter().all(|f| f.is_empty()) {
+            break;
+        }
+    }
+    for i in 0..FOUR_SIZE {
+        if s.wdl[i] == UNKNOWN && Four::from_index(i).legal() {
+            s.wdl[i] = DRAW;
+        }
+    }
+    s
+}
+
+// Index encoding
+
+struct Maps {
+    b1h1h7: [u64; 64],
+    a1d1d4: [u64; 64],
+    map_pawns: [usize; 64],
+    lead_pawn_idx: [u64; 64],
+}
+
+fn off(s: usize) -> i32 {
+    rank(s) - file(s)
+}
+
+fn maps() -> Maps {
+    let mut m = Maps {
+        b1h1h7: [0; 64],
+        a1d1d4: [0; 64],
+        map_pawns: [0; 64],
+        lead_pawn_idx: [0; 64],
+    };
+    let mut code = 0;
+    for s in 0..64 {
+        if off(s) < 0 {
+            m.b1h1h7[s] = code;
+            code += 1;
+        }
+    }
+    code = 0;
+    let mut diag = vec![];
+    for s in 0..=27 {
+        if file(s) > 3 {
+            continue;
+        }
+        if off(s) < 0 {
+            m.a1d1d4[s] = code;
+            code += 1;
+        } else if off(s) == 0 {
+            diag.push(s);
+        }
+    }
+    for s in diag {
+        m.a1d1d4[s] = code;
+        code += 1;
+    }
+    let mut available = 47;
+    for f in 0..4 {
+        for r in 1..7 {
+            let s = r * 8 + f;
+            m.map_pawns[s] = available;
+            m.map_pawns[s ^ 7] = available - 1;
+            available = available.saturating_sub(2);
+            m.lead_pawn_idx[s] = (r - 1) as u64;
+        }
+    }
+    m
+}
+
+/// Mirrors a pawnless position so the first piece is in a1-d1-d4 and the first of
+/// the three leading pieces off the long diagonal is below it
+fn normalise(s: &mut [usize]) {
+    if file(s[0]) > 3 {
+        s.iter_mut().for_each(|x| *x ^= 7);
+    }
+    if rank(s[0]) > 3 {
+        s.iter_mut().for_each(|x| *x ^= 56);
+    }
+    for i in 0..3 {
+        if off(s[i]) == 0 {
+            continue;
+        }
+        if off(s[i]) > 0 {
+            for x in s[i..].iter_mut() {
+                *x = ((*x >> 3) | (*x << 3)) & 63;
+            }
+        }
+        break;
+    }
+}
+
+/// Index of three unique pieces in a pawnless table, squares in table order
+fn index_unique(m: &Maps, sq: [usize; 3]) -> u64 {
+    let mut s = sq;
+    normalise(&mut s);
+    index_leading(m, s)
+}
+
+/// Index of three unique pieces in a pawnless table, which can be behind a fourth piece
+/// of another kind: the fourth is numbered among the 61 squares the others leave free.
+fn index_unique4(m: &Maps, sq: [usize; 4]) -> u64 {
+    let mut s = sq;
+    normalise(&mut s);
+    let free = s[3] - s[..3].iter().filter(|&&x| x < s[3]).count();
+    index_leading(m, [s[0], s[1], s[2]]) + free as u64 * 31332
+}
+
+/// The 31332 index of three normalised unique pieces
+fn index_leading(m: &Maps, s: [usize; 3]) -> u64 {
+    let a1 = (s[1] > s[0]) as u64;
+    let a2 = (s[2] > s[0]) as u64 + (s[2] > s[1]) as u64;
+    let (s0, s1, s2) = (s[0] as u64, s[1] as u64, s[2] as u64);
+    if off(s[0]) != 0 {
+        (m.a1d1d4[s[0]] * 63 + (s1 - a1)) * 62 + s2 - a2
+    } else if off(s[1]) != 0 {
+        (6 * 63 + (s0 >> 3) * 28 + m.b1h1h7[s[1]]) * 62 + s2 - a2
+    } else if off(s[2]) != 0 {
+        6 * 63 * 62 + 4 * 28 * 62 + (s0 >> 3) * 7 * 28 + ((s1 >> 3) - a1) * 28 + m.b1h1h7[s[2]]
+    } else {
+        6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + (s0 >> 3) * 7 * 6 + ((s1 >> 3) - a1) * 6 + ((s2 >> 3) - a2)
+    }
+}
+
+/// File and index of pawn, white king, black king; groups in that order
+fn index_pawn(m: &Maps, sq: [usize; 3]) -> (usize, u64) {
+    let mut s = sq;
+    if file(s[0]) > 3 {
+        s.iter_mut().for_each(|x| *x ^= 7);
+    }
+    let f = file(s[0]) as usize;
+    let lead = m.lead_pawn_idx[s[0]];
+    let k1 = s[1] as u64 - (s[0] < s[1]) as u64;
+    let k2 = s[2] as u64 - (s[0] < s[2]) as u64 - (s[1] < s[2]) as u64;
+    (f, lead + k1 * 6 + k2 * 6 * 63)
+}
+
+// Compression
+
+struct Encoded {
+    header: Vec<u8>,
+    sparse: Vec<u8>,
+    lengths: Vec<u8>,
+    data: Vec<u8>,
+}
+
+const BLOCK_LOG: u8 = 8;
+const SPAN_LOG: u8 = 10;
+
+fn compress(values: &[i32], flags: u8) -> Encoded {
+    // Don't care entries continue the previous value
+    let first = values.iter().copied().find(|&v| v >= 0).unwrap_or(0);
+    let mut last = first;
+    let filled: Vec<u16> = values
+        .iter()
+        .map(|&v| {
+            if v >= 0 {
+                last = v;
+            }
+            last as u16
+        })
+        .collect();
+    let mut distinct: Vec<u16> = filled.clone();
+    distinct.sort();
+    distinct.dedup();
+    if distinct.len() == 1 {
+        return Encoded {
+            header: vec![flags | 128, distinct[0] as u8],
+            sparse: vec![],
+            lengths: vec![],
+            data: vec![],
+        };
+    }
+
+    // Recursive pairing
+    let mut pair: Vec<(u16, u16)> = distinct.iter().map(|&v| (v, 0xfff)).collect();
+    let mut leaf: Vec<bool> = vec![true; distinct.len()];
+    let mut explen: Vec<usize> = vec![1; distinct.len()];
+    let mut seq: Vec<u16> = filled
+        .iter()
+        .map(|v| distinct.binary_search(v).unwrap() as u16)
+        .collect();
+    while pair.len() < 4000 {
+        let mut counts: HashMap<(u16, u16), usize> = HashMap::new();
+        let mut i = 0;
+        while i + 1 < seq.len() {
+            let p = (seq[i], seq[i + 1]);
+            if explen[p.0 as usize] + explen[p.1 as usize] <= 256 {
+                *counts.entry(p).or_default() += 1;
+            }
+            // Count runs of one symbol without overlap
+            i += if p.0 == p.1 && i + 2 < seq.len() && seq[i + 2] == p.0 {
+                2
+            } else {
+                1
+            };
+        }
+        let Some((&best, &count)) = counts.iter().max_by_key(|&(p, c)| (*c, std::cmp::Reverse(*p))) else {
+            break;
+        };
+        if count < 8 {
+            break;
+        }
+        let new = pair.len() as u16;
+        pair.push(best);
+        leaf.push(false);
+        explen.push(explen[best.0 as usize] + explen[best.1 as usize]);
+        let mut out = Vec::with_capacity(seq.len());
+        let mut i = 0;
+        while i < seq.len() {
+            if i + 1 < seq.len() && (seq[i], seq[i + 1]) == best {
+                out.push(new);
+                i += 2;
+            } else {
+                out.push(seq[i]);
+                i += 1;
+            }
+        }
+        seq = out;
+    }
+
+    // Huffman code lengths
+    let symbols = pair.len();
+    let mut freq = vec![0usize; symbols];
+    for &s in &seq {
+        freq[s as usize] += 1;
+    }
+    let mut coded: Vec<usize> = (0..symbols).filter(|&s| freq[s] > 0).collect();
+    if coded.len() == 1 {
+        coded.push((0..symbols).find(|&s| s != coded[0]).unwrap());
+    }
+    let mut lens = vec![0u32; symbols];
+    {
+        // nodes: (weight, members)
+        let mut nodes: Vec<(usize, Vec<usize>)> = coded.iter().map(|&s| (freq[s], vec![s])).collect();
+        while nodes.len() > 1 {
+            nodes.sort_by_key(|n| std::cmp::Reverse(n.0));
+            let a = nodes.pop().unwrap();
+            let b = nodes.pop().unwrap();
+            for &s in a.1.iter().chain(b.1.iter()) {
+                lens[s] += 1;
+            }
+            let mut members = a.1;
+            members.extend(b.1);
+            nodes.push((a.0 + b.0, members));
+        }
+    }
+    let max_len = coded.iter().map(|&s| lens[s]).max().unwrap();
+    let min_len = coded.iter().map(|&s| lens[s]).min().unwrap();
+    assert!(max_len <= 32);
+
+    // Canonical numbering: longest codes first
+    let mut order = coded.clone();
+    order.sort_by_key(|&s| (std::cmp::Reverse(lens[s]), s));
+    let mut number = vec![usize::MAX; symbols];
+    for (n, &s) in order.iter().enumerate() {
+        number[s] = n;
+    }
+    let mut next = order.len();
+    for n in number.iter_mut() {
+        if *n == usize::MAX {
+            *n = next;
+            next += 1;
+        }
+    }
+    let count_of = |l: u32| order.iter().filter(|&&s| lens[s] == l).count() as u64;
+    let nlen = (max_len - min_len + 1) as usize;
+    let mut lowest = vec![0u64; nlen];
+    let mut base = vec![0u64; nlen];
+    // index i <-> length min_len + i; lowest symbols for the longest codes
+    let mut acc = 0u64;
+    for i in (0..nlen).rev() {
+        lowest[i] = acc;
+        acc += count_of(min_len + i as u32);
+    }
+    for i in (0..nlen - 1).rev() {
+        let sum = base[i + 1] + count_of(min_len + i as u32 + 1);
+        assert!(sum.is_multiple_of(2));
+        base[i] = sum / 2;
+    }
+    let code_of = |s: usize| -> (u64, u32) {
+        let l = lens[s];
+        let i = (l - min_len) as usize;
+        (base[i] + (number[s] as u64 - lowest[i]), l)
+    };
+
+    // Blocks
+    let block_size = 1usize << BLOCK_LOG;
+    let limit = (block_size - 8) * 8;
+    let mut blocks: Vec<(Vec<(u64, u32)>, usize)> = vec![];
+    let mut cur: Vec<(u64, u32)> = vec![];
+    let (mut bits, mut count) = (0usize, 0usize);
+    for &s in &seq {
+        let c = code_of(s as usize);
+        let e = explen[s as usize];
+        if bits + c.1 as usize > limit || count + e > 60000 {
+            blocks.push((std::mem::take(&mut cur), count));
+            bits = 0;
+            count = 0;
+        }
+        cur.push(c);
+        bits += c.1 as usize;
+        count += e;
+    }
+    blocks.push((cur, count));
+
+    let mut data = vec![];
+    for (codes, _) in &blocks {
+        let mut bytes = vec![0u8; block_size];
+        let mut pos = 0usize;
+        for &(code, len) in codes {
+            for b in (0..len).rev() {
+                if (code >> b) & 1 == 1 {
+                    bytes[pos / 8] |= 0x80 >> (pos % 8);
+                }
+                pos += 1;
+            }
+        }
+        data.extend(bytes);
+    }
+    let mut lengths = vec![];
+    let mut starts = vec![];
+    let mut start = 0usize;
+    for (_, count) in &blocks {
+        starts.push(start);
+        lengths.extend(((count - 1) as u16).to_le_bytes());
+        start += count;
+    }
+    let span = 1usize << SPAN_LOG;
+    let entries = values.len().div_ceil(span);
+    let mut sparse = vec![];
+    for k in 0..entries {
+        let target = k * span + span / 2;
+        let b = starts.iter().rposition(|&s| s <= target).unwrap();
+        let offset = target - starts[b];
+        assert!(offset < 65536);
+        sparse.extend((b as u32).to_le_bytes());
+        sparse.extend((offset as u16).to_le_bytes());
+    }
+
+    let mut header = vec![flags, BLOCK_LOG, SPAN_LOG, 0];
+    header.extend((blocks.len() as u32).to_le_bytes());
+    header.push(max_len as u8);
+    header.push(min_len as u8);
+    for &l in &lowest {
+        header.extend((l as u16).to_le_bytes());
+    }
+    header.extend((symbols as u16).to_le_bytes());
+    let mut btree = vec![[0u8; 3]; symbols];
+    for s in 0..symbols {
+        let (l, r) = if leaf[s] {
+            (pair[s].0 as usize, 0xfff)
+        } else {
+            (number[pair[s].0 as usize], number[pair[s].1 as usize])
+        };
+        btree[number[s]] = [(l & 0xff) as u8, ((l >> 8) | ((r & 0xf) << 4)) as u8, (r >> 4) as u8];
+    }
+    for b in btree {
+        header.extend(b);
+    }
+    if symbols % 2 == 1 {
+        header.push(0);
+    }
+    Encoded {
+        header,
+        sparse,
+        lengths,
+        data,
+    }
+}
+
+/// `files[f][side]` value arrays; `pieces` per file as codes (side 0 only, high nibble copies)
+fn write_table(path: &str, magic: [u8; 4], pawns: bool, files: &[Vec<(Vec<i32>, u8)>], pieces: &[u8]) {
+    let mut out = magic.to_vec();
+    out.push(1 | if pawns { 2 } else { 0 });
+    for _ in files {
+        // The leading group comes first in the index for both sides, then the pieces
+        // in the same order for both
+        let order = 0x00;
+        out.push(order);
+        out.extend(pieces.iter().map(|&p| p | (p << 4)));
+    }
+    if out.len() % 2 == 1 {
+        out.push(0);
+    }
+    let encoded: Vec<Vec<Encoded>> = files
+        .iter()
+        .map(|sides| sides.iter().map(|(v, fl)| compress(v, *fl)).collect())
+        .collect();
+    for f in &encoded {
+        for e in f {
+            out.extend(&e.header);
+        }
+    }
+    if magic[0] == 0xd7 && out.len() % 2 == 1 {
+        out.push(0);
+    }
+    for f in &encoded {
+        for e in f {
+            out.extend(&e.sparse);
+        }
+    }
+    for f in &encoded {
+        for e in f {
+            out.extend(&e.lengths);
+        }
+    }
+    for f in &encoded {
+        for e in f {
+            while !out.len().is_multiple_of(64) {
+                out.push(0);
+            }
+            out.extend(&e.data);
+        }
+    }
+    std::fs::write(path, out).unwrap();
+}
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+
+fn wdl_value(w: u8) -> i32 {
+    match w {
+        WIN => 4,
+        LOSS => 0,
+        _ => 2,
+    }
+}
+
+fn main() {
+    let out_dir = std::env::args().nth(1).expect("usage: syzygy_gen <directory> [--dump]");
+    let dumping = std::env::args().any(|arg| arg == "--dump");
+    let m = maps();
+    let all: Vec<usize> = (0..64).collect();
+    let none = |_: Kind, _: usize, _: usize, _: usize| -> u8 { panic!() };
+
+    let mut solved = HashMap::new();
+    for (kind, name, code) in [(Kind::Q, "KQvK", 5u8), (Kind::R, "KRvK", 4u8)] {
+        let mut s = new_solved();
+        solve(kind, &all, &mut s, &none);
+        let mut wdl = [vec![-1; 31332], vec![-1; 31332]];
+        let mut dtz = vec![-1; 31332];
+        let mut max = [0u16; 2];
+        for stm in 0..2 {
+            for wk in 0..64 {
+                for bk in 0..64 {
+                    for x in 0..64 {
+                        if !legal(kind, stm, wk, bk, x) {
+                            continue;
+                        }
+                        let i = idx3(stm, wk, bk, x);
+                        let idx = index_unique(&m, [wk, bk, x]) as usize;
+                        let v = wdl_value(s.wdl[i]);
+                        assert!(wdl[stm][idx] == -1 || wdl[stm][idx] == v, "{name} wdl clash");
+                        wdl[stm][idx] = v;
+                        if s.wdl[i] != DRAW {
+                            max[stm] = max[stm].max(s.dtz[i]);
+                        }
+                        if stm == WHITE && s.wdl[i] == WIN && !s.zeroing_win[i] {
+                            let d = s.dtz[i] as i32 - 1;
+                            assert!(dtz[idx] == -1 || dtz[idx] == d, "{name} dtz clash");
+                            dtz[idx] = d;
+                        }
+                    }
+                }
+            }
+        }
+        eprintln!(
+            "{name}: longest white win {} plies, black loss {} plies",
+            max[0], max[1]
+        );
+        write_table(
+            &format!("{out_dir}/{name}.rtbw"),
+            WDL_MAGIC,
+            false,
+            &[vec![(wdl[0].clone(), 0), (wdl[1].clone(), 0)]],
+            &[6, 14, code],
+        );
+        write_table(
+            &format!("{out_dir}/{name}.rtbz"),
+            DTZ_MAGIC,
+            false,
+            &[vec![(dtz, 4 | 8)]],
+            &[6, 14, code],
+        );
+        if dumping {
+            dump(&format!("{out_dir}/{name}.txt"), kind, &s);
+        }
+        solved.insert(kind, s);
+    }
+
+    for (name, code) in [("KBvK", 3u8), ("KNvK", 2u8)] {
+        write_table(
+            &format!("{out_dir}/{name}.rtbw"),
+            WDL_MAGIC,
+            false,
+            &[vec![(vec![2; 31332], 0), (vec![2; 31332], 0)]],
+            &[6, 14, code],
+        );
+        write_table(
+            &format!("{out_dir}/{name}.rtbz"),
+            DTZ_MAGIC,
+            false,
+            &[vec![(vec![-1; 31332], 4 | 8)]],
+            &[6, 14, code],
+        );
+    }
+
+    // KQvKR, with white (the queen) to move in the DTZ table
+    let four = solve_four(&solved[&Kind::Q], &solved[&Kind::R]);
+    let size = 31332 * 61;
+    let mut wdl = [vec![-1; size], vec![-1; size]];
+    let mut dtz = vec![-1; size];
+    let mut max = [0u8; 2];
+    let mut longest = [0; 2];
+    for i in 0..FOUR_SIZE {
+        let p = Four::from_index(i);
+        if !p.legal() {
+            continue;
+        }
+        let idx = index_unique4(&m, p.occupied()) as usize;
+        let v = wdl_value(four.wdl[i]);
+        assert!(wdl[p.stm][idx] == -1 || wdl[p.stm][idx] == v, "KQvKR wdl clash");
+        wdl[p.stm][idx] = v;
+        if four.wdl[i] != DRAW && four.dtz[i] > max[p.stm] {
+            max[p.stm] = four.dtz[i];
+            longest[p.stm] = i;
+        }
+        if p.stm == WHITE && four.wdl[i] != DRAW && !four.zeroing[i] {
+            let d = four.dtz[i] as i32 - 1;
+            assert!(dtz[idx] == -1 || dtz[idx] == d, "KQvKR dtz clash");
+            dtz[idx] = d;
+        }
+    }
+    // Wins past 100 plies would need the cursed win values
+    assert!(max[0] < 100 && max[1] < 100);
+    for stm in [WHITE, BLACK] {
+        eprintln!(
+            "KQvKR: longest result {} plies in {}",
+            max[stm],
+            fen_four(Four::from_index(longest[stm]))
+        );
+    }
+    write_table(
+        &format!("{out_dir}/KQvKR.rtbw"),
+        WDL_MAGIC,
+        false,
+        &[vec![(wdl[0].clone(), 0), (wdl[1].clone(), 0)]],
+        &[6, 14, 5, 12],
+    );
+    write_table(
+        &format!("{out_dir}/KQvKR.rtbz"),
+        DTZ_MAGIC,
+        false,
+        &[vec![(dtz, 4 | 8)]],
+        &[6, 14, 5, 12],
+    );
+    if dumping {
+        dump_four(&format!("{out_dir}/KQvKR.txt"), &four);
+    }
+
+    // KPvK, pawn ranks from the seventh down
+    let mut s = new_solved();
+    for r in (1..7).rev() {
+        let squares: Vec<usize> = (0..8).map(|f| r * 8 + f).collect();
+        let done = std::mem::replace(&mut s, new_solved());
+        let lookup = |kind: Kind, wk: usize, bk: usize, x: usize| -> u8 {
+            match kind {
+                Kind::P => done.wdl[idx3(BLACK, wk, bk, x)],
+                other => solved[&other].wdl[idx3(BLACK, wk, bk, x)],
+            }
+        };
+        let mut next = Solved {
+            wdl: done.wdl.clone(),
+            dtz: done.dtz.clone(),
+            zeroing_win: done.zeroing_win.clone(),
+        };
+        solve(Kind::P, &squares, &mut next, &lookup);
+        s = next;
+    }
+    let mut wdl = vec![vec![vec![-1; 23436]; 2]; 4];
+    let mut dtz = vec![vec![-1; 23436]; 4];
+    let mut max = [0u16; 2];
+    for stm in 0..2 {
+        for wk in 0..64 {
+            for bk in 0..64 {
+                for p in 8..56 {
+                    if !legal(Kind::P, stm, wk, bk, p) {
+                        continue;
+                    }
+                    let i = idx3(stm, wk, bk, p);
+                    let (f, idx) = index_pawn(&m, [p, wk, bk]);
+                    let idx = idx as usize;
+                    let v = wdl_value(s.wdl[i]);
+                    assert!(wdl[f][stm][idx] == -1 || wdl[f][stm][idx] == v, "KPvK wdl clash");
+                    wdl[f][stm][idx] = v;
+                    if s.wdl[i] != DRAW {
+                        max[stm] = max[stm].max(s.dtz[i]);
+                    }
+                    if stm == WHITE && s.wdl[i] == WIN && !s.zeroing_win[i] {
+                        let d = s.dtz[i] as i32 - 1;
+                        assert!(dtz[f][idx] == -1 || dtz[f][idx] == d, "KPvK dtz clash");
+                        dtz[f][idx] = d;
+                    }
+                }
+            }
+        }
+    }
+    eprintln!("KPvK: longest white win {} plies, black loss {} plies", max[0], max[1]);
+    let wfiles: Vec<Vec<(Vec<i32>, u8)>> = wdl
+        .iter()
+        .map(|sides| sides.iter().map(|v| (v.clone(), 0)).collect())
+        .collect();
+    write_table(&format!("{out_dir}/KPvK.rtbw"), WDL_MAGIC, true, &wfiles, &[1, 6, 14]);
+    let zfiles: Vec<Vec<(Vec<i32>, u8)>> = dtz.iter().map(|v| vec![(v.clone(), 4 | 8)]).collect();
+    write_table(&format!("{out_dir}/KPvK.rtbz"), DTZ_MAGIC, true, &zfiles, &[1, 6, 14]);
+    if dumping {
+        dump(&format!("{out_dir}/KPvK.txt"), Kind::P, &s);
+    }
+}
+
+/// Every legal position as a FEN with its WDL and DTZ, for checking the probe code
+fn dump(path: &str, kind: Kind, s: &Solved) {
+    let mut out = std::io::BufWriter::new(std::fs::File::create(path).unwrap());
+    let letter = match kind {
+        Kind::Q => 'Q',
+        Kind::R => 'R',
+        Kind::P => 'P',
+    };
+    for stm in 0..2 {
+        for wk in 0..64 {
+            for bk in 0..64 {
+                for x in 0..64 {
+                    if !legal(kind, stm, wk, bk, x) {
+                        continue;
+                    }
+                    let i = idx3(stm, wk, bk, x);
+                    let mut fen = String::new();
+                    for r in (0..8).rev() {
+                        let mut empty = 0;
+                        for f in 0..8 {
+                            let sq = r * 8 + f;
+                            let c = if sq == wk {
+                                Some('K')
+                            } else if sq == bk {
+                                Some('k')
+                            } else if sq == x {
+                                Some(letter)
+                            } else {
+                                None
+                            };
+                            match c {
+                                Some(c) => {
+                                    if empty > 0 {
+                                        fen.push_str(&empty.to_string());
+                                        empty = 0;
+                                    }
+                                    fen.push(c);
+                                }
+                                None => empty += 1,
+                            }
+                        }
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                        }
+                        if r > 0 {
+                            fen.push('/');
+                        }
+                    }
+                    let side = if stm == WHITE { 'w' } else { 'b' };
+                    let (w, d) = match s.wdl[i] {
+                        WIN => (2, s.dtz[i] as i32),
+                        LOSS => (-2, -(s.dtz[i] as i32)),
+                        _ => (0, 0),
+                    };
+                    // Zeroing best moves have DTZ 1 for the winner
+                    let d = if s.zeroing_win[i] { 1 } else { d };
+                    writeln!(out, "{fen} {side} - - 0 1;{w};{d}").unwrap();
+                }
+            }
+        }
+    }
+}
+
+/// Every 101st legal KQvKR position, in the same format
+fn dump_four(path: &str, s: &SolvedFour) {
+    let mut out = std::io::BufWriter::new(std::fs::File::create(path).unwrap());
+    for i in (0..FOUR_SIZE).step_by(101) {
+        let p = Four::from_index(i);
+        if !p.legal() {
+            continue;
+        }
+        let d = if s.zeroing[i] { 1 } else { s.dtz[i] as i32 };
+        let (w, d) = match s.wdl[i] {
+            WIN => (2, d),
+            LOSS => (-2, -d),
+            _ => (0, 0),
+        };
+        writeln!(out, "{};{w};{d}", fen_four(p)).unwrap();
+    }
+}
+
+fn fen_four(p: Four) -> String {
+    let mut board = [None; 64];
+    for (sq, c) in [(p.wk, 'K'), (p.bk, 'k'), (p.q, 'Q'), (p.r, 'r')] {
+        board[sq] = Some(c);
+    }
+    let mut fen = String::new();
+    for r in (0..8).rev() {
+        let mut empty = 0;
+        for f in 0..8 {
+            match board[r * 8 + f] {
+                Some(c) => {
+                    if empty > 0 {
+                        fen.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    fen.push(c);
+                }
+                None => empty += 1,
+            }
+        }
+        if empty > 0 {
+            fen.push_str(&empty.to_string());
+        }
+        if r > 0 {
+            fen.push('/');
+        }
+    }
+    let side = if p.stm == WHITE { 'w' } else { 'b' };
+    format!("{fen} {side} - - 0 1")
+}